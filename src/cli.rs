@@ -0,0 +1,93 @@
+use anyhow::Context;
+use chrono::Duration;
+
+use crate::{
+    jobs::{
+        models::quiet_windows::QuietWindows,
+        utils::action_utils::{ActionFilter, ActionUtils},
+    },
+    logger::enums::category::Category,
+    setup::Setup,
+    utils::date_utils::DateUtils,
+};
+
+pub struct Cli;
+
+impl Cli {
+    /**
+     * Run a command instead of the jobs, returns false if args contain no known command
+     */
+    pub fn run(args: &[String]) -> Result<bool, anyhow::Error> {
+        match args.first().map(|arg| arg.as_str()) {
+            Some("actions") => {
+                Cli::print_actions(&args[1..])?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /**
+     * Print recorded actions
+     * Usage: actions [--since YYYY-MM-DD] [--until YYYY-MM-DD] [--job JOB] [--tracker DOMAIN], dates are in the configured timezone and until is inclusive
+     */
+    fn print_actions(args: &[String]) -> Result<(), anyhow::Error> {
+        let mut action_filter = ActionFilter::default();
+        let mut args_iter = args.iter();
+        while let Some(arg) = args_iter.next() {
+            let Some(value) = args_iter.next() else {
+                anyhow::bail!("Missing value for {}", arg);
+            };
+            match arg.as_str() {
+                "--since" => {
+                    action_filter.since = Some(DateUtils::parse_local_date_from_str(value).context(format!("Failed to parse --since date: {}", value))?);
+                }
+                "--until" => {
+                    let until_date = DateUtils::parse_local_date_from_str(value).context(format!("Failed to parse --until date: {}", value))?;
+                    action_filter.until = Some(until_date + Duration::days(1));
+                }
+                "--job" => action_filter.job = Some(value.to_string()),
+                "--tracker" => action_filter.tracker = Some(value.to_string()),
+                _ => anyhow::bail!("Unknown argument: {}", arg),
+            }
+        }
+
+        let db_manager = Setup::setup_cli()?;
+        let mut action_utils = ActionUtils::new(db_manager, Category::Setup, QuietWindows::default());
+        let action_records = action_utils.get_actions(&action_filter).context("Failed to get actions")?;
+
+        let mut bytes_reclaimed: i64 = 0;
+        for action_record in &action_records {
+            let result_str = match action_record.success() {
+                true => String::from("ok"),
+                false => format!("failed ({})", action_record.error()),
+            };
+            let cross_seeds_str = match action_record.kept_for_cross_seeds() {
+                true => ", files kept for cross-seeds",
+                false => "",
+            };
+            println!(
+                "{} {} {} {} [{}] rule {}, {:.2}GB{}: ({}) {}",
+                DateUtils::convert_utc_datetime_to_local_string(action_record.created_at()),
+                action_record.job(),
+                action_record.action(),
+                result_str,
+                action_record.tracker(),
+                action_record.rule(),
+                action_record.bytes_reclaimed() as f64 / 1000.0 / 1000.0 / 1000.0,
+                cross_seeds_str,
+                action_record.hash(),
+                action_record.name()
+            );
+            for path in action_record.paths() {
+                println!("  -> {}", path);
+            }
+            if action_record.success() {
+                bytes_reclaimed += action_record.bytes_reclaimed();
+            }
+        }
+        println!("{} actions, {:.2}GB reclaimed", action_records.len(), bytes_reclaimed as f64 / 1000.0 / 1000.0 / 1000.0);
+
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{jobs::enums::strike_policy_type::StrikePolicyType, torrent_clients::enums::torrent_state::TorrentState};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Notification {
@@ -44,20 +48,552 @@ impl TorrentClient {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Paths {
+    link_paths: Vec<String>,
+    library_paths: Vec<String>,
+}
+
+impl Paths {
+    /**
+     * Auxiliary dirs (e.g. cross-seed linkDir) whose hardlinks count as known like the torrent folder
+     */
+    pub fn link_paths(&self) -> &Vec<String> {
+        &self.link_paths
+    }
+    /**
+     * Media library roots, if set a torrent only counts as linked if its files are found in one of them
+     */
+    pub fn library_paths(&self) -> &Vec<String> {
+        &self.library_paths
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Time {
+    timezone: String,
+    day_start_hour: u32,
+}
+
+impl Time {
+    /**
+     * IANA timezone (e.g. Europe/Berlin) strike days are counted in, empty for the system timezone
+     */
+    pub fn timezone(&self) -> &str {
+        &self.timezone
+    }
+    /**
+     * Hour (0-23) a strike day starts at
+     */
+    pub fn day_start_hour(&self) -> u32 {
+        self.day_start_hour
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Database {
+    path: String,
+    busy_timeout_ms: u64,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self {
+            path: String::from("/config/database.db"),
+            busy_timeout_ms: 5000,
+        }
+    }
+}
+
+impl Database {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /**
+     * How long a write waits for a lock held by another connection (e.g. a backup tool) before failing
+     */
+    pub fn busy_timeout_ms(&self) -> u64 {
+        self.busy_timeout_ms
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct HitAndRunPolicy {
+    min_seeding_days: f64,
+    min_ratio: f64,
+}
+
+impl HitAndRunPolicy {
+    /**
+     * Seeding days after which the requirement is met, 0 to ignore
+     */
+    pub fn min_seeding_days(&self) -> f64 {
+        self.min_seeding_days
+    }
+    /**
+     * Ratio after which the requirement is met, 0 to ignore
+     */
+    pub fn min_ratio(&self) -> f64 {
+        self.min_ratio
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Quota {
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    tracker: String,
+    max_size_gb: f64,
+}
+
+impl Quota {
+    /**
+     * Only count torrents of this category, empty for all
+     */
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+    /**
+     * Only count torrents of this tracker domain, empty for all
+     */
+    pub fn tracker(&self) -> &str {
+        &self.tracker
+    }
+    pub fn max_size_gb(&self) -> f64 {
+        self.max_size_gb
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetentionWeights {
+    age_days: f64,
+    ratio: f64,
+    seeders: f64,
+    size_gb: f64,
+    linked: f64,
+}
+
+impl Default for RetentionWeights {
+    fn default() -> Self {
+        Self {
+            age_days: 0.0,
+            ratio: 1.0,
+            seeders: -1.0,
+            size_gb: -0.01,
+            linked: 100.0,
+        }
+    }
+}
+
+impl RetentionWeights {
+    pub fn age_days(&self) -> f64 {
+        self.age_days
+    }
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+    pub fn seeders(&self) -> f64 {
+        self.seeders
+    }
+    pub fn size_gb(&self) -> f64 {
+        self.size_gb
+    }
+    pub fn linked(&self) -> f64 {
+        self.linked
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Retention {
+    enabled: bool,
+    action: String,
+    quotas: Vec<Quota>,
+    weights: RetentionWeights,
+    tracker_weights: HashMap<String, f64>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            action: String::from("test"),
+            quotas: Vec::new(),
+            weights: RetentionWeights::default(),
+            tracker_weights: HashMap::new(),
+        }
+    }
+}
+
+impl Retention {
+    pub fn enabled(&self) -> &bool {
+        &self.enabled
+    }
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+    pub fn quotas(&self) -> &Vec<Quota> {
+        &self.quotas
+    }
+    pub fn weights(&self) -> &RetentionWeights {
+        &self.weights
+    }
+    /**
+     * Score added to torrents of the tracker domain
+     */
+    pub fn tracker_weights(&self) -> &HashMap<String, f64> {
+        &self.tracker_weights
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RuleOverride {
+    name: String,
+    tracker: String,
+    category: String,
+    tag: String,
+    path: String,
+    min_seeding_days: Option<i32>,
+    required_strikes: Option<i32>,
+    min_strike_days: Option<i32>,
+    action: Option<String>,
+    protection_tag: Option<String>,
+    protected: Option<bool>,
+}
+
+impl RuleOverride {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /**
+     * Match torrents of this tracker domain (subdomains included), empty to ignore
+     */
+    pub fn tracker(&self) -> &str {
+        &self.tracker
+    }
+    /**
+     * Match torrents of this category, empty to ignore
+     */
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+    /**
+     * Match torrents with this tag, empty to ignore
+     */
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+    /**
+     * Match torrents or orphaned files inside this path, empty to ignore
+     */
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    pub fn min_seeding_days(&self) -> Option<i32> {
+        self.min_seeding_days
+    }
+    pub fn required_strikes(&self) -> Option<i32> {
+        self.required_strikes
+    }
+    pub fn min_strike_days(&self) -> Option<i32> {
+        self.min_strike_days
+    }
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+    pub fn protection_tag(&self) -> Option<&str> {
+        self.protection_tag.as_deref()
+    }
+    /**
+     * Never act on matching torrents or files
+     */
+    pub fn protected(&self) -> Option<bool> {
+        self.protected
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct QuietHours {
+    start: String,
+    end: String,
+    days: Vec<String>,
+    mode: String,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            start: String::from("01:00"),
+            end: String::from("07:00"),
+            days: Vec::new(),
+            mode: String::from("defer_actions"),
+        }
+    }
+}
+
+impl QuietHours {
+    /**
+     * HH:MM in the configured timezone, a window with an end before its start ends on the next day
+     */
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+    pub fn end(&self) -> &str {
+        &self.end
+    }
+    /**
+     * Days the window starts on (mon-sun), empty for every day
+     */
+    pub fn days(&self) -> &Vec<String> {
+        &self.days
+    }
+    /**
+     * defer_actions (scan and strike, but queue actions until the window ends) or skip_run (don't start the job)
+     */
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Schedule {
+    cron: String,
+    jitter_minutes: u32,
+    run_on_startup: bool,
+}
+
+impl Schedule {
+    /**
+     * Cron expression (minute hour day-of-month month day-of-week) in the configured timezone, replaces interval_hours if set
+     */
+    pub fn cron(&self) -> &str {
+        &self.cron
+    }
+    /**
+     * Up to this many minutes are randomly added to every computed run time
+     */
+    pub fn jitter_minutes(&self) -> u32 {
+        self.jitter_minutes
+    }
+    /**
+     * Run once directly on startup, then follow the schedule
+     */
+    pub fn run_on_startup(&self) -> bool {
+        self.run_on_startup
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Pipeline {
+    name: String,
+    jobs: Vec<String>,
+    interval_hours: i32,
+    schedule: Schedule,
+    quiet_hours: Vec<QuietHours>,
+    on_failure: String,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            jobs: Vec::new(),
+            interval_hours: 24,
+            schedule: Schedule::default(),
+            quiet_hours: Vec::new(),
+            on_failure: String::from("abort"),
+        }
+    }
+}
+
+impl Pipeline {
+    /**
+     * Name the pipeline is logged and recorded in the job history as, can't be the name of a job
+     */
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /**
+     * Job names in the order they run, jobs in a pipeline ignore their own interval_hours and schedule
+     */
+    pub fn jobs(&self) -> &Vec<String> {
+        &self.jobs
+    }
+    pub fn interval_hours(&self) -> i32 {
+        self.interval_hours
+    }
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+    /**
+     * Added to the global quiet_hours, skip_run windows postpone the whole pipeline
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
+    /**
+     * abort (skip the remaining jobs) or continue if a job fails
+     */
+    pub fn on_failure(&self) -> &str {
+        &self.on_failure
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StrikePolicy {
+    policy: String,
+    window_hours: i64,
+    continuous_hours: i64,
+}
+
+impl Default for StrikePolicy {
+    fn default() -> Self {
+        Self {
+            policy: String::from("consecutive_days"),
+            window_hours: 168,
+            continuous_hours: 72,
+        }
+    }
+}
+
+/**
+ * Hours that the policy doesn't use are written with their defaults
+ */
+impl From<StrikePolicyType> for StrikePolicy {
+    fn from(strike_policy_type: StrikePolicyType) -> Self {
+        let default_strike_policy = StrikePolicy::default();
+        match strike_policy_type {
+            StrikePolicyType::ConsecutiveDays => Self {
+                policy: String::from("consecutive_days"),
+                ..default_strike_policy
+            },
+            StrikePolicyType::Tolerant => Self {
+                policy: String::from("tolerant"),
+                ..default_strike_policy
+            },
+            StrikePolicyType::SlidingWindow { window_hours } => Self {
+                policy: String::from("sliding_window"),
+                window_hours,
+                ..default_strike_policy
+            },
+            StrikePolicyType::Continuous { min_hours } => Self {
+                policy: String::from("continuous"),
+                continuous_hours: min_hours,
+                ..default_strike_policy
+            },
+        }
+    }
+}
+
+impl StrikePolicy {
+    /**
+     * consecutive_days, tolerant, sliding_window or continuous
+     */
+    pub fn policy(&self) -> &str {
+        &self.policy
+    }
+    /**
+     * Only used by sliding_window
+     */
+    pub fn window_hours(&self) -> i64 {
+        self.window_hours
+    }
+    /**
+     * Only used by continuous
+     */
+    pub fn continuous_hours(&self) -> i64 {
+        self.continuous_hours
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct StateFilter {
+    #[serde(deserialize_with = "deserialize_config_states")]
+    include: Vec<TorrentState>,
+    #[serde(deserialize_with = "deserialize_config_states")]
+    exclude: Vec<TorrentState>,
+}
+
+/**
+ * States of a state filter, the config fails to load on a state qBittorrent doesn't have
+ */
+fn deserialize_config_states<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<TorrentState>, D::Error> {
+    let state_strs: Vec<String> = Vec::deserialize(deserializer)?;
+    state_strs.iter().map(|state_str| TorrentState::from_config_str(state_str).map_err(serde::de::Error::custom)).collect()
+}
+
+impl StateFilter {
+    /**
+     * Only torrents in one of these states are handled, empty for every state
+     */
+    pub fn include(&self) -> &Vec<TorrentState> {
+        &self.include
+    }
+    /**
+     * Torrents in one of these states are never handled
+     */
+    pub fn exclude(&self) -> &Vec<TorrentState> {
+        &self.exclude
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct HandleUnlinked {
     interval_hours: i32,
+    #[serde(default)]
+    schedule: Schedule,
+    #[serde(default)]
+    quiet_hours: Vec<QuietHours>,
     min_seeding_days: i32,
     min_strike_days: i32,
     required_strikes: i32,
+    #[serde(default)]
+    strike_policy: StrikePolicyType,
+    #[serde(default)]
+    min_linked_percent: f64,
+    #[serde(default)]
+    min_swarm_seeders: i64,
+    #[serde(default)]
+    protect_upspeed_kbps: f64,
+    #[serde(default)]
+    protect_recent_upload_gb: f64,
+    #[serde(default = "default_recent_upload_days")]
+    recent_upload_days: i64,
     protection_tag: String,
     action: String,
+    #[serde(default)]
+    retention: Retention,
+    #[serde(default)]
+    state_filter: StateFilter,
+    #[serde(default)]
+    overrides: Vec<RuleOverride>,
 }
 
 impl HandleUnlinked {
     pub fn interval_hours(&self) -> i32 {
         self.interval_hours
     }
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+    /**
+     * Added to the global quiet_hours
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
     pub fn min_seeding_days(&self) -> i32 {
         self.min_seeding_days
     }
@@ -67,36 +603,327 @@ impl HandleUnlinked {
     pub fn required_strikes(&self) -> i32 {
         self.required_strikes
     }
+    pub fn strike_policy(&self) -> &StrikePolicyType {
+        &self.strike_policy
+    }
+    /**
+     * Torrents with less than this percentage of their bytes linked are treated as unlinked
+     */
+    pub fn min_linked_percent(&self) -> f64 {
+        self.min_linked_percent
+    }
+    /**
+     * Keep torrents with fewer seeders than this in the swarm, 0 to disable
+     */
+    pub fn min_swarm_seeders(&self) -> i64 {
+        self.min_swarm_seeders
+    }
+    /**
+     * Keep torrents that are uploading faster than this right now, 0 to disable
+     */
+    pub fn protect_upspeed_kbps(&self) -> f64 {
+        self.protect_upspeed_kbps
+    }
+    /**
+     * Keep torrents that uploaded more than this in the last recent_upload_days, 0 to disable
+     */
+    pub fn protect_recent_upload_gb(&self) -> f64 {
+        self.protect_recent_upload_gb
+    }
+    pub fn recent_upload_days(&self) -> i64 {
+        self.recent_upload_days
+    }
     pub fn protection_tag(&self) -> &str {
         &self.protection_tag
     }
     pub fn action(&self) -> &str {
         &self.action
     }
+    pub fn retention(&self) -> &Retention {
+        &self.retention
+    }
+    pub fn state_filter(&self) -> &StateFilter {
+        &self.state_filter
+    }
+    /**
+     * Checked in order, the first override matching a torrent is applied
+     */
+    pub fn overrides(&self) -> &Vec<RuleOverride> {
+        &self.overrides
+    }
+}
+
+/**
+ * Same as Config::default, so configs written before recent_upload_days existed get the protection too
+ */
+fn default_recent_upload_days() -> i64 {
+    7
+}
+
+static DEFAULT_UNREGISTERED_MESSAGES: [&str; 33] = [
+    "complete season uploaded",
+    "dead",
+    "dupe",
+    "i'm sorry dave, i can't do that",
+    "infohash not found",
+    "internal available",
+    "not exist",
+    "not registered",
+    "nuked",
+    "pack is available",
+    "packs are available",
+    "problem with description",
+    "problem with file",
+    "problem with pack",
+    "retitled",
+    "season pack",
+    "specifically banned",
+    "torrent does not exist",
+    "torrent existiert nicht",
+    "torrent has been deleted",
+    "torrent has been nuked",
+    "torrent introuvable",
+    "torrent is not authorized for use on this tracker",
+    "torrent is not found",
+    "torrent nicht gefunden",
+    "tracker nicht registriert",
+    "torrent not found",
+    "trump",
+    "unknown",
+    "unregistered",
+    "não registrado",
+    "upgraded",
+    "uploaded",
+];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MessageRule {
+    pattern: String,
+    #[serde(default)]
+    regex: bool,
+}
+
+impl MessageRule {
+    /**
+     * Matched case-insensitive against the tracker message
+     */
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+    /**
+     * Use pattern as regex instead of substring
+     */
+    pub fn regex(&self) -> bool {
+        self.regex
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TrackerMessageRules {
+    add: Vec<MessageRule>,
+    remove: Vec<String>,
+}
+
+impl TrackerMessageRules {
+    /**
+     * Rules that only apply to this tracker
+     */
+    pub fn add(&self) -> &Vec<MessageRule> {
+        &self.add
+    }
+    /**
+     * Patterns of global rules that don't apply to this tracker
+     */
+    pub fn remove(&self) -> &Vec<String> {
+        &self.remove
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct UnregisteredMessages {
+    rules: Vec<MessageRule>,
+    trackers: HashMap<String, TrackerMessageRules>,
+    report_unknown: bool,
+}
+
+impl Default for UnregisteredMessages {
+    fn default() -> Self {
+        Self {
+            rules: DEFAULT_UNREGISTERED_MESSAGES
+                .iter()
+                .map(|message| MessageRule {
+                    pattern: message.to_string(),
+                    regex: false,
+                })
+                .collect(),
+            trackers: HashMap::new(),
+            report_unknown: true,
+        }
+    }
+}
+
+impl UnregisteredMessages {
+    /**
+     * Rules for every tracker, defaults to the built-in list
+     */
+    pub fn rules(&self) -> &Vec<MessageRule> {
+        &self.rules
+    }
+    /**
+     * Rules to add or remove per tracker domain (subdomains included)
+     */
+    pub fn trackers(&self) -> &HashMap<String, TrackerMessageRules> {
+        &self.trackers
+    }
+    /**
+     * Notify about new messages of non working trackers that match no rule
+     */
+    pub fn report_unknown(&self) -> &bool {
+        &self.report_unknown
+    }
+}
+
+static DEFAULT_OUTAGE_ERROR_PATTERNS: [&str; 14] = [
+    "bad gateway",
+    "connection refused",
+    "connection reset",
+    "could not resolve",
+    "gateway timeout",
+    "host not found",
+    "http error",
+    "internal server error",
+    "network is unreachable",
+    "service unavailable",
+    "ssl error",
+    "timed out",
+    "timeout",
+    "too many requests",
+];
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OutageDetection {
+    enabled: bool,
+    max_failure_percent: f64,
+    min_torrents: i64,
+    error_patterns: Vec<String>,
+}
+
+impl Default for OutageDetection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_failure_percent: 50.0,
+            min_torrents: 5,
+            error_patterns: DEFAULT_OUTAGE_ERROR_PATTERNS.iter().map(|pattern| pattern.to_string()).collect(),
+        }
+    }
+}
+
+impl OutageDetection {
+    pub fn enabled(&self) -> &bool {
+        &self.enabled
+    }
+    /**
+     * A tracker is down if more than this percentage of its contacted torrents are not working
+     */
+    pub fn max_failure_percent(&self) -> f64 {
+        self.max_failure_percent
+    }
+    /**
+     * Minimum contacted torrents of a tracker before max_failure_percent is checked
+     */
+    pub fn min_torrents(&self) -> i64 {
+        self.min_torrents
+    }
+    /**
+     * A tracker is down if any of its messages contains one of these (case-insensitive)
+     */
+    pub fn error_patterns(&self) -> &Vec<String> {
+        &self.error_patterns
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Reannounce {
+    enabled: bool,
+    delay_secs: u64,
+}
+
+impl Default for Reannounce {
+    fn default() -> Self {
+        Self { enabled: true, delay_secs: 30 }
+    }
+}
+
+impl Reannounce {
+    /**
+     * Reannounce torrents that meet criteria and only strike them if they are still unregistered afterwards
+     */
+    pub fn enabled(&self) -> &bool {
+        &self.enabled
+    }
+    /**
+     * Seconds to wait for the trackers to answer before fetching them again
+     */
+    pub fn delay_secs(&self) -> u64 {
+        self.delay_secs
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct HandleUnregistered {
     interval_hours: i32,
+    #[serde(default)]
+    schedule: Schedule,
+    #[serde(default)]
+    quiet_hours: Vec<QuietHours>,
     min_strike_days: i32,
     required_strikes: i32,
+    #[serde(default)]
+    strike_policy: StrikePolicyType,
     protection_tag: String,
     ignore_dht: bool,
     ignore_pex: bool,
     ignore_lsd: bool,
+    #[serde(default)]
+    unregistered_messages: UnregisteredMessages,
+    #[serde(default)]
+    outage_detection: OutageDetection,
+    #[serde(default)]
+    reannounce: Reannounce,
+    #[serde(default)]
+    state_filter: StateFilter,
     action: String,
+    #[serde(default)]
+    overrides: Vec<RuleOverride>,
 }
 
 impl HandleUnregistered {
     pub fn interval_hours(&self) -> i32 {
         self.interval_hours
     }
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+    /**
+     * Added to the global quiet_hours
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
     pub fn min_strike_days(&self) -> i32 {
         self.min_strike_days
     }
     pub fn required_strikes(&self) -> i32 {
         self.required_strikes
     }
+    pub fn strike_policy(&self) -> &StrikePolicyType {
+        &self.strike_policy
+    }
     pub fn protection_tag(&self) -> &str {
         &self.protection_tag
     }
@@ -109,51 +936,269 @@ impl HandleUnregistered {
     pub fn ignore_lsd(&self) -> &bool {
         &self.ignore_lsd
     }
+    pub fn unregistered_messages(&self) -> &UnregisteredMessages {
+        &self.unregistered_messages
+    }
+    pub fn outage_detection(&self) -> &OutageDetection {
+        &self.outage_detection
+    }
+    pub fn reannounce(&self) -> &Reannounce {
+        &self.reannounce
+    }
+    pub fn state_filter(&self) -> &StateFilter {
+        &self.state_filter
+    }
     pub fn action(&self) -> &str {
         &self.action
     }
+    /**
+     * Checked in order, the first override matching a torrent is applied
+     */
+    pub fn overrides(&self) -> &Vec<RuleOverride> {
+        &self.overrides
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct HandleOrphaned {
     interval_hours: i32,
+    #[serde(default)]
+    schedule: Schedule,
+    #[serde(default)]
+    quiet_hours: Vec<QuietHours>,
     min_strike_days: i32,
     required_strikes: i32,
+    #[serde(default)]
+    strike_policy: StrikePolicyType,
     protect_external_hardlinks: bool,
     action: String,
+    #[serde(default)]
+    overrides: Vec<RuleOverride>,
 }
 
 impl HandleOrphaned {
     pub fn interval_hours(&self) -> i32 {
         self.interval_hours
     }
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+    /**
+     * Added to the global quiet_hours
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
     pub fn min_strike_days(&self) -> i32 {
         self.min_strike_days
     }
     pub fn required_strikes(&self) -> i32 {
         self.required_strikes
     }
+    pub fn strike_policy(&self) -> &StrikePolicyType {
+        &self.strike_policy
+    }
     pub fn protect_external_hardlinks(&self) -> &bool {
         &self.protect_external_hardlinks
     }
     pub fn action(&self) -> &str {
         &self.action
     }
+    /**
+     * Checked in order, the first override matching an orphaned path is applied, only path can match here
+     */
+    pub fn overrides(&self) -> &Vec<RuleOverride> {
+        &self.overrides
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AnnounceUrlEdit {
+    tracker: String,
+    from: String,
+    to: String,
+}
+
+impl AnnounceUrlEdit {
+    /**
+     * Tracker domain, subdomains match as well
+     */
+    pub fn tracker(&self) -> &str {
+        &self.tracker
+    }
+    /**
+     * Part of the announce url that is replaced, e.g. the old passkey or domain
+     */
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HandleTrackerErrors {
+    interval_hours: i32,
+    schedule: Schedule,
+    quiet_hours: Vec<QuietHours>,
+    min_not_working_days: i32,
+    edit_announce_urls: bool,
+    announce_url_edits: Vec<AnnounceUrlEdit>,
+    state_filter: StateFilter,
+}
+
+impl Default for HandleTrackerErrors {
+    fn default() -> Self {
+        Self {
+            interval_hours: -1,
+            schedule: Schedule::default(),
+            quiet_hours: Vec::new(),
+            min_not_working_days: 14,
+            edit_announce_urls: false,
+            announce_url_edits: Vec::new(),
+            state_filter: StateFilter::default(),
+        }
+    }
+}
+
+impl HandleTrackerErrors {
+    pub fn interval_hours(&self) -> i32 {
+        self.interval_hours
+    }
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+    /**
+     * Added to the global quiet_hours
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
+    /**
+     * Days a tracker has to be not working before it is reported
+     */
+    pub fn min_not_working_days(&self) -> i32 {
+        self.min_not_working_days
+    }
+    /**
+     * Apply announce_url_edits, otherwise they are only logged
+     */
+    pub fn edit_announce_urls(&self) -> &bool {
+        &self.edit_announce_urls
+    }
+    pub fn announce_url_edits(&self) -> &Vec<AnnounceUrlEdit> {
+        &self.announce_url_edits
+    }
+    pub fn state_filter(&self) -> &StateFilter {
+        &self.state_filter
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HandleErrored {
+    interval_hours: i32,
+    schedule: Schedule,
+    quiet_hours: Vec<QuietHours>,
+    min_strike_days: i32,
+    required_strikes: i32,
+    strike_policy: StrikePolicyType,
+    protection_tag: String,
+    action: String,
+    state_filter: StateFilter,
+    overrides: Vec<RuleOverride>,
+}
+
+impl Default for HandleErrored {
+    fn default() -> Self {
+        Self {
+            interval_hours: 5,
+            schedule: Schedule::default(),
+            quiet_hours: Vec::new(),
+            min_strike_days: 1,
+            required_strikes: 2,
+            strike_policy: StrikePolicyType::default(),
+            protection_tag: String::from("protected-errored"),
+            action: String::from("test"),
+            state_filter: StateFilter::default(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl HandleErrored {
+    pub fn interval_hours(&self) -> i32 {
+        self.interval_hours
+    }
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+    /**
+     * Added to the global quiet_hours
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
+    pub fn min_strike_days(&self) -> i32 {
+        self.min_strike_days
+    }
+    pub fn required_strikes(&self) -> i32 {
+        self.required_strikes
+    }
+    pub fn strike_policy(&self) -> &StrikePolicyType {
+        &self.strike_policy
+    }
+    pub fn protection_tag(&self) -> &str {
+        &self.protection_tag
+    }
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+    pub fn state_filter(&self) -> &StateFilter {
+        &self.state_filter
+    }
+    /**
+     * Checked in order, the first override matching a torrent is applied
+     */
+    pub fn overrides(&self) -> &Vec<RuleOverride> {
+        &self.overrides
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct HealthCheckFiles {
     interval_hours: i32,
+    #[serde(default)]
+    schedule: Schedule,
+    #[serde(default)]
+    quiet_hours: Vec<QuietHours>,
     action: String,
+    #[serde(default)]
+    state_filter: StateFilter,
 }
 
 impl HealthCheckFiles {
     pub fn interval_hours(&self) -> i32 {
         self.interval_hours
     }
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+    /**
+     * Added to the global quiet_hours
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
     pub fn action(&self) -> &str {
         &self.action
     }
+    pub fn state_filter(&self) -> &StateFilter {
+        &self.state_filter
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -161,6 +1206,10 @@ pub struct Jobs {
     handle_unlinked: HandleUnlinked,
     handle_unregistered: HandleUnregistered,
     handle_orphaned: HandleOrphaned,
+    #[serde(default)]
+    handle_tracker_errors: HandleTrackerErrors,
+    #[serde(default)]
+    handle_errored: HandleErrored,
     health_check_files: HealthCheckFiles,
 }
 
@@ -174,6 +1223,12 @@ impl Jobs {
     pub fn handle_orphaned(&self) -> &HandleOrphaned {
         &self.handle_orphaned
     }
+    pub fn handle_tracker_errors(&self) -> &HandleTrackerErrors {
+        &self.handle_tracker_errors
+    }
+    pub fn handle_errored(&self) -> &HandleErrored {
+        &self.handle_errored
+    }
     pub fn health_check_files(&self) -> &HealthCheckFiles {
         &self.health_check_files
     }
@@ -183,6 +1238,18 @@ impl Jobs {
 pub struct Config {
     notification: Notification,
     torrent_client: TorrentClient,
+    #[serde(default)]
+    paths: Paths,
+    #[serde(default)]
+    hit_and_run: HashMap<String, HitAndRunPolicy>,
+    #[serde(default)]
+    time: Time,
+    #[serde(default)]
+    database: Database,
+    #[serde(default)]
+    quiet_hours: Vec<QuietHours>,
+    #[serde(default)]
+    pipelines: Vec<Pipeline>,
     jobs: Jobs,
 }
 
@@ -200,35 +1267,72 @@ impl Config {
                 username: String::from(""),
                 password: String::from(""),
             },
+            paths: Paths {
+                link_paths: Vec::new(),
+                library_paths: Vec::new(),
+            },
+            hit_and_run: HashMap::new(),
+            time: Time::default(),
+            database: Database::default(),
+            quiet_hours: Vec::new(),
+            pipelines: Vec::new(),
             jobs: Jobs {
                 handle_unlinked: HandleUnlinked {
                     interval_hours: 13,
+                    schedule: Schedule::default(),
+                    quiet_hours: Vec::new(),
                     min_seeding_days: 20,
                     min_strike_days: 3,
                     required_strikes: 3,
+                    strike_policy: StrikePolicyType::default(),
+                    min_linked_percent: 0.0,
+                    min_swarm_seeders: 0,
+                    protect_upspeed_kbps: 0.0,
+                    protect_recent_upload_gb: 0.0,
+                    recent_upload_days: 7,
                     protection_tag: String::from("protected-unlinked"),
                     action: String::from("test"),
+                    retention: Retention::default(),
+                    state_filter: StateFilter::default(),
+                    overrides: Vec::new(),
                 },
                 handle_unregistered: HandleUnregistered {
                     interval_hours: 7,
+                    schedule: Schedule::default(),
+                    quiet_hours: Vec::new(),
                     min_strike_days: 1,
                     required_strikes: 2,
+                    strike_policy: StrikePolicyType::default(),
                     ignore_dht: true,
                     ignore_pex: true,
                     ignore_lsd: true,
+                    unregistered_messages: UnregisteredMessages::default(),
+                    outage_detection: OutageDetection::default(),
+                    reannounce: Reannounce::default(),
+                    state_filter: StateFilter::default(),
                     protection_tag: String::from("protected-unregistered"),
                     action: String::from("test"),
+                    overrides: Vec::new(),
                 },
                 handle_orphaned: HandleOrphaned {
                     interval_hours: 11,
+                    schedule: Schedule::default(),
+                    quiet_hours: Vec::new(),
                     min_strike_days: 3,
                     required_strikes: 3,
+                    strike_policy: StrikePolicyType::default(),
                     protect_external_hardlinks: true,
                     action: String::from("test"),
+                    overrides: Vec::new(),
                 },
+                handle_tracker_errors: HandleTrackerErrors::default(),
+                handle_errored: HandleErrored::default(),
                 health_check_files: HealthCheckFiles {
                     interval_hours: 17,
+                    schedule: Schedule::default(),
+                    quiet_hours: Vec::new(),
                     action: String::from("test"),
+                    state_filter: StateFilter::default(),
                 },
             },
         }
@@ -240,7 +1344,62 @@ impl Config {
     pub fn torrent_client(&self) -> &TorrentClient {
         &self.torrent_client
     }
+    pub fn paths(&self) -> &Paths {
+        &self.paths
+    }
+    /**
+     * Hit-and-run requirements per tracker domain, no job stops or deletes a torrent before they are met
+     */
+    pub fn hit_and_run(&self) -> &HashMap<String, HitAndRunPolicy> {
+        &self.hit_and_run
+    }
+    pub fn time(&self) -> &Time {
+        &self.time
+    }
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+    /**
+     * Windows that apply to every job
+     */
+    pub fn quiet_hours(&self) -> &Vec<QuietHours> {
+        &self.quiet_hours
+    }
+    /**
+     * Jobs that run after each other as a single scheduled unit
+     */
+    pub fn pipelines(&self) -> &Vec<Pipeline> {
+        &self.pipelines
+    }
     pub fn jobs(&self) -> &Jobs {
         &self.jobs
     }
 }
+
+#[cfg(test)]
+impl Config {
+    /**
+     * Config for unit tests, the given fields are merged into the default config
+     */
+    pub fn from_test_fields(fields: serde_json::Value) -> Self {
+        let mut config_value = serde_json::to_value(Config::default()).unwrap();
+        Config::merge_test_fields(&mut config_value, fields);
+        serde_json::from_value(config_value).unwrap()
+    }
+
+    fn merge_test_fields(value: &mut serde_json::Value, fields: serde_json::Value) {
+        match (value.as_object_mut(), fields) {
+            (Some(value_fields), serde_json::Value::Object(fields)) => {
+                for (key, field) in fields {
+                    match value_fields.get_mut(&key) {
+                        Some(value_field) => Config::merge_test_fields(value_field, field),
+                        None => {
+                            value_fields.insert(key, field);
+                        }
+                    }
+                }
+            }
+            (_, fields) => *value = fields,
+        }
+    }
+}
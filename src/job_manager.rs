@@ -1,126 +1,294 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 use anyhow::Context;
-use chrono::{NaiveDateTime, TimeDelta};
+use chrono::{DateTime, TimeDelta, Utc};
 use reqwest::Url;
 use rusqlite::{OptionalExtension, params};
 use tokio::{sync::Mutex, time::sleep};
 
 use crate::{
-    config::Config,
+    config::{Config, Pipeline},
     error, info,
-    jobs::{handle_orphaned::runner::HandleOrphaned, handle_unlinked::runner::HandleUnlinked, handle_unregistered::runner::HandleUnregistered, health_check_files::runner::HealthCheckFiles},
+    jobs::{
+        deferred_actions::runner::DeferredActions,
+        enums::{action_type::ActionType, failure_policy::FailurePolicy, job_run_status::JobRunStatus, schedule_type::ScheduleType},
+        handle_errored::runner::HandleErrored,
+        handle_orphaned::runner::HandleOrphaned,
+        handle_tracker_errors::runner::HandleTrackerErrors,
+        handle_unlinked::runner::HandleUnlinked,
+        handle_unregistered::runner::HandleUnregistered,
+        health_check_files::runner::HealthCheckFiles,
+        models::{job_pipeline::JobPipeline, job_run_stats::JobRunStats, job_schedule::JobSchedule, pipeline_context::PipelineContext, quiet_windows::QuietWindows},
+        utils::action_utils::{ActionFilter, ActionUtils},
+    },
     logger::enums::category::Category,
     torrent_clients::torrent_manager::TorrentManager,
-    utils::{date_utils::DateUtils, db_manager::Session, discord_webhook_utils::DiscordWebhookUtils},
+    utils::{date_utils::DateUtils, db_manager::DbManager, discord_webhook_utils::DiscordWebhookUtils},
     warn,
 };
 
+/**
+ * Failed or interrupted runs are retried after this (or the interval if it is shorter) instead of waiting for the whole interval
+ */
+const FAILED_RUN_RETRY_MINUTES: i64 = 60;
+
+/**
+ * How often the queue of deferred actions is checked for actions whose quiet hours ended
+ */
+const DEFERRED_ACTIONS_CHECK_MINUTES: u64 = 10;
+
+/**
+ * Jobs that can run on their own or as part of a pipeline
+ */
+const JOB_NAMES: &[&str] = &["handle_unlinked", "handle_unregistered", "handle_orphaned", "handle_tracker_errors", "handle_errored", "health_check_files"];
+
+/**
+ * Runs the jobs of a pipeline by their name
+ */
+trait JobRunner {
+    async fn run(&self, job_name: &str, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error>;
+}
+
+struct JobRunners {
+    handle_unlinked: HandleUnlinked,
+    handle_unregistered: HandleUnregistered,
+    handle_orphaned: HandleOrphaned,
+    handle_tracker_errors: HandleTrackerErrors,
+    handle_errored: HandleErrored,
+    health_check_files: HealthCheckFiles,
+}
+
+impl JobRunner for JobRunners {
+    async fn run(&self, job_name: &str, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
+        match job_name {
+            "handle_unlinked" => self.handle_unlinked.run(pipeline_context).await,
+            "handle_unregistered" => self.handle_unregistered.run(pipeline_context).await,
+            "handle_orphaned" => self.handle_orphaned.run(pipeline_context).await,
+            "handle_tracker_errors" => self.handle_tracker_errors.run(pipeline_context).await,
+            "handle_errored" => self.handle_errored.run(pipeline_context).await,
+            "health_check_files" => self.health_check_files.run(pipeline_context).await,
+            _ => anyhow::bail!("Unknown job {}", job_name),
+        }
+    }
+}
+
 pub struct JobManager {
     config: Config,
     torrent_manager: Arc<TorrentManager>,
+    db_manager: DbManager,
     torrents_path: String,
     job_lock: Arc<Mutex<()>>,
 }
 
 impl JobManager {
-    pub fn new(config: Config, torrent_manager: Arc<TorrentManager>, torrents_path: String) -> Self {
+    pub fn new(config: Config, torrent_manager: Arc<TorrentManager>, db_manager: DbManager, torrents_path: String) -> Self {
         Self {
             config,
             torrent_manager,
+            db_manager,
             torrents_path,
             job_lock: Arc::new(Mutex::new(())),
         }
     }
 
-    pub fn setup(&self) {
-        let handle_unlinked = Arc::new(HandleUnlinked::new(self.torrent_manager.clone(), self.config.clone(), self.torrents_path.clone()));
-        let handle_unregistered = Arc::new(HandleUnregistered::new(self.torrent_manager.clone(), self.config.clone()));
-        let handle_orphaned = Arc::new(HandleOrphaned::new(self.torrent_manager.clone(), self.config.clone(), self.torrents_path.clone()));
-        let health_check_files = Arc::new(HealthCheckFiles::new(self.torrent_manager.clone(), self.config.clone()));
+    pub fn setup(&self) -> Result<(), anyhow::Error> {
+        let job_runners = Arc::new(JobRunners {
+            handle_unlinked: HandleUnlinked::new(self.torrent_manager.clone(), self.db_manager.clone(), self.config.clone(), self.torrents_path.clone()),
+            handle_unregistered: HandleUnregistered::new(self.torrent_manager.clone(), self.db_manager.clone(), self.config.clone()),
+            handle_orphaned: HandleOrphaned::new(self.torrent_manager.clone(), self.db_manager.clone(), self.config.clone(), self.torrents_path.clone()),
+            handle_tracker_errors: HandleTrackerErrors::new(self.torrent_manager.clone(), self.db_manager.clone(), self.config.clone()),
+            handle_errored: HandleErrored::new(self.torrent_manager.clone(), self.db_manager.clone(), self.config.clone()),
+            health_check_files: HealthCheckFiles::new(self.torrent_manager.clone(), self.config.clone()),
+        });
+
+        // Runs that were still running when the process stopped will never finish
+        if let Err(e) = JobManager::mark_interrupted_runs(&self.db_manager) {
+            error!(Category::JobManager, "Error while marking interrupted job runs: {:#}", e);
+        }
 
         let discord_webhook_url = Some(self.config.notification().discord_webhook_url()).filter(|s| !s.is_empty()).and_then(|url_str| Url::parse(url_str).ok());
 
-        self.spawn_job(
-            String::from("handle_unlinked"),
-            self.config.jobs().handle_unlinked().interval_hours(),
-            Config::default().jobs().handle_unlinked().interval_hours(),
-            *self.config.notification().on_job_error(),
-            discord_webhook_url.clone(),
-            handle_unlinked.clone(),
-            |handler: Arc<HandleUnlinked>| async move { handler.run().await },
-        );
-
-        self.spawn_job(
-            String::from("handle_unregistered"),
-            self.config.jobs().handle_unregistered().interval_hours(),
-            Config::default().jobs().handle_unregistered().interval_hours(),
-            *self.config.notification().on_job_error(),
-            discord_webhook_url.clone(),
-            handle_unregistered.clone(),
-            |handler: Arc<HandleUnregistered>| async move { handler.run().await },
-        );
-
-        self.spawn_job(
-            String::from("handle_orphaned"),
-            self.config.jobs().handle_orphaned().interval_hours(),
-            Config::default().jobs().handle_orphaned().interval_hours(),
-            *self.config.notification().on_job_error(),
-            discord_webhook_url.clone(),
-            handle_orphaned.clone(),
-            |handler: Arc<HandleOrphaned>| async move { handler.run().await },
-        );
-
-        self.spawn_job(
-            String::from("health_check_files"),
-            self.config.jobs().health_check_files().interval_hours(),
-            Config::default().jobs().health_check_files().interval_hours(),
-            *self.config.notification().on_job_error(),
-            discord_webhook_url.clone(),
-            health_check_files.clone(),
-            |handler: Arc<HealthCheckFiles>| async move { handler.run().await },
-        );
-    }
-
-    fn spawn_job<T, F, Fut>(&self, job_name: String, mut interval_hours: i32, default_interval_hours: i32, notify_on_job_error: bool, discord_webhook_url: Option<Url>, handler: Arc<T>, job_fn: F)
-    where
-        T: Send + Sync + 'static,
-        F: (Fn(Arc<T>) -> Fut) + Send + Sync + 'static,
-        Fut: std::future::Future<Output = Result<(), anyhow::Error>> + Send + 'static,
-    {
-        if interval_hours == -1 {
-            return;
+        // Pipelines
+        let mut pipeline_job_names: HashSet<String> = HashSet::new();
+        for pipeline in self.config.pipelines() {
+            let job_pipeline = JobPipeline::from_config(pipeline, JOB_NAMES).context("Failed to get pipeline")?;
+            let pipeline_schedule = JobSchedule::from_config(
+                pipeline.interval_hours(),
+                Pipeline::default().interval_hours(),
+                pipeline.schedule(),
+                QuietWindows::from_config(self.config.quiet_hours(), pipeline.quiet_hours()).context(format!("Failed to get quiet hours of pipeline {}", pipeline.name()))?,
+            )
+            .context(format!("Failed to get schedule of pipeline {}", pipeline.name()))?;
+            info!(Category::JobManager, "Pipeline {}: {}", pipeline.name(), pipeline.jobs().join(" -> "));
+            pipeline_job_names.extend(pipeline.jobs().iter().cloned());
+            self.spawn_job(job_runners.clone(), job_pipeline, pipeline_schedule, *self.config.notification().on_job_error(), discord_webhook_url.clone());
+        }
+
+        // Jobs that are not part of a pipeline
+        for job_name in JOB_NAMES {
+            if pipeline_job_names.contains(*job_name) {
+                info!(Category::JobManager, "{} runs in a pipeline, its own schedule and skip_run quiet hours are ignored", job_name);
+                continue;
+            }
+            let job_schedule = self.get_job_schedule(job_name).context(format!("Failed to get schedule of {}", job_name))?;
+            self.spawn_job(
+                job_runners.clone(),
+                JobPipeline::from_job_name(job_name),
+                job_schedule,
+                *self.config.notification().on_job_error(),
+                discord_webhook_url.clone(),
+            );
         }
 
+        self.spawn_deferred_actions(*self.config.notification().on_job_error(), discord_webhook_url);
+
+        Ok(())
+    }
+
+    /**
+     * Schedule of a job that runs on its own, None if it is disabled
+     */
+    fn get_job_schedule(&self, job_name: &str) -> Result<Option<JobSchedule>, anyhow::Error> {
+        let jobs = self.config.jobs();
+        let default_config = Config::default();
+        let default_jobs = default_config.jobs();
+        let (interval_hours, default_interval_hours, schedule, quiet_hours) = match job_name {
+            "handle_unlinked" => (
+                jobs.handle_unlinked().interval_hours(),
+                default_jobs.handle_unlinked().interval_hours(),
+                jobs.handle_unlinked().schedule(),
+                jobs.handle_unlinked().quiet_hours(),
+            ),
+            "handle_unregistered" => (
+                jobs.handle_unregistered().interval_hours(),
+                default_jobs.handle_unregistered().interval_hours(),
+                jobs.handle_unregistered().schedule(),
+                jobs.handle_unregistered().quiet_hours(),
+            ),
+            "handle_orphaned" => (
+                jobs.handle_orphaned().interval_hours(),
+                default_jobs.handle_orphaned().interval_hours(),
+                jobs.handle_orphaned().schedule(),
+                jobs.handle_orphaned().quiet_hours(),
+            ),
+            "handle_tracker_errors" => (
+                jobs.handle_tracker_errors().interval_hours(),
+                default_jobs.handle_tracker_errors().interval_hours(),
+                jobs.handle_tracker_errors().schedule(),
+                jobs.handle_tracker_errors().quiet_hours(),
+            ),
+            "handle_errored" => (
+                jobs.handle_errored().interval_hours(),
+                default_jobs.handle_errored().interval_hours(),
+                jobs.handle_errored().schedule(),
+                jobs.handle_errored().quiet_hours(),
+            ),
+            "health_check_files" => (
+                jobs.health_check_files().interval_hours(),
+                default_jobs.health_check_files().interval_hours(),
+                jobs.health_check_files().schedule(),
+                jobs.health_check_files().quiet_hours(),
+            ),
+            _ => anyhow::bail!("Unknown job {}", job_name),
+        };
+        let quiet_windows = QuietWindows::from_config(self.config.quiet_hours(), quiet_hours).context("Failed to get quiet hours")?;
+
+        JobSchedule::from_config(interval_hours, default_interval_hours, schedule, quiet_windows)
+    }
+
+    /**
+     * Regularly take the actions that were deferred by quiet hours once they are over
+     */
+    fn spawn_deferred_actions(&self, notify_on_job_error: bool, discord_webhook_url: Option<Url>) {
+        let deferred_actions = DeferredActions::new(self.torrent_manager.clone(), self.db_manager.clone(), self.config.clone(), self.torrents_path.clone());
         let lock = self.job_lock.clone();
 
         tokio::spawn(async move {
+            loop {
+                sleep(Duration::from_secs(DEFERRED_ACTIONS_CHECK_MINUTES * 60)).await;
+
+                match deferred_actions.has_deferred_actions() {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(e) => {
+                        error!(Category::JobManager, "Failed to check for deferred actions: {:#}", e);
+                        continue;
+                    }
+                }
+
+                let _guard = lock.lock().await;
+                if let Err(e) = deferred_actions.run().await {
+                    error!(Category::JobManager, "Failed to take deferred actions: {:#}", e);
+                    // Notify on discord
+                    if notify_on_job_error {
+                        let mut discord_webhook_utils = DiscordWebhookUtils::new(discord_webhook_url.clone());
+                        if let Err(e) = discord_webhook_utils
+                            .send_webhook_embed("Error", "Taking deferred actions threw an error. Please check logs for more details.", vec![])
+                            .await
+                        {
+                            error!(Category::JobManager, "Error while sending discord webhook error message: {:#}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /**
+     * Run the job or pipeline on its schedule, nothing is spawned if it is disabled
+     */
+    fn spawn_job(&self, job_runners: Arc<JobRunners>, job_pipeline: JobPipeline, job_schedule_option: Option<JobSchedule>, notify_on_job_error: bool, discord_webhook_url: Option<Url>) {
+        let Some(job_schedule) = job_schedule_option else {
+            return;
+        };
+        let job_name = job_pipeline.name().to_string();
+
+        let lock = self.job_lock.clone();
+        let db_manager = self.db_manager.clone();
+
+        tokio::spawn(async move {
+            let mut is_startup = true;
             loop {
                 ///////////
                 // Sleep //
                 ///////////
-                if interval_hours == 0 {
-                    interval_hours = default_interval_hours;
+                if is_startup && job_schedule.run_on_startup() {
+                    info!(Category::JobManager, "{} ({}) runs on startup", job_name, job_schedule.schedule_type());
                 } else {
-                    let last_run = JobManager::get_last_run(job_name.as_str()).unwrap_or(None).unwrap_or_default();
-                    let sleep_minutes = JobManager::get_startup_sleep_minutes(job_name.as_str(), interval_hours as i64);
+                    let now = DateUtils::get_current_utc_datetime();
+                    let next_run = match JobManager::get_next_run(&db_manager, job_name.as_str(), job_schedule.schedule_type(), &now) {
+                        Ok(next_run) => next_run,
+                        Err(e) => {
+                            error!(Category::JobManager, "Failed to get next run of {}, stopping its schedule: {:#}", job_name, e);
+                            return;
+                        }
+                    };
+                    let jitter_secs = rand::random_range(0..=job_schedule.jitter_minutes() as i64 * 60);
+                    let next_run = next_run + TimeDelta::seconds(jitter_secs);
 
+                    let last_run = JobManager::get_last_run(&db_manager, job_name.as_str()).unwrap_or(None);
+                    let last_successful_run = JobManager::get_last_successful_run(&db_manager, job_name.as_str()).unwrap_or(None);
                     info!(
                         Category::JobManager,
-                        "{} (interval of {}h) last ran {:.2}h ago, next run in {:.2}h",
+                        "{} ({}) last ran {} ({}), last succeeded {}, next run at {} (in {:.2}h, {}min jitter)",
                         job_name,
-                        interval_hours,
-                        (DateUtils::get_current_local_naive_datetime() - last_run).num_minutes() as f64 / 60.0,
-                        sleep_minutes as f64 / 60.0
+                        job_schedule.schedule_type(),
+                        JobManager::get_time_ago_str(last_run.as_ref().map(|(started_at, _)| started_at)),
+                        last_run.as_ref().map(|(_, job_run_status)| job_run_status.to_string()).unwrap_or(String::from("never")),
+                        JobManager::get_time_ago_str(last_successful_run.as_ref()),
+                        DateUtils::convert_utc_datetime_to_local_string(&next_run),
+                        (next_run - now).num_minutes() as f64 / 60.0,
+                        jitter_secs / 60
                     );
 
-                    // If interval_hours is 0 use default instead
-                    interval_hours = match interval_hours {
-                        0 => default_interval_hours,
-                        _ => interval_hours,
-                    };
+                    sleep(Duration::from_secs((next_run - now).num_seconds().max(0) as u64)).await;
+                }
+                is_startup = false;
 
-                    sleep(Duration::from_mins(sleep_minutes as u64)).await;
+                // Quiet hours that skip runs postpone the run until they end
+                while let Some(quiet_end) = job_schedule.get_skip_run_end(&DateUtils::get_current_utc_datetime()) {
+                    info!(Category::JobManager, "{} postponed until {} (quiet hours)", job_name, DateUtils::convert_utc_datetime_to_local_string(&quiet_end));
+                    sleep(Duration::from_secs((quiet_end - DateUtils::get_current_utc_datetime()).num_seconds().max(0) as u64 + 1)).await;
                 }
 
                 /////////////
@@ -128,34 +296,92 @@ impl JobManager {
                 /////////////
                 {
                     let _guard = lock.lock().await;
+                    JobManager::run_pipeline(&db_manager, job_runners.as_ref(), &job_pipeline, &job_schedule, notify_on_job_error, discord_webhook_url.as_ref()).await;
+                }
+            }
+        });
+    }
 
-                    info!(Category::JobManager, "Starting {}...", job_name);
+    /**
+     * Run the jobs of the pipeline in order and record each of them, the pipeline itself is recorded too if it is not a single job
+     * Jobs pass their results on through the PipelineContext, it is cleared after a job took actions
+     */
+    async fn run_pipeline(db_manager: &DbManager, job_runners: &impl JobRunner, job_pipeline: &JobPipeline, job_schedule: &JobSchedule, notify_on_job_error: bool, discord_webhook_url: Option<&Url>) {
+        let pipeline_run_id_option = match job_pipeline.is_single_job() {
+            true => None,
+            false => {
+                info!(Category::JobManager, "Starting pipeline {}...", job_pipeline.name());
+                JobManager::start_run(db_manager, job_pipeline.name())
+                    .inspect_err(|e| error!(Category::JobManager, "Failed to record start of pipeline {}: {:#}", job_pipeline.name(), e))
+                    .ok()
+            }
+        };
 
-                    // Set last job run, then run job and save result
-                    let result: Result<(), anyhow::Error> = async {
-                        JobManager::set_last_run(job_name.as_str())?;
-                        job_fn(handler.clone()).await?;
-                        Ok(())
-                    }
-                    .await;
-
-                    // Check result for error and log & send discord message
-                    if let Err(e) = result {
-                        error!(Category::JobManager, "Failed to run {}: {:#}", job_name, e);
-                        // Notify on discord
-                        if notify_on_job_error {
-                            let mut discord_webhook_utils = DiscordWebhookUtils::new(discord_webhook_url.clone());
-                            if let Err(e) = discord_webhook_utils
-                                .send_webhook_embed("Error", format!("`{}` threw an error. Please check logs for more details.", job_name).as_str(), vec![])
-                                .await
-                            {
-                                error!(Category::JobManager, "Error while sending discord webhook error message: {:#}", e);
-                            }
-                        }
-                    }
+        let mut pipeline_context = PipelineContext::new(job_schedule.clone());
+        let mut failed_job_names: Vec<&str> = Vec::new();
+        for (i, job_name) in job_pipeline.job_names().iter().enumerate() {
+            info!(Category::JobManager, "Starting {}...", job_name);
+            let job_started_at = DateUtils::get_current_utc_datetime();
+
+            // Record the start, run job and record its result
+            let result: Result<(), anyhow::Error> = async {
+                let job_run_id = JobManager::start_run(db_manager, job_name)?;
+                let job_result = job_runners.run(job_name, &mut pipeline_context).await;
+                JobManager::finish_run(db_manager, job_run_id, &job_result)?;
+                job_result?;
+                Ok(())
+            }
+            .await;
+
+            // Torrents and files might have changed, so the next job has to get them again
+            match JobManager::has_taken_actions(db_manager, job_name, &job_started_at) {
+                Ok(false) => {}
+                Ok(true) => pipeline_context.clear(),
+                Err(e) => {
+                    error!(Category::JobManager, "Error while checking actions of {}: {:#}", job_name, e);
+                    pipeline_context.clear();
                 }
             }
-        });
+
+            // Check result for error and log & send discord message
+            let Err(e) = result else {
+                continue;
+            };
+            error!(Category::JobManager, "Failed to run {}: {:#}", job_name, e);
+            failed_job_names.push(job_name);
+            // Notify on discord
+            if notify_on_job_error {
+                let msg = match job_pipeline.is_single_job() {
+                    true => format!("`{}` threw an error. Please check logs for more details.", job_name),
+                    false => format!("`{}` of pipeline `{}` threw an error. Please check logs for more details.", job_name, job_pipeline.name()),
+                };
+                let mut discord_webhook_utils = DiscordWebhookUtils::new(discord_webhook_url.cloned());
+                if let Err(e) = discord_webhook_utils.send_webhook_embed("Error", msg.as_str(), vec![]).await {
+                    error!(Category::JobManager, "Error while sending discord webhook error message: {:#}", e);
+                }
+            }
+            if *job_pipeline.failure_policy() == FailurePolicy::Abort && i + 1 < job_pipeline.job_names().len() {
+                warn!(Category::JobManager, "Aborting pipeline {}, skipping {}", job_pipeline.name(), job_pipeline.job_names()[i + 1..].join(", "));
+                break;
+            }
+        }
+
+        if let Some(pipeline_run_id) = pipeline_run_id_option {
+            let pipeline_result = match failed_job_names.is_empty() {
+                true => Ok(JobRunStats::default()),
+                false => Err(anyhow::anyhow!("Failed jobs: {}", failed_job_names.join(", "))),
+            };
+            if let Err(e) = JobManager::finish_run(db_manager, pipeline_run_id, &pipeline_result) {
+                error!(Category::JobManager, "Failed to record end of pipeline {}: {:#}", job_pipeline.name(), e);
+            }
+            info!(
+                Category::JobManager,
+                "Pipeline {} finished, {} of {} jobs failed",
+                job_pipeline.name(),
+                failed_job_names.len(),
+                job_pipeline.job_names().len()
+            );
+        }
     }
 
     pub async fn wait_for_jobs_to_finish(&self) {
@@ -175,23 +401,49 @@ impl JobManager {
     // Private Utils //
     ///////////////////
 
-    fn get_startup_sleep_minutes(job_name: &str, interval_hours: i64) -> i64 {
-        match JobManager::get_last_run(job_name) {
-            // Check for get_last_run error
-            Ok(last_run_naive_datetime_option) => match last_run_naive_datetime_option {
-                // Check for None
-                Some(last_run_naive_datetime) => {
-                    // If the last job run is bigger than interval_hours then it's long ago and should instantly run
-                    // Else the job would wait longer than it has to, so return the interval minus the time since the last run
-                    let time_delta = DateUtils::get_current_local_naive_datetime() - last_run_naive_datetime;
-                    if time_delta > TimeDelta::hours(interval_hours) { 0 } else { interval_hours * 60 - time_delta.num_minutes() }
-                }
-                None => interval_hours * 60,
-            },
-            Err(e) => {
+    /**
+     * Next run without jitter, cron schedules don't depend on earlier runs
+     */
+    fn get_next_run(db_manager: &DbManager, job_name: &str, schedule_type: &ScheduleType, now: &DateTime<Utc>) -> Result<DateTime<Utc>, anyhow::Error> {
+        match schedule_type {
+            ScheduleType::Interval { hours } => Ok(*now + TimeDelta::minutes(JobManager::get_startup_sleep_minutes(db_manager, job_name, *hours))),
+            ScheduleType::Cron(cron) => DateUtils::get_next_cron_datetime(cron, now).context(format!("No next run found for cron {}", cron.as_str())),
+        }
+    }
+
+    /**
+     * Minutes until the next run, counted from the last successful run
+     * A failed or interrupted last run is retried after FAILED_RUN_RETRY_MINUTES, but never before the interval since the last successful run passed
+     */
+    fn get_startup_sleep_minutes(db_manager: &DbManager, job_name: &str, interval_hours: i64) -> i64 {
+        let (last_run, last_successful_run) = match (JobManager::get_last_run(db_manager, job_name), JobManager::get_last_successful_run(db_manager, job_name)) {
+            (Ok(last_run), Ok(last_successful_run)) => (last_run, last_successful_run),
+            (Err(e), _) | (_, Err(e)) => {
                 error!(Category::JobManager, "Error while getting last job run for {}: {:#}", job_name, e);
-                interval_hours * 60
+                return interval_hours * 60;
             }
+        };
+
+        let now = DateUtils::get_current_utc_datetime();
+        let next_run = match last_run {
+            None => return interval_hours * 60,
+            Some((last_run_started_at, job_run_status)) if job_run_status != JobRunStatus::Success => {
+                let retry_at = last_run_started_at + TimeDelta::minutes(FAILED_RUN_RETRY_MINUTES.min(interval_hours * 60));
+                match last_successful_run {
+                    Some(last_successful_run_started_at) => retry_at.max(last_successful_run_started_at + TimeDelta::hours(interval_hours)),
+                    None => retry_at,
+                }
+            }
+            Some((last_run_started_at, _)) => last_run_started_at + TimeDelta::hours(interval_hours),
+        };
+        // If the next run is in the past it's overdue and should instantly run
+        (next_run - now).num_minutes().max(0)
+    }
+
+    fn get_time_ago_str(datetime_option: Option<&DateTime<Utc>>) -> String {
+        match datetime_option {
+            Some(datetime) => format!("{:.2}h ago", (DateUtils::get_current_utc_datetime() - *datetime).num_minutes() as f64 / 60.0),
+            None => String::from("never"),
         }
     }
 
@@ -199,32 +451,322 @@ impl JobManager {
     // Db Stuff //
     //////////////
 
-    fn get_last_run(job_name: &str) -> Result<Option<NaiveDateTime>, anyhow::Error> {
-        let session = Session::new()?;
-        let conn = session.into_conn().ok_or(anyhow::anyhow!("Failed to get conn from session"))?;
+    /**
+     * Start and status of the last run
+     */
+    fn get_last_run(db_manager: &DbManager, job_name: &str) -> Result<Option<(DateTime<Utc>, JobRunStatus)>, anyhow::Error> {
+        let conn = db_manager.conn()?;
+
+        let mut stmt = conn
+            .prepare("SELECT started_at, status FROM job_runs WHERE job = ?1 ORDER BY started_at DESC, id DESC LIMIT 1")
+            .context("Failed to prepare get last job run")?;
+        let last_run_option: Option<(String, String)> = stmt.query_one(params![job_name], |row| Ok((row.get(0)?, row.get(1)?))).optional().context("Failed to query last job run")?;
+
+        match last_run_option {
+            Some((started_at_str, status_str)) => {
+                let started_at = DateUtils::parse_utc_datetime_from_str(started_at_str.as_str())?;
+                Ok(Some((started_at, JobRunStatus::from_str(&status_str)?)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_last_successful_run(db_manager: &DbManager, job_name: &str) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        let conn = db_manager.conn()?;
 
-        let mut stmt = conn.prepare("SELECT last_job_run FROM jobs WHERE job_name = ?1").context("Failed to prepare get last job run")?;
-        let last_job_run_str_option: Option<String> = stmt.query_one(params![job_name], |row| row.get(0)).optional().context("Failed to query last job run")?;
+        let mut stmt = conn
+            .prepare("SELECT started_at FROM job_runs WHERE job = ?1 AND status = ?2 ORDER BY started_at DESC, id DESC LIMIT 1")
+            .context("Failed to prepare get last successful job run")?;
+        let started_at_str_option: Option<String> = stmt
+            .query_one(params![job_name, JobRunStatus::Success.to_string()], |row| row.get(0))
+            .optional()
+            .context("Failed to query last successful job run")?;
 
-        match last_job_run_str_option {
-            Some(last_job_run_str) => {
-                let last_job_run = DateUtils::parse_naive_datetime_from_str(last_job_run_str.as_str())?;
-                Ok(Some(last_job_run))
+        match started_at_str_option {
+            Some(started_at_str) => {
+                let started_at = DateUtils::parse_utc_datetime_from_str(started_at_str.as_str())?;
+                Ok(Some(started_at))
             }
             None => Ok(None),
         }
     }
 
-    fn set_last_run(job_name: &str) -> Result<(), anyhow::Error> {
-        let session = Session::new()?;
-        let conn = session.into_conn().ok_or(anyhow::anyhow!("Failed to get conn from session"))?;
+    /**
+     * Record the start of a run, returns its id
+     */
+    fn start_run(db_manager: &DbManager, job_name: &str) -> Result<i64, anyhow::Error> {
+        let conn = db_manager.conn()?;
 
         conn.execute(
-            "INSERT OR REPLACE INTO jobs(job_name, last_job_run) VALUES(?1, ?2)",
-            params![job_name, DateUtils::convert_naive_datetime_to_string(DateUtils::get_current_local_naive_datetime())],
+            "INSERT INTO job_runs (job, started_at, ended_at, status, error, torrents_scanned, candidates, strikes, actions, bytes_freed) VALUES (?1, ?2, '', ?3, '', 0, 0, 0, 0, 0)",
+            params![job_name, DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime()), JobRunStatus::Running.to_string()],
         )
-        .context("Failed to insert or update last job run")?;
+        .context("Failed to insert job run")?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /**
+     * Record the end and result of a run, actions and freed bytes are counted from the actions the job recorded since the run started
+     */
+    fn finish_run(db_manager: &DbManager, job_run_id: i64, job_result: &Result<JobRunStats, anyhow::Error>) -> Result<(), anyhow::Error> {
+        let (job_run_status, error_str, job_run_stats) = match job_result {
+            Ok(job_run_stats) => (JobRunStatus::Success, String::new(), job_run_stats),
+            Err(e) => (JobRunStatus::Failed, format!("{:#}", e), &JobRunStats::default()),
+        };
+
+        let conn = db_manager.conn()?;
+        conn.execute(
+            "UPDATE job_runs SET ended_at = ?1, status = ?2, error = ?3, torrents_scanned = ?4, candidates = ?5, strikes = ?6,
+                actions = (SELECT COUNT(*) FROM actions WHERE actions.job = job_runs.job AND actions.created_at >= job_runs.started_at),
+                bytes_freed = (SELECT COALESCE(SUM(bytes_reclaimed), 0) FROM actions WHERE actions.job = job_runs.job AND actions.created_at >= job_runs.started_at AND success = 1)
+                WHERE id = ?7",
+            params![
+                DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime()),
+                job_run_status.to_string(),
+                error_str,
+                job_run_stats.torrents_scanned() as i64,
+                job_run_stats.candidates() as i64,
+                job_run_stats.strikes() as i64,
+                job_run_id
+            ],
+        )
+        .context("Failed to update job run")?;
 
         Ok(())
     }
+
+    /**
+     * True if the job successfully took any action other than test since the given time, skipped actions are recorded as failed
+     */
+    fn has_taken_actions(db_manager: &DbManager, job_name: &str, since: &DateTime<Utc>) -> Result<bool, anyhow::Error> {
+        let mut action_utils = ActionUtils::from_job_name(db_manager.clone(), Category::JobManager, job_name.to_string(), QuietWindows::default());
+        let action_records = action_utils.get_actions(&ActionFilter {
+            since: Some(*since),
+            job: Some(job_name.to_string()),
+            ..Default::default()
+        })?;
+        Ok(action_records.iter().any(|action_record| action_record.success() && action_record.action() != ActionType::Test.to_string()))
+    }
+
+    fn mark_interrupted_runs(db_manager: &DbManager) -> Result<(), anyhow::Error> {
+        let conn = db_manager.conn()?;
+
+        let interrupted_count = conn
+            .execute("UPDATE job_runs SET status = ?1 WHERE status = ?2", params![JobRunStatus::Interrupted.to_string(), JobRunStatus::Running.to_string()])
+            .context("Failed to mark interrupted job runs")?;
+        if interrupted_count > 0 {
+            warn!(Category::JobManager, "{} job runs were interrupted by a shutdown", interrupted_count);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use chrono::TimeZone;
+    use chrono_tz::Europe::Berlin;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::{QuietHours, Schedule},
+        jobs::utils::action_utils::ActionEntry,
+    };
+
+    const JOB_NAME: &str = "handle_unlinked";
+
+    fn get_db_manager() -> DbManager {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        DbManager::open_in_memory().unwrap()
+    }
+
+    fn add_run(db_manager: &DbManager, minutes_ago: i64, job_run_status: JobRunStatus) {
+        let started_at_str = DateUtils::convert_utc_datetime_to_string(&(DateUtils::get_current_utc_datetime() - TimeDelta::minutes(minutes_ago)));
+        db_manager
+            .conn()
+            .unwrap()
+            .execute(
+                "INSERT INTO job_runs (job, started_at, ended_at, status, error, torrents_scanned, candidates, strikes, actions, bytes_freed) VALUES (?1, ?2, ?2, ?3, '', 0, 0, 0, 0, 0)",
+                params![JOB_NAME, started_at_str, job_run_status.to_string()],
+            )
+            .unwrap();
+    }
+
+    /**
+     * Time passes while the test runs, so the sleep may be a minute shorter
+     */
+    fn assert_sleep_minutes(db_manager: &DbManager, interval_hours: i64, expected_minutes: i64) {
+        let sleep_minutes = JobManager::get_startup_sleep_minutes(db_manager, JOB_NAME, interval_hours);
+        assert!((expected_minutes - 1..=expected_minutes).contains(&sleep_minutes), "expected {} minutes, got {}", expected_minutes, sleep_minutes);
+    }
+
+    #[test]
+    fn never_run_job_waits_for_its_interval() {
+        let db_manager = get_db_manager();
+        assert_sleep_minutes(&db_manager, 5, 5 * 60);
+    }
+
+    #[test]
+    fn next_run_counts_from_the_last_successful_run() {
+        let db_manager = get_db_manager();
+        add_run(&db_manager, 60, JobRunStatus::Success);
+        assert_sleep_minutes(&db_manager, 5, 4 * 60);
+    }
+
+    #[test]
+    fn overdue_job_runs_instantly() {
+        let db_manager = get_db_manager();
+        add_run(&db_manager, 10 * 60, JobRunStatus::Success);
+        assert_sleep_minutes(&db_manager, 5, 0);
+    }
+
+    #[test]
+    fn failed_run_is_retried_after_an_hour() {
+        let db_manager = get_db_manager();
+        add_run(&db_manager, 10 * 60, JobRunStatus::Success);
+        add_run(&db_manager, 10, JobRunStatus::Failed);
+        assert_sleep_minutes(&db_manager, 5, 50);
+    }
+
+    #[test]
+    fn interrupted_run_without_successful_run_is_retried_after_an_hour() {
+        let db_manager = get_db_manager();
+        add_run(&db_manager, 10, JobRunStatus::Interrupted);
+        assert_sleep_minutes(&db_manager, 5, 50);
+    }
+
+    #[test]
+    fn failed_run_is_not_retried_before_the_interval_of_the_last_successful_run() {
+        let db_manager = get_db_manager();
+        add_run(&db_manager, 60, JobRunStatus::Success);
+        add_run(&db_manager, 10, JobRunStatus::Failed);
+        assert_sleep_minutes(&db_manager, 5, 4 * 60);
+    }
+
+    #[test]
+    fn skip_run_postpones_the_run_until_following_windows_end() {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        let quiet_hours: Vec<QuietHours> = serde_json::from_value(json!([
+            { "start": "22:00", "end": "02:00", "mode": "skip_run" },
+            { "start": "02:00", "end": "06:00", "mode": "skip_run" },
+            { "start": "06:00", "end": "08:00", "mode": "defer_actions" },
+        ]))
+        .unwrap();
+        let quiet_windows = QuietWindows::from_config(&[], &quiet_hours).unwrap();
+        let job_schedule = JobSchedule::from_config(5, 13, &Schedule::default(), quiet_windows).unwrap().unwrap();
+        let now = Berlin.with_ymd_and_hms(2026, 10, 12, 23, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(job_schedule.get_skip_run_end(&now), Some(Berlin.with_ymd_and_hms(2026, 10, 13, 6, 0, 0).unwrap().with_timezone(&Utc)));
+        // Windows that defer actions don't postpone the run
+        let now = Berlin.with_ymd_and_hms(2026, 10, 13, 7, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(job_schedule.get_skip_run_end(&now), None);
+    }
+
+    /**
+     * Runs no real job, a job leaves torrents in the PipelineContext and may take an action or fail
+     */
+    struct FakeJobRunners {
+        db_manager: DbManager,
+        acting_job_names: Vec<&'static str>,
+        failing_job_names: Vec<&'static str>,
+        /**
+         * Jobs in the order they ran, with whether torrents of an earlier job were passed on
+         */
+        runs: RefCell<Vec<(String, bool)>>,
+    }
+
+    impl JobRunner for FakeJobRunners {
+        async fn run(&self, job_name: &str, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
+            self.runs.borrow_mut().push((job_name.to_string(), pipeline_context.has_torrents()));
+            pipeline_context.set_test_torrents(Vec::new());
+            if self.acting_job_names.contains(&job_name) {
+                let mut action_utils = ActionUtils::from_job_name(self.db_manager.clone(), Category::JobManager, job_name.to_string(), QuietWindows::default());
+                action_utils.record(&[ActionEntry::new("stop", "a", "a", "tracker.example.org", Vec::new(), "default")], &Ok(()))?;
+            }
+            match self.failing_job_names.contains(&job_name) {
+                true => anyhow::bail!("{} failed", job_name),
+                false => Ok(JobRunStats::default()),
+            }
+        }
+    }
+
+    /**
+     * Runs handle_unregistered, handle_unlinked and handle_orphaned as pipeline cleanup
+     */
+    async fn run_pipeline(on_failure: &str, acting_job_names: Vec<&'static str>, failing_job_names: Vec<&'static str>) -> (Vec<(String, bool)>, Vec<(String, String)>) {
+        let db_manager = get_db_manager();
+        let fake_job_runners = FakeJobRunners {
+            db_manager: db_manager.clone(),
+            acting_job_names,
+            failing_job_names,
+            runs: RefCell::new(Vec::new()),
+        };
+        let pipeline: Pipeline = serde_json::from_value(json!({
+            "name": "cleanup",
+            "jobs": ["handle_unregistered", "handle_unlinked", "handle_orphaned"],
+            "on_failure": on_failure
+        }))
+        .unwrap();
+        let job_pipeline = JobPipeline::from_config(&pipeline, JOB_NAMES).unwrap();
+        let job_schedule = JobSchedule::from_config(5, 13, &Schedule::default(), QuietWindows::default()).unwrap().unwrap();
+        JobManager::run_pipeline(&db_manager, &fake_job_runners, &job_pipeline, &job_schedule, false, None).await;
+
+        let job_runs: Vec<(String, String)> = db_manager
+            .conn()
+            .unwrap()
+            .prepare("SELECT job, status FROM job_runs ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<(String, String)>, _>>()
+            .unwrap();
+        (fake_job_runners.runs.into_inner(), job_runs)
+    }
+
+    fn get_job_names(runs: &[(String, bool)]) -> Vec<&str> {
+        runs.iter().map(|(job_name, _)| job_name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn pipeline_and_its_jobs_are_recorded() {
+        let (runs, job_runs) = run_pipeline("abort", Vec::new(), Vec::new()).await;
+        assert_eq!(get_job_names(&runs), vec!["handle_unregistered", "handle_unlinked", "handle_orphaned"]);
+        let expected_job_runs: Vec<(String, String)> = [("cleanup", "success"), ("handle_unregistered", "success"), ("handle_unlinked", "success"), ("handle_orphaned", "success")]
+            .iter()
+            .map(|(job_name, status)| (job_name.to_string(), status.to_string()))
+            .collect();
+        assert_eq!(job_runs, expected_job_runs);
+    }
+
+    #[tokio::test]
+    async fn abort_skips_the_jobs_after_a_failed_one() {
+        let (runs, job_runs) = run_pipeline("abort", Vec::new(), vec!["handle_unlinked"]).await;
+        assert_eq!(get_job_names(&runs), vec!["handle_unregistered", "handle_unlinked"]);
+        let expected_job_runs: Vec<(String, String)> = [("cleanup", "failed"), ("handle_unregistered", "success"), ("handle_unlinked", "failed")]
+            .iter()
+            .map(|(job_name, status)| (job_name.to_string(), status.to_string()))
+            .collect();
+        assert_eq!(job_runs, expected_job_runs);
+    }
+
+    #[tokio::test]
+    async fn continue_runs_the_jobs_after_a_failed_one() {
+        let (runs, job_runs) = run_pipeline("continue", Vec::new(), vec!["handle_unlinked"]).await;
+        assert_eq!(get_job_names(&runs), vec!["handle_unregistered", "handle_unlinked", "handle_orphaned"]);
+        assert_eq!(job_runs.first(), Some(&(String::from("cleanup"), String::from("failed"))));
+        assert_eq!(job_runs.last(), Some(&(String::from("handle_orphaned"), String::from("success"))));
+    }
+
+    #[tokio::test]
+    async fn pipeline_context_is_cleared_after_a_job_took_actions() {
+        let (runs, _) = run_pipeline("abort", vec!["handle_unlinked"], Vec::new()).await;
+        let expected_runs: Vec<(String, bool)> = [("handle_unregistered", false), ("handle_unlinked", true), ("handle_orphaned", false)]
+            .iter()
+            .map(|(job_name, has_torrents)| (job_name.to_string(), *has_torrents))
+            .collect();
+        assert_eq!(runs, expected_runs);
+    }
 }
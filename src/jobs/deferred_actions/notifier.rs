@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::utils::{
+    date_utils::DateUtils,
+    discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+};
+
+pub struct DeferralNotifier;
+
+impl DeferralNotifier {
+    /**
+     * Send 1 notification for the actions a job run deferred because of quiet hours
+     */
+    pub async fn send_deferral_notification(discord_webhook_utils: &mut DiscordWebhookUtils, job_name: &str, deferred_count: usize, deferral_end: &DateTime<Utc>) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let fields: Vec<EmbedField> = vec![
+            EmbedField {
+                name: String::from("Job"),
+                value: job_name.to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Deferred actions"),
+                value: deferred_count.to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Until"),
+                value: DateUtils::convert_utc_datetime_to_local_string(deferral_end),
+                inline: true,
+            },
+        ];
+
+        discord_webhook_utils.send_webhook_embed("Quiet hours", "Actions were deferred until the quiet hours end", fields).await
+    }
+
+    /**
+     * Send 1 notification for the deferred actions that were taken after quiet hours ended
+     */
+    pub async fn send_taken_notification(discord_webhook_utils: &mut DiscordWebhookUtils, taken_counts: &BTreeMap<String, usize>, failed_count: usize, skipped_count: usize) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let mut fields: Vec<EmbedField> = taken_counts
+            .iter()
+            .map(|(job_name, taken_count)| EmbedField {
+                name: job_name.to_string(),
+                value: format!("{} actions", taken_count),
+                inline: true,
+            })
+            .collect();
+        fields.push(EmbedField {
+            name: String::from("Failed"),
+            value: failed_count.to_string(),
+            inline: true,
+        });
+        fields.push(EmbedField {
+            name: String::from("Skipped"),
+            value: skipped_count.to_string(),
+            inline: true,
+        });
+
+        discord_webhook_utils.send_webhook_embed("Quiet hours", "Deferred actions were taken after the quiet hours ended", fields).await
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use reqwest::Url;
+
+use crate::{
+    config::{Config, QuietHours},
+    debug, info,
+    jobs::{
+        deferred_actions::notifier::DeferralNotifier,
+        enums::{action_type::ActionType, quiet_hours_mode::QuietHoursMode},
+        handle_errored, handle_orphaned, handle_unlinked,
+        handle_unlinked::retention::Retention,
+        handle_unregistered,
+        models::{quiet_windows::QuietWindows, rule::Rule, torrent_links::TorrentLinks, unregistered_matcher::UnregisteredMatcher},
+        utils::{
+            action_utils::{ActionEntry, ActionUtils, DeferredActionRecord},
+            cross_seed_utils::{CrossSeedGroups, CrossSeedUtils},
+            file_utils::FileUtils,
+            hit_and_run_utils::HitAndRunUtils,
+            rule_utils::RuleUtils,
+            tracker_domain_utils::TrackerDomainUtils,
+            upload_history_utils::UploadHistoryUtils,
+        },
+    },
+    logger::enums::category::Category,
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    utils::{date_utils::DateUtils, db_manager::DbManager, discord_webhook_utils::DiscordWebhookUtils},
+    warn,
+};
+
+pub struct DeferredActions {
+    torrent_manager: Arc<TorrentManager>,
+    db_manager: DbManager,
+    config: Config,
+    torrents_path: String,
+}
+
+impl DeferredActions {
+    pub fn new(torrent_manager: Arc<TorrentManager>, db_manager: DbManager, config: Config, torrents_path: String) -> Self {
+        Self {
+            torrent_manager,
+            db_manager,
+            config,
+            torrents_path,
+        }
+    }
+
+    /**
+     * Check if there is anything queued, so an empty queue doesn't have to wait for running jobs
+     */
+    pub fn has_deferred_actions(&self) -> Result<bool, anyhow::Error> {
+        ActionUtils::new(self.db_manager.clone(), Category::DeferredActions, QuietWindows::default()).has_deferred_actions()
+    }
+
+    /**
+     * Take the deferred actions of jobs whose quiet hours ended, they are recorded for their job like any other action
+     */
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let mut queue_action_utils = ActionUtils::new(self.db_manager.clone(), Category::DeferredActions, QuietWindows::default());
+        let deferred_action_records = queue_action_utils.get_deferred_actions().context("Failed to get deferred actions")?;
+
+        // Actions of jobs that are still in their quiet hours stay queued
+        let now = DateUtils::get_current_utc_datetime();
+        let mut due_records: Vec<DeferredActionRecord> = Vec::new();
+        for deferred_action_record in deferred_action_records {
+            let quiet_windows = self
+                .get_job_quiet_windows(deferred_action_record.job())
+                .context(format!("Failed to get quiet hours of {}", deferred_action_record.job()))?;
+            if quiet_windows.get_end(&QuietHoursMode::DeferActions, &now).is_none() {
+                due_records.push(deferred_action_record);
+            }
+        }
+        if due_records.is_empty() {
+            debug!(Category::DeferredActions, "No deferred actions are due");
+            return Ok(());
+        }
+        info!(Category::DeferredActions, "Taking {} deferred actions...", due_records.len());
+
+        let discord_webhook_url: Option<Url> = match self.config.notification().discord_webhook_url().len() > 1 {
+            true => Some(Url::parse(self.config.notification().discord_webhook_url()).context("Failed to parse discord_webhook_url")?),
+            false => None,
+        };
+        let mut discord_webhook_utils = DiscordWebhookUtils::new(discord_webhook_url);
+
+        // Login
+        self.torrent_manager.login().await.context("Failed to login to torrent client")?;
+
+        // Everything may have changed since the actions were queued, so they are checked again before they are taken
+        let torrents = self.torrent_manager.get_all_torrents().await.context("Failed to get all torrents")?;
+        let torrents_by_hash: HashMap<&str, &Torrent> = torrents.iter().map(|torrent| (torrent.hash(), torrent)).collect();
+
+        // Queued torrents often have no working tracker (e.g. unregistered), so tracker overrides match their trackers lists
+        let queued_hashes: HashSet<&str> = due_records.iter().map(|deferred_action_record| deferred_action_record.action_entry().hash()).collect();
+        let queued_torrents: Vec<Torrent> = torrents.iter().filter(|torrent| queued_hashes.contains(torrent.hash())).cloned().collect();
+        let hit_and_run_utils = HitAndRunUtils::new(&self.config);
+        let mut tracker_domain_utils = TrackerDomainUtils::default();
+        tracker_domain_utils.resolve_tracker_domains(&self.torrent_manager, &queued_torrents).await?;
+
+        // Queued torrents have to still meet the criteria of their job, e.g. a tracker that works again or a torrent that was imported into the library since
+        let unmet_criteria_hashes: HashSet<(String, String)> = self.get_unmet_criteria_hashes(&due_records, &torrents_by_hash, &hit_and_run_utils, &tracker_domain_utils).await?;
+
+        // Cross-seed groups are only needed if files of torrents are deleted, torrents that are protected now or don't meet the criteria anymore won't be deleted
+        let queued_delete_hashes: HashSet<String> = due_records
+            .iter()
+            .filter(|deferred_action_record| deferred_action_record.action_entry().delete_files())
+            .filter(|deferred_action_record| !unmet_criteria_hashes.contains(&(deferred_action_record.job().to_string(), deferred_action_record.action_entry().hash().to_string())))
+            .filter_map(|deferred_action_record| {
+                let torrent = torrents_by_hash.get(deferred_action_record.action_entry().hash())?;
+                let is_protected = self.get_job_rule(deferred_action_record.job(), torrent, &tracker_domain_utils).is_some_and(|rule| rule.is_protected(torrent.tags()));
+                (!is_protected).then(|| torrent.hash().to_string())
+            })
+            .collect();
+        let cross_seed_groups_option: Option<CrossSeedGroups> = match queued_delete_hashes.is_empty() {
+            true => None,
+            false => {
+                debug!(Category::DeferredActions, "Getting cross-seed groups...");
+                let torrent_refs: Vec<&Torrent> = torrents.iter().collect();
+                Some(CrossSeedUtils::get_cross_seed_groups(&torrent_refs).context("Failed to get cross-seed groups")?)
+            }
+        };
+
+        // Torrent paths are only needed if orphaned paths are deleted
+        let is_any_path_queued = due_records.iter().any(|deferred_action_record| deferred_action_record.action_entry().hash().is_empty());
+        let torrent_paths: HashSet<PathBuf> = match is_any_path_queued {
+            true => handle_orphaned::receiver::Receiver::get_torrent_paths(self.torrent_manager.clone(), &torrents).await?,
+            false => HashSet::new(),
+        };
+        let known_hardlinks_option: Option<HashMap<(u64, u64), u64>> = match is_any_path_queued && *self.config.jobs().handle_orphaned().protect_external_hardlinks() {
+            true => Some(FileUtils::get_known_hardlinks(&self.torrents_path, self.config.paths().link_paths())?),
+            false => None,
+        };
+
+        let mut taken_counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut failed_count: usize = 0;
+        let mut skipped_count: usize = 0;
+        for deferred_action_record in &due_records {
+            let action_entry = deferred_action_record.action_entry();
+            let skip_reason_option = match action_entry.hash().is_empty() {
+                true => self.get_path_skip_reason(action_entry, &torrent_paths, known_hardlinks_option.as_ref())?,
+                false => self.get_torrent_skip_reason(
+                    deferred_action_record,
+                    &torrents_by_hash,
+                    cross_seed_groups_option.as_ref(),
+                    &queued_delete_hashes,
+                    &unmet_criteria_hashes,
+                    &tracker_domain_utils,
+                ),
+            };
+            if let Some(skip_reason) = skip_reason_option {
+                info!(
+                    Category::DeferredActions,
+                    "Skipping {} of {}, {}: {}",
+                    action_entry.action(),
+                    deferred_action_record.job(),
+                    skip_reason,
+                    action_entry.name()
+                );
+                queue_action_utils.delete_deferred_action(deferred_action_record)?;
+                skipped_count += 1;
+                continue;
+            }
+
+            info!(Category::DeferredActions, "Action ({}): {} {}", deferred_action_record.job(), action_entry.action(), action_entry.name());
+            let result = self.take_action(action_entry).await;
+            let mut action_utils = ActionUtils::from_job_name(self.db_manager.clone(), Category::DeferredActions, deferred_action_record.job().to_string(), QuietWindows::default());
+            action_utils.record(std::slice::from_ref(action_entry), &result)?;
+            queue_action_utils.delete_deferred_action(deferred_action_record)?;
+
+            match result {
+                Ok(()) => *taken_counts.entry(deferred_action_record.job().to_string()).or_insert(0) += 1,
+                Err(e) => {
+                    warn!(Category::DeferredActions, "Failed to take deferred action: {:#}", e);
+                    failed_count += 1;
+                }
+            }
+        }
+
+        // Notification
+        if *self.config.notification().on_job_action() {
+            DeferralNotifier::send_taken_notification(&mut discord_webhook_utils, &taken_counts, failed_count, skipped_count)
+                .await
+                .context("Failed to send deferred actions notification")?;
+        }
+
+        // Logout
+        self.torrent_manager.logout().await.context("Failed to logout of torrent client")?;
+
+        Ok(())
+    }
+
+    /**
+     * Reason the deferred action of a torrent is dropped, None if it is still valid
+     * Files are only deleted if every torrent that shares them is deleted as well
+     */
+    fn get_torrent_skip_reason(
+        &self,
+        deferred_action_record: &DeferredActionRecord,
+        torrents_by_hash: &HashMap<&str, &Torrent>,
+        cross_seed_groups_option: Option<&CrossSeedGroups>,
+        queued_delete_hashes: &HashSet<String>,
+        unmet_criteria_hashes: &HashSet<(String, String)>,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Option<String> {
+        let action_entry = deferred_action_record.action_entry();
+        let Some(torrent) = torrents_by_hash.get(action_entry.hash()) else {
+            return Some(String::from("torrent doesn't exist anymore"));
+        };
+        if unmet_criteria_hashes.contains(&(deferred_action_record.job().to_string(), action_entry.hash().to_string())) {
+            return Some(format!("torrent doesn't meet the criteria of {} anymore", deferred_action_record.job()));
+        }
+        if let Some(rule) = self.get_job_rule(deferred_action_record.job(), torrent, tracker_domain_utils)
+            && rule.is_protected(torrent.tags())
+        {
+            return Some(format!("torrent is protected now (rule {})", rule.name()));
+        }
+        if action_entry.delete_files()
+            && let Some(cross_seed_groups) = cross_seed_groups_option
+            && cross_seed_groups
+                .get_groups_of_hashes(&[action_entry.hash().to_string()])
+                .iter()
+                .any(|cross_seed_group| cross_seed_group.hashes().iter().any(|hash| !queued_delete_hashes.contains(hash)))
+        {
+            return Some(String::from("files are shared with a torrent that is not deleted"));
+        }
+        None
+    }
+
+    /**
+     * Queued torrents that don't meet the criteria of the job that queued them anymore, checked the same way as in the job itself
+     * Actions taken for a retention quota are checked for eligibility instead since they don't need the criteria
+     * Returns: HashSet<(String, String)> | HashSet<(job_name, torrent_hash)>
+     */
+    async fn get_unmet_criteria_hashes(
+        &self,
+        due_records: &[DeferredActionRecord],
+        torrents_by_hash: &HashMap<&str, &Torrent>,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<HashSet<(String, String)>, anyhow::Error> {
+        let jobs = self.config.jobs();
+        let mut job_records: BTreeMap<&str, Vec<&DeferredActionRecord>> = BTreeMap::new();
+        for deferred_action_record in due_records.iter().filter(|deferred_action_record| torrents_by_hash.contains_key(deferred_action_record.action_entry().hash())) {
+            job_records.entry(deferred_action_record.job()).or_default().push(deferred_action_record);
+        }
+
+        let mut unmet_criteria_hashes: HashSet<(String, String)> = HashSet::new();
+        for (job_name, deferred_action_records) in job_records {
+            let torrents: Vec<Torrent> = deferred_action_records
+                .iter()
+                .filter_map(|deferred_action_record| torrents_by_hash.get(deferred_action_record.action_entry().hash()))
+                .map(|torrent| (*torrent).clone())
+                .collect();
+            match job_name {
+                "handle_unlinked" => {
+                    let known_hardlinks = FileUtils::get_known_hardlinks(&self.torrents_path, self.config.paths().link_paths())?;
+                    let library_paths = self.config.paths().library_paths();
+                    let library_index_option = (!library_paths.is_empty()).then(|| FileUtils::get_library_index(library_paths)).transpose().context("Failed to get library index")?;
+                    let recent_upload_days = jobs.handle_unlinked().recent_upload_days();
+                    let recent_uploads: HashMap<String, i64> = match jobs.handle_unlinked().protect_recent_upload_gb() > 0.0 && recent_upload_days > 0 {
+                        true => UploadHistoryUtils::new(self.db_manager.clone(), Category::DeferredActions)
+                            .get_recent_uploads(&torrents, recent_upload_days)
+                            .context("Failed to get recent uploads")?,
+                        false => HashMap::new(),
+                    };
+                    for (deferred_action_record, torrent) in deferred_action_records.iter().zip(&torrents) {
+                        let Some(rule) = self.get_job_rule(job_name, torrent, tracker_domain_utils) else {
+                            continue;
+                        };
+                        let is_criteria_met = match Retention::is_retention_rule_name(deferred_action_record.action_entry().rule()) {
+                            true => {
+                                let torrent_links = TorrentLinks::new(FileUtils::get_file_links(&known_hardlinks, library_index_option.as_ref(), torrent.content_path())?);
+                                Retention::is_eligible(torrent, Some(&rule), Some(&torrent_links), hit_and_run_utils, tracker_domain_utils, &self.config)
+                            }
+                            false => handle_unlinked::receiver::Receiver::is_criteria_met(torrent, &rule, &known_hardlinks, library_index_option.as_ref(), &recent_uploads, &mut HashMap::new(), &self.config)?,
+                        };
+                        if !is_criteria_met {
+                            unmet_criteria_hashes.insert((job_name.to_string(), torrent.hash().to_string()));
+                        }
+                    }
+                }
+                "handle_unregistered" => {
+                    let unregistered_matcher = UnregisteredMatcher::new(jobs.handle_unregistered().unregistered_messages()).context("Failed to compile unregistered messages")?;
+                    let torrent_trackers = handle_unregistered::receiver::Receiver::get_torrent_trackers(self.torrent_manager.clone(), &torrents, &self.config).await?;
+                    for torrent in &torrents {
+                        let (Some(rule), Some(trackers)) = (self.get_job_rule(job_name, torrent, tracker_domain_utils), torrent_trackers.get(torrent.hash())) else {
+                            continue;
+                        };
+                        if !handle_unregistered::receiver::Receiver::is_criteria_met(torrent, trackers, &rule, &unregistered_matcher, jobs.handle_unregistered().state_filter()).await? {
+                            unmet_criteria_hashes.insert((job_name.to_string(), torrent.hash().to_string()));
+                        }
+                    }
+                }
+                "handle_errored" => {
+                    for torrent in &torrents {
+                        let Some(rule) = self.get_job_rule(job_name, torrent, tracker_domain_utils) else {
+                            continue;
+                        };
+                        if !handle_errored::receiver::Receiver::is_criteria_met(torrent, &rule, jobs.handle_errored().state_filter()) {
+                            unmet_criteria_hashes.insert((job_name.to_string(), torrent.hash().to_string()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(unmet_criteria_hashes)
+    }
+
+    /**
+     * Reason the deferred deletion of orphaned paths is dropped, None if every path is still orphaned and not protected
+     */
+    fn get_path_skip_reason(&self, action_entry: &ActionEntry, torrent_paths: &HashSet<PathBuf>, known_hardlinks_option: Option<&HashMap<(u64, u64), u64>>) -> Result<Option<String>, anyhow::Error> {
+        let base_rule = Rule::from_handle_orphaned(self.config.jobs().handle_orphaned());
+        for path_string in action_entry.paths() {
+            let path = Path::new(path_string.as_str());
+            let Ok(metadata) = fs::symlink_metadata(path) else {
+                return Ok(Some(format!("path doesn't exist anymore ({})", path_string)));
+            };
+            if !handle_orphaned::receiver::Receiver::is_orphaned_path(path, &metadata.file_type(), torrent_paths, known_hardlinks_option)? {
+                return Ok(Some(format!("path is not orphaned anymore ({})", path_string)));
+            }
+            let rule = RuleUtils::get_path_rule(path_string, &base_rule, self.config.jobs().handle_orphaned().overrides(), Category::DeferredActions);
+            if rule.protected() {
+                return Ok(Some(format!("path is protected now (rule {})", rule.name())));
+            }
+        }
+        Ok(None)
+    }
+
+    /**
+     * Current rule of the torrent for the job that queued the action, None if the job has no rules
+     */
+    fn get_job_rule(&self, job_name: &str, torrent: &Torrent, tracker_domain_utils: &TrackerDomainUtils) -> Option<Rule> {
+        let jobs = self.config.jobs();
+        let (base_rule, overrides) = match job_name {
+            "handle_unlinked" => (Rule::from_handle_unlinked(jobs.handle_unlinked()), jobs.handle_unlinked().overrides()),
+            "handle_unregistered" => (Rule::from_handle_unregistered(jobs.handle_unregistered()), jobs.handle_unregistered().overrides()),
+            "handle_errored" => (Rule::from_handle_errored(jobs.handle_errored()), jobs.handle_errored().overrides()),
+            _ => return None,
+        };
+        RuleUtils::get_torrent_rules(std::slice::from_ref(torrent), &base_rule, overrides, tracker_domain_utils, Category::DeferredActions).remove(torrent.hash())
+    }
+
+    /**
+     * Take action, a deferred action without hash is an orphaned path
+     */
+    async fn take_action(&self, action_entry: &ActionEntry) -> Result<(), anyhow::Error> {
+        match ActionType::from_str(action_entry.action())? {
+            ActionType::Test => Ok(()),
+            ActionType::Recheck => self.torrent_manager.recheck(&[action_entry.hash().to_string()]).await.context("Failed to recheck torrent"),
+            ActionType::Stop => self.torrent_manager.stop_torrent(action_entry.hash()).await.context("Failed to stop torrent"),
+            ActionType::Delete if action_entry.hash().is_empty() => {
+                for path_string in action_entry.paths() {
+                    let path = Path::new(path_string.as_str());
+                    if path.is_file() {
+                        fs::remove_file(path).map_err(|e| anyhow::anyhow!("Error deleting orphaned file ({}): {:#}", path.display(), e))?;
+                    } else if path.is_dir() {
+                        fs::remove_dir(path).map_err(|e| anyhow::anyhow!("Error deleting orphaned dir ({}): {:#}", path.display(), e))?;
+                    } else {
+                        anyhow::bail!("Path is neither file or dir: {}", path.display());
+                    }
+                }
+                Ok(())
+            }
+            ActionType::Delete => self.torrent_manager.delete_torrent(action_entry.hash(), action_entry.delete_files()).await.context("Failed to delete torrent"),
+        }
+    }
+
+    /**
+     * Quiet windows the job defers its actions in, including the ones of pipelines it runs in
+     */
+    fn get_job_quiet_windows(&self, job_name: &str) -> Result<QuietWindows, anyhow::Error> {
+        let mut quiet_windows = QuietWindows::from_config(self.config.quiet_hours(), self.get_job_quiet_hours(job_name))?;
+        for pipeline in self.config.pipelines().iter().filter(|pipeline| pipeline.jobs().iter().any(|pipeline_job_name| pipeline_job_name == job_name)) {
+            quiet_windows = quiet_windows.merge(&QuietWindows::from_config(&[], pipeline.quiet_hours())?);
+        }
+        Ok(quiet_windows)
+    }
+
+    fn get_job_quiet_hours(&self, job_name: &str) -> &[QuietHours] {
+        let jobs = self.config.jobs();
+        match job_name {
+            "handle_unlinked" => jobs.handle_unlinked().quiet_hours(),
+            "handle_unregistered" => jobs.handle_unregistered().quiet_hours(),
+            "handle_orphaned" => jobs.handle_orphaned().quiet_hours(),
+            "handle_tracker_errors" => jobs.handle_tracker_errors().quiet_hours(),
+            "handle_errored" => jobs.handle_errored().quiet_hours(),
+            "health_check_files" => jobs.health_check_files().quiet_hours(),
+            _ => &[],
+        }
+    }
+}
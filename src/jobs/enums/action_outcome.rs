@@ -0,0 +1,22 @@
+/**
+ * What happened to the action a job took on a torrent
+ */
+#[derive(PartialEq, Eq)]
+pub enum ActionOutcome {
+    /**
+     * Taken right away
+     */
+    Taken,
+    /**
+     * Taken right away and the torrent is deleted
+     */
+    Deleted,
+    /**
+     * Queued until the quiet hours end
+     */
+    Deferred,
+    /**
+     * Not taken since a hit-and-run requirement is not met or taken without removing the torrent (recheck), the strikes are kept
+     */
+    Skipped,
+}
@@ -1,5 +1,12 @@
+use std::fmt;
+
+/**
+ * Ordered from least to most destructive
+ */
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub enum ActionType {
     Test,
+    Recheck,
     Stop,
     Delete,
 }
@@ -8,9 +15,22 @@ impl ActionType {
     pub fn from_str(s: &str) -> Result<Self, anyhow::Error> {
         match s.to_lowercase().as_str() {
             "test" => Ok(ActionType::Test),
+            "recheck" => Ok(ActionType::Recheck),
             "stop" => Ok(ActionType::Stop),
             "delete" => Ok(ActionType::Delete),
             _ => anyhow::bail!("Unknown action type '{}'", s),
         }
     }
 }
+
+impl fmt::Display for ActionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action_type_str = match self {
+            ActionType::Test => String::from("test"),
+            ActionType::Recheck => String::from("recheck"),
+            ActionType::Stop => String::from("stop"),
+            ActionType::Delete => String::from("delete"),
+        };
+        write!(f, "{}", action_type_str)
+    }
+}
@@ -0,0 +1,24 @@
+/**
+ * What a pipeline does when one of its jobs fails
+ */
+#[derive(Clone, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /**
+     * Skip the remaining jobs of the pipeline
+     */
+    Abort,
+    /**
+     * Run the remaining jobs anyway
+     */
+    Continue,
+}
+
+impl FailurePolicy {
+    pub fn from_str(s: &str) -> Result<Self, anyhow::Error> {
+        match s.to_lowercase().as_str() {
+            "abort" => Ok(FailurePolicy::Abort),
+            "continue" => Ok(FailurePolicy::Continue),
+            _ => anyhow::bail!("Unknown pipeline on_failure '{}'", s),
+        }
+    }
+}
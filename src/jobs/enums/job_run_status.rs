@@ -0,0 +1,52 @@
+use std::fmt;
+
+/**
+ * Status of a run in job_runs
+ */
+#[derive(PartialEq, Eq, Debug)]
+pub enum JobRunStatus {
+    Running,
+    Success,
+    Failed,
+    /**
+     * Still running when the process stopped
+     */
+    Interrupted,
+}
+
+impl JobRunStatus {
+    pub fn from_str(s: &str) -> Result<Self, anyhow::Error> {
+        match s.to_lowercase().as_str() {
+            "running" => Ok(JobRunStatus::Running),
+            "success" => Ok(JobRunStatus::Success),
+            "failed" => Ok(JobRunStatus::Failed),
+            "interrupted" => Ok(JobRunStatus::Interrupted),
+            _ => anyhow::bail!("Unknown job run status '{}'", s),
+        }
+    }
+}
+
+impl fmt::Display for JobRunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let job_run_status_str = match self {
+            JobRunStatus::Running => String::from("running"),
+            JobRunStatus::Success => String::from("success"),
+            JobRunStatus::Failed => String::from("failed"),
+            JobRunStatus::Interrupted => String::from("interrupted"),
+        };
+        write!(f, "{}", job_run_status_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_status_is_parsed_back() {
+        for job_run_status in [JobRunStatus::Running, JobRunStatus::Success, JobRunStatus::Failed, JobRunStatus::Interrupted] {
+            assert_eq!(JobRunStatus::from_str(&job_run_status.to_string()).unwrap(), job_run_status);
+        }
+        assert!(JobRunStatus::from_str("unknown").is_err());
+    }
+}
@@ -1,2 +1,8 @@
+pub mod action_outcome;
 pub mod action_type;
+pub mod failure_policy;
+pub mod job_run_status;
+pub mod quiet_hours_mode;
+pub mod schedule_type;
+pub mod strike_policy_type;
 pub mod strike_type;
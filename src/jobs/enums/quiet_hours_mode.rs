@@ -0,0 +1,21 @@
+#[derive(Clone, PartialEq, Eq)]
+pub enum QuietHoursMode {
+    /**
+     * Jobs scan and strike, but actions are queued until the window ends
+     */
+    DeferActions,
+    /**
+     * Jobs don't start until the window ends
+     */
+    SkipRun,
+}
+
+impl QuietHoursMode {
+    pub fn from_str(s: &str) -> Result<Self, anyhow::Error> {
+        match s.to_lowercase().as_str() {
+            "defer_actions" => Ok(QuietHoursMode::DeferActions),
+            "skip_run" => Ok(QuietHoursMode::SkipRun),
+            _ => anyhow::bail!("Unknown quiet hours mode '{}'", s),
+        }
+    }
+}
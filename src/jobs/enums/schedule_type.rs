@@ -0,0 +1,27 @@
+use std::fmt;
+
+use croner::Cron;
+
+/**
+ * When a job runs
+ */
+#[derive(Clone)]
+pub enum ScheduleType {
+    /**
+     * hours after the start of the last successful run
+     */
+    Interval { hours: i64 },
+    /**
+     * At the times of the cron expression in the configured timezone
+     */
+    Cron(Box<Cron>),
+}
+
+impl fmt::Display for ScheduleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleType::Interval { hours } => write!(f, "interval of {}h", hours),
+            ScheduleType::Cron(cron) => write!(f, "cron {}", cron.as_str()),
+        }
+    }
+}
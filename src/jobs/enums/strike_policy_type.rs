@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::StrikePolicy;
+
+/**
+ * How strikes add up to the strike limit of a rule, parsed from the strike_policy of a job when the config loads
+ */
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(try_from = "StrikePolicy", into = "StrikePolicy")]
+pub enum StrikePolicyType {
+    /**
+     * required_strikes over min_strike_days consecutive calendar days, a day without a strike resets everything
+     */
+    #[default]
+    ConsecutiveDays,
+    /**
+     * Like ConsecutiveDays, but 1 missed run or 1 missed day in a row doesn't reset anything
+     */
+    Tolerant,
+    /**
+     * required_strikes within the last window_hours
+     */
+    SlidingWindow { window_hours: i64 },
+    /**
+     * Criteria met without interruption for at least min_hours between the first and the last strike
+     * A gap between 2 strikes that is longer than the schedule of the job (e.g. while the app was down) is an interruption
+     */
+    Continuous { min_hours: i64 },
+}
+
+impl TryFrom<StrikePolicy> for StrikePolicyType {
+    type Error = anyhow::Error;
+
+    fn try_from(strike_policy: StrikePolicy) -> Result<Self, Self::Error> {
+        match strike_policy.policy().to_lowercase().as_str() {
+            "consecutive_days" => Ok(StrikePolicyType::ConsecutiveDays),
+            "tolerant" => Ok(StrikePolicyType::Tolerant),
+            "sliding_window" => Ok(StrikePolicyType::SlidingWindow {
+                window_hours: strike_policy.window_hours(),
+            }),
+            "continuous" => Ok(StrikePolicyType::Continuous {
+                min_hours: strike_policy.continuous_hours(),
+            }),
+            _ => anyhow::bail!("Unknown strike policy '{}'", strike_policy.policy()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unknown_policy_fails_to_load() {
+        let strike_policy_type: Result<StrikePolicyType, serde_json::Error> = serde_json::from_value(json!({ "policy": "continous" }));
+        assert!(strike_policy_type.is_err_and(|e| e.to_string().contains("Unknown strike policy 'continous'")));
+    }
+
+    #[test]
+    fn policy_keeps_its_hours() {
+        let strike_policy_type: StrikePolicyType = serde_json::from_value(json!({ "policy": "continuous", "continuous_hours": 48 })).unwrap();
+        assert!(matches!(strike_policy_type, StrikePolicyType::Continuous { min_hours: 48 }));
+        assert_eq!(serde_json::to_value(strike_policy_type).unwrap(), json!({ "policy": "continuous", "window_hours": 168, "continuous_hours": 48 }));
+    }
+}
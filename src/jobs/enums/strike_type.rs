@@ -5,6 +5,7 @@ pub enum StrikeType {
     HandleUnlinked,
     HandleUnregistered,
     HandleOrphaned,
+    HandleErrored,
 }
 
 impl fmt::Display for StrikeType {
@@ -13,6 +14,7 @@ impl fmt::Display for StrikeType {
             StrikeType::HandleUnlinked => String::from("handle_unlinked"),
             StrikeType::HandleUnregistered => String::from("handle_unregistered"),
             StrikeType::HandleOrphaned => String::from("handle_orphaned"),
+            StrikeType::HandleErrored => String::from("handle_errored"),
         };
         write!(f, "{}", strike_type_str)
     }
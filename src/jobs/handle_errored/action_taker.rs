@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+
+use crate::{
+    info,
+    jobs::{
+        enums::{action_outcome::ActionOutcome, action_type::ActionType},
+        models::rule::Rule,
+        utils::{
+            action_utils::{ActionEntry, ActionUtils},
+            hit_and_run_utils::HitAndRunUtils,
+            tracker_domain_utils::TrackerDomainUtils,
+        },
+    },
+    logger::enums::category::Category,
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+};
+
+pub struct ActionTaker;
+
+impl ActionTaker {
+    /**
+     * Take action, files are never deleted since they are usually missing or belong to something else
+     */
+    pub async fn take_action(
+        torrent_manager: Arc<TorrentManager>,
+        action_utils: &mut ActionUtils,
+        torrent: &Torrent,
+        rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<ActionOutcome, anyhow::Error> {
+        let action_type = ActionType::from_str(rule.action())?;
+        // Hit-and-run requirements override every job config
+        if action_type > ActionType::Recheck
+            && let Some(risk_str) = hit_and_run_utils.get_risk_str(torrent, tracker_domain_utils)
+        {
+            info!(Category::HandleErrored, "Action: Skipped, hit-and-run requirement not met ({})", risk_str);
+            let action_entry = ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), rule.name());
+            action_utils.record_skipped(&[action_entry], &format!("hit-and-run requirement not met ({})", risk_str))?;
+            return Ok(ActionOutcome::Skipped);
+        }
+        let action_entry = ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), rule.name());
+        if action_type != ActionType::Test && action_utils.is_deferred(std::slice::from_ref(&action_entry))? {
+            return Ok(ActionOutcome::Deferred);
+        }
+        let result = match action_type {
+            ActionType::Test => {
+                info!(Category::HandleErrored, "Action: Test");
+                Ok(())
+            }
+            ActionType::Recheck => {
+                info!(Category::HandleErrored, "Action: Rechecking torrent");
+                torrent_manager.recheck(&[torrent.hash().to_string()]).await.context("Failed to recheck torrent")
+            }
+            ActionType::Stop => {
+                info!(Category::HandleErrored, "Action: Stopping torrent");
+                torrent_manager.stop_torrent(torrent.hash()).await.context("Failed to stop torrent")
+            }
+            ActionType::Delete => {
+                info!(Category::HandleErrored, "Action: Deleting torrent but keeping files");
+                torrent_manager.delete_torrent(torrent.hash(), false).await.context("Failed to delete torrent")
+            }
+        };
+        action_utils.record(&[action_entry], &result)?;
+        result?;
+        // A rechecked torrent is kept, so its strikes are kept too and the action is taken again if it stays errored
+        match action_type {
+            ActionType::Delete => Ok(ActionOutcome::Deleted),
+            ActionType::Recheck => Ok(ActionOutcome::Skipped),
+            _ => Ok(ActionOutcome::Taken),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{
+        config::Config,
+        jobs::{
+            models::quiet_windows::QuietWindows,
+            utils::action_utils::{ActionFilter, ActionRecord},
+        },
+        torrent_clients::{adapters::qbittorrent::Qbittorrent, enums::any_client::AnyClient},
+        utils::db_manager::DbManager,
+    };
+
+    /**
+     * Torrent client that answers every request with 200
+     */
+    async fn get_torrent_manager() -> Arc<TorrentManager> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = [0; 4096];
+                let _ = stream.read(&mut buffer).await;
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\nOk.").await;
+            }
+        });
+        Arc::new(TorrentManager::new(AnyClient::Qbittorrent(Qbittorrent::new(&base_url, "", "").unwrap())))
+    }
+
+    fn get_rule(action: &str) -> Rule {
+        Rule::from_handle_errored(Config::from_test_fields(json!({ "jobs": { "handle_errored": { "action": action } } })).jobs().handle_errored())
+    }
+
+    async fn take_action(action: &str, hit_and_run: serde_json::Value) -> (ActionOutcome, Vec<ActionRecord>) {
+        let db_manager = DbManager::open_in_memory().unwrap();
+        let mut action_utils = ActionUtils::new(db_manager, Category::HandleErrored, QuietWindows::default());
+        let torrent = Torrent::from_test_fields(json!({ "hash": "a", "name": "a", "state": "missingFiles", "tracker": "https://tracker.example.org/announce" }));
+        let hit_and_run_utils = HitAndRunUtils::new(&Config::from_test_fields(json!({ "hit_and_run": hit_and_run })));
+        let action_outcome = ActionTaker::take_action(get_torrent_manager().await, &mut action_utils, &torrent, &get_rule(action), &hit_and_run_utils, &TrackerDomainUtils::default())
+            .await
+            .unwrap();
+        (action_outcome, action_utils.get_actions(&ActionFilter::default()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn rechecked_torrent_is_skipped_to_keep_its_strikes() {
+        let (action_outcome, action_records) = take_action("recheck", json!({})).await;
+        assert!(action_outcome == ActionOutcome::Skipped);
+        assert_eq!(action_records.iter().map(|action_record| action_record.action()).collect::<Vec<&str>>(), vec!["recheck"]);
+    }
+
+    #[tokio::test]
+    async fn stopped_and_deleted_torrents_are_taken() {
+        let (action_outcome, _) = take_action("stop", json!({})).await;
+        assert!(action_outcome == ActionOutcome::Taken);
+        let (action_outcome, action_records) = take_action("delete", json!({})).await;
+        assert!(action_outcome == ActionOutcome::Deleted);
+        assert!(action_records.iter().all(|action_record| action_record.success()));
+    }
+
+    #[tokio::test]
+    async fn hit_and_run_requirement_skips_delete_but_not_recheck() {
+        let hit_and_run = json!({ "example.org": { "min_seeding_days": 7.0 } });
+        let (action_outcome, action_records) = take_action("delete", hit_and_run.clone()).await;
+        assert!(action_outcome == ActionOutcome::Skipped);
+        assert!(action_records.iter().all(|action_record| !action_record.success() && action_record.error().starts_with("Skipped, hit-and-run")));
+        assert_eq!(action_records.len(), 1);
+        let (_, action_records) = take_action("recheck", hit_and_run).await;
+        assert_eq!(action_records.len(), 1);
+    }
+}
@@ -0,0 +1,5 @@
+mod action_taker;
+mod notifier;
+pub mod receiver;
+pub mod runner;
+mod striker;
@@ -0,0 +1,95 @@
+use crate::{
+    jobs::{
+        models::rule::Rule,
+        utils::{hit_and_run_utils::HitAndRunUtils, tracker_domain_utils::TrackerDomainUtils},
+    },
+    torrent_clients::models::torrent::Torrent,
+    utils::discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+};
+
+pub struct Notifier;
+
+impl Notifier {
+    /**
+     * Send notification
+     */
+    pub async fn send_notification(
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        torrent: &Torrent,
+        missing_files: &[String],
+        rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let total_size_gib = format!("{:.2}", (*torrent.total_size() / 1024 / 1024) as f32 / 1024.0);
+        let total_size_gb = format!("{:.2}", (*torrent.total_size() / 1000 / 1000) as f32 / 1000.0);
+
+        let missing_files_str = match missing_files.len() {
+            0 => String::from("None, all files exist on disk"),
+            _ => {
+                let mut missing_files_str = format!("{} files:\n{}", missing_files.len(), missing_files.iter().take(5).cloned().collect::<Vec<String>>().join("\n"));
+                if missing_files.len() > 5 {
+                    missing_files_str.push_str(format!("\nand {} more", missing_files.len() - 5).as_str());
+                }
+                missing_files_str
+            }
+        };
+
+        let mut fields: Vec<EmbedField> = vec![
+            EmbedField {
+                name: String::from("State"),
+                value: torrent.state().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Action"),
+                value: rule.action().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Rule"),
+                value: rule.name().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Category"),
+                value: torrent.category().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Tags"),
+                value: torrent.tags().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Total Size"),
+                value: format!("{total_size_gib}GiB | {total_size_gb}GB"),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Save path"),
+                value: torrent.save_path().chars().take(1024).collect(),
+                inline: false,
+            },
+            EmbedField {
+                name: String::from("Missing files"),
+                value: missing_files_str.chars().take(1024).collect(),
+                inline: false,
+            },
+        ];
+
+        if let Some(risk_str) = hit_and_run_utils.get_risk_str(torrent, tracker_domain_utils) {
+            fields.push(EmbedField {
+                name: String::from("Hit and run risk"),
+                value: format!("{} (stop/delete is skipped)", risk_str),
+                inline: false,
+            });
+        }
+
+        discord_webhook_utils.send_webhook_embed(torrent.name(), "Found errored torrent", fields).await
+    }
+}
@@ -0,0 +1,117 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::Context;
+
+use crate::{
+    config::StateFilter,
+    debug,
+    jobs::{models::rule::Rule, utils::state_filter_utils::StateFilterUtils},
+    logger::enums::category::Category,
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    trace, warn,
+};
+
+pub struct Receiver;
+
+impl Receiver {
+    /**
+     * Get torrents and if they match criteria
+     * Returns: HashMap<String, (Torrent, bool)> | HashMap<torrent_hash, (Torrent, is_criteria_met))>
+     */
+    pub fn get_torrents_criteria(torrents: &Vec<Torrent>, torrent_rules: &HashMap<String, Rule>, state_filter: &StateFilter) -> HashMap<String, (Torrent, bool)> {
+        let mut torrents_criteria: HashMap<String, (Torrent, bool)> = HashMap::new();
+        for torrent in torrents {
+            let Some(rule) = torrent_rules.get(torrent.hash()) else {
+                warn!(Category::HandleErrored, "Didn't find rule for torrent: ({}) {}", torrent.hash(), torrent.name());
+                continue;
+            };
+            torrents_criteria.insert(torrent.hash().to_string(), (torrent.clone(), Receiver::is_criteria_met(torrent, rule, state_filter)));
+        }
+
+        torrents_criteria
+    }
+
+    /**
+     * Files of the torrent that don't exist on disk
+     */
+    pub async fn get_missing_files(torrent_manager: Arc<TorrentManager>, torrent: &Torrent) -> Result<Vec<String>, anyhow::Error> {
+        let torrent_files = torrent_manager
+            .get_torrent_files(torrent.hash())
+            .await
+            .context(format!("Failed to get files for torrent: ({}) {}", torrent.hash(), torrent.name()))?;
+
+        let mut missing_files: Vec<String> = Vec::new();
+        for torrent_file in torrent_files {
+            let path_str = format!("{}/{}", torrent.save_path(), torrent_file.name());
+            if !Path::new(&path_str).try_exists().context(format!("Failed to check if file exists: {}", path_str))? {
+                missing_files.push(path_str);
+            }
+        }
+
+        Ok(missing_files)
+    }
+
+    /**
+     * Is criteria met
+     */
+    pub fn is_criteria_met(torrent: &Torrent, rule: &Rule, state_filter: &StateFilter) -> bool {
+        // Errored
+        if !torrent.state().is_errored() {
+            trace!(Category::HandleErrored, "Torrent doesn't meet criteria (state {}): ({}) {}", torrent.state(), torrent.hash(), torrent.name());
+            return false;
+        }
+        // State filter
+        if !StateFilterUtils::is_allowed(torrent.state(), state_filter) {
+            trace!(Category::HandleErrored, "Torrent doesn't meet criteria (state {} is filtered): ({}) {}", torrent.state(), torrent.hash(), torrent.name());
+            return false;
+        }
+        // Protection tag
+        if rule.is_protected(torrent.tags()) {
+            trace!(Category::HandleErrored, "Torrent doesn't meet criteria (protected by rule {}): ({}) {}", rule.name(), torrent.hash(), torrent.name());
+            return false;
+        }
+        // All good
+        debug!(Category::HandleErrored, "Torrent meets criteria (state {}): ({}) {}", torrent.state(), torrent.hash(), torrent.name());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::Config;
+
+    fn get_rule() -> Rule {
+        Rule::from_handle_errored(Config::default().jobs().handle_errored())
+    }
+
+    #[test]
+    fn only_errored_torrents_meet_criteria() {
+        let rule = get_rule();
+        let state_filter = StateFilter::default();
+        assert!(Receiver::is_criteria_met(&Torrent::from_test_fields(json!({ "hash": "a", "state": "error" })), &rule, &state_filter));
+        assert!(Receiver::is_criteria_met(&Torrent::from_test_fields(json!({ "hash": "b", "state": "missingFiles" })), &rule, &state_filter));
+        assert!(!Receiver::is_criteria_met(&Torrent::from_test_fields(json!({ "hash": "c", "state": "stoppedUP" })), &rule, &state_filter));
+    }
+
+    #[test]
+    fn state_filter_and_protection_tag_are_applied() {
+        let rule = get_rule();
+        let state_filter: StateFilter = serde_json::from_value(json!({ "exclude": ["error"] })).unwrap();
+        assert!(!Receiver::is_criteria_met(&Torrent::from_test_fields(json!({ "hash": "a", "state": "error" })), &rule, &state_filter));
+        assert!(Receiver::is_criteria_met(&Torrent::from_test_fields(json!({ "hash": "b", "state": "missingFiles" })), &rule, &state_filter));
+        let torrent = Torrent::from_test_fields(json!({ "hash": "c", "state": "missingFiles", "tags": "protected-errored" }));
+        assert!(!Receiver::is_criteria_met(&torrent, &rule, &StateFilter::default()));
+    }
+
+    #[test]
+    fn torrents_without_rule_are_left_out() {
+        let torrents = vec![Torrent::from_test_fields(json!({ "hash": "a", "state": "error" })), Torrent::from_test_fields(json!({ "hash": "b", "state": "error" }))];
+        let torrent_rules = HashMap::from([(String::from("a"), get_rule())]);
+        let torrents_criteria = Receiver::get_torrents_criteria(&torrents, &torrent_rules, &StateFilter::default());
+        assert_eq!(torrents_criteria.len(), 1);
+        assert!(torrents_criteria.get("a").is_some_and(|(_, is_criteria_met)| *is_criteria_met));
+    }
+}
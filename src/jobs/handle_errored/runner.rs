@@ -0,0 +1,178 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use reqwest::Url;
+
+use crate::{
+    config::Config,
+    debug, info,
+    jobs::{
+        deferred_actions::notifier::DeferralNotifier,
+        enums::{action_outcome::ActionOutcome, strike_type::StrikeType},
+        handle_errored::{action_taker::ActionTaker, notifier::Notifier, receiver::Receiver, striker::Striker},
+        models::{job_run_stats::JobRunStats, pipeline_context::PipelineContext, quiet_windows::QuietWindows, rule::Rule},
+        utils::{action_utils::ActionUtils, hit_and_run_utils::HitAndRunUtils, rule_utils::RuleUtils, strike_utils::StrikeUtils, tracker_domain_utils::TrackerDomainUtils},
+    },
+    logger::enums::category::Category,
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    utils::{date_utils::DateUtils, db_manager::DbManager, discord_webhook_utils::DiscordWebhookUtils},
+    warn,
+};
+
+pub struct HandleErrored {
+    torrent_manager: Arc<TorrentManager>,
+    db_manager: DbManager,
+    config: Config,
+}
+
+impl HandleErrored {
+    pub fn new(torrent_manager: Arc<TorrentManager>, db_manager: DbManager, config: Config) -> Self {
+        Self { torrent_manager, db_manager, config }
+    }
+
+    /**
+     * Run
+     */
+    pub async fn run(&self, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
+        let discord_webhook_url: Option<Url> = match self.config.notification().discord_webhook_url().len() > 1 {
+            true => Some(Url::parse(self.config.notification().discord_webhook_url()).context("Failed to parse discord_webhook_url")?),
+            false => None,
+        };
+        let mut discord_webhook_utils = DiscordWebhookUtils::new(discord_webhook_url);
+
+        // Login
+        self.torrent_manager.login().await.context("Failed to login to torrent client")?;
+
+        // Get torrents from torrent client
+        debug!(Category::HandleErrored, "Getting torrents...");
+        let torrents = pipeline_context.get_torrents(&self.torrent_manager).await?;
+        debug!(Category::HandleErrored, "Received {} torrents", torrents.len());
+
+        // Resolve tracker domains, errored torrents usually have no working tracker for tracker overrides and the hit-and-run policies
+        debug!(Category::HandleErrored, "Resolving tracker domains...");
+        let hit_and_run_utils = HitAndRunUtils::new(&self.config);
+        let mut tracker_domain_utils = TrackerDomainUtils::default();
+        let errored_torrents: Vec<Torrent> = torrents.iter().filter(|torrent| torrent.state().is_errored()).cloned().collect();
+        tracker_domain_utils.resolve_tracker_domains(&self.torrent_manager, &errored_torrents).await?;
+        debug!(Category::HandleErrored, "Done resolving tracker domains");
+
+        // Resolve rules
+        let base_rule = Rule::from_handle_errored(self.config.jobs().handle_errored());
+        let torrent_rules: HashMap<String, Rule> = RuleUtils::get_torrent_rules(&torrents, &base_rule, self.config.jobs().handle_errored().overrides(), &tracker_domain_utils, Category::HandleErrored);
+
+        // Get torrents with criteria
+        debug!(Category::HandleErrored, "Checking torrents for criteria...");
+        let torrents_criteria: HashMap<String, (Torrent, bool)> = Receiver::get_torrents_criteria(&torrents, &torrent_rules, self.config.jobs().handle_errored().state_filter());
+        debug!(Category::HandleErrored, "Done checking torrents for criteria");
+
+        let criteria_met_count = torrents_criteria.values().filter(|(_, is_criteria_met)| *is_criteria_met).count();
+        info!(Category::HandleErrored, "{} torrents meet criteria", criteria_met_count);
+
+        // Striking
+        debug!(Category::HandleErrored, "Striking torrents...");
+        let mut strike_utils = StrikeUtils::new(self.db_manager.clone(), self.config.jobs().handle_errored().strike_policy().clone()).with_job_schedule(pipeline_context.job_schedule());
+        let limit_reached_torrents = Striker::strike_torrents(&mut strike_utils, &torrents_criteria, &torrent_rules)?;
+        debug!(Category::HandleErrored, "Done striking torrents");
+
+        info!(Category::HandleErrored, "{} torrents that meet criteria have reached their strike limits", limit_reached_torrents.len(),);
+        let job_run_stats = JobRunStats::new(torrents.len(), criteria_met_count, limit_reached_torrents.len());
+
+        // Files are never deleted, so cross-seeds don't have to be grouped
+        let quiet_windows = pipeline_context.get_quiet_windows(QuietWindows::from_config(self.config.quiet_hours(), self.config.jobs().handle_errored().quiet_hours()).context("Failed to get quiet hours")?);
+        let mut action_utils = ActionUtils::new(self.db_manager.clone(), Category::HandleErrored, quiet_windows);
+        // Torrents skipped for a hit-and-run requirement or rechecked keep their strikes
+        let mut acted_hashes: HashSet<String> = HashSet::new();
+        for torrent in &limit_reached_torrents {
+            let Some(rule) = torrent_rules.get(torrent.hash()) else {
+                warn!(Category::HandleErrored, "Didn't find rule for torrent: ({}) {}", torrent.hash(), torrent.name());
+                continue;
+            };
+            let missing_files: Vec<String> = Receiver::get_missing_files(self.torrent_manager.clone(), torrent).await?;
+
+            // Log
+            info!(
+                Category::HandleErrored,
+                "Torrent errored (state {}, {} missing files, rule {}): {}",
+                torrent.state(),
+                missing_files.len(),
+                rule.name(),
+                torrent.name()
+            );
+
+            // Notification
+            if *self.config.notification().on_job_action() {
+                Notifier::send_notification(&mut discord_webhook_utils, torrent, &missing_files, rule, &hit_and_run_utils, &tracker_domain_utils)
+                    .await
+                    .context("Failed to send notification")?;
+            }
+
+            // Take action
+            let action_outcome = ActionTaker::take_action(self.torrent_manager.clone(), &mut action_utils, torrent, rule, &hit_and_run_utils, &tracker_domain_utils).await?;
+            if action_outcome != ActionOutcome::Skipped {
+                acted_hashes.insert(torrent.hash().to_string());
+            }
+        }
+
+        // Deferred actions
+        if let Some((deferred_count, deferral_end)) = action_utils.get_deferred() {
+            info!(
+                Category::HandleErrored,
+                "{} actions deferred until {} (quiet hours)",
+                deferred_count,
+                DateUtils::convert_utc_datetime_to_local_string(&deferral_end)
+            );
+            if *self.config.notification().on_job_action() {
+                DeferralNotifier::send_deferral_notification(&mut discord_webhook_utils, &Category::HandleErrored.to_string(), deferred_count, &deferral_end)
+                    .await
+                    .context("Failed to send deferral notification")?;
+            }
+        }
+
+        // Clean db
+        debug!(Category::HandleErrored, "Cleaning db...");
+        self.clean_db(&mut strike_utils, &torrents_criteria, &acted_hashes)?;
+        debug!(Category::HandleErrored, "Cleaned db");
+
+        // Logout
+        self.torrent_manager.logout().await.context("Failed to logout of torrent client")?;
+
+        Ok(job_run_stats)
+    }
+
+    /**
+     * Clean db
+     */
+    fn clean_db(&self, strike_utils: &mut StrikeUtils, torrents_criteria: &HashMap<String, (Torrent, bool)>, acted_hashes: &HashSet<String>) -> Result<(), anyhow::Error> {
+        let mut hashes_to_remove: Vec<String> = Vec::new();
+        let mut hashes_to_reset: Vec<String> = Vec::new();
+
+        // Torrents that reached limit and were handled, rechecked torrents keep their strikes
+        hashes_to_remove.extend(acted_hashes.iter().cloned());
+
+        let strike_records = strike_utils.get_strikes(&StrikeType::HandleErrored, None).context("Failed to get all strikes for HandleErrored")?;
+        for strike_record in strike_records {
+            match torrents_criteria.get(strike_record.hash()) {
+                // Check for stuff that doesn't meet criteria
+                Some((_, is_criteria_met)) => {
+                    if !*is_criteria_met {
+                        hashes_to_reset.push(strike_record.hash().to_string());
+                    }
+                }
+                // Check for stuff that doesn't exist in torrents anymore
+                None => {
+                    hashes_to_remove.push(strike_record.hash().to_string());
+                }
+            }
+        }
+
+        debug!(Category::HandleErrored, "Deleting {} hashes, resetting {} hashes", hashes_to_remove.len(), hashes_to_reset.len());
+
+        strike_utils.delete(StrikeType::HandleErrored, &hashes_to_remove).context("Failed to delete hashes")?;
+        strike_utils.reset(StrikeType::HandleErrored, &hashes_to_reset).context("Failed to reset hashes")?;
+
+        Ok(())
+    }
+}
@@ -1,36 +1,55 @@
 use std::{fs, path::Path};
 
-use crate::{config::Config, info, jobs::enums::action_type::ActionType, logger::enums::category::Category, warn};
+use crate::{
+    info,
+    jobs::{
+        enums::action_type::ActionType,
+        models::rule::Rule,
+        utils::action_utils::{ActionEntry, ActionUtils},
+    },
+    logger::enums::category::Category,
+    warn,
+};
 
 pub struct ActionTaker;
 
 impl ActionTaker {
     /**
-     * Take action
+     * Take action, orphaned paths have no torrent so hash and tracker are recorded empty
      */
-    pub fn take_action(path: &Path, config: &Config) -> Result<(), anyhow::Error> {
-        let action_type = ActionType::from_str(config.jobs().handle_orphaned().action())?;
+    pub fn take_action(action_utils: &mut ActionUtils, path: &Path, rule: &Rule) -> Result<(), anyhow::Error> {
+        let action_type = ActionType::from_str(rule.action())?;
+        let name = path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+        let action_entry = ActionEntry::new(&action_type.to_string(), "", &name, "", vec![path.display().to_string()], rule.name());
         match action_type {
             ActionType::Test => {
                 info!(Category::HandleOrphaned, "Action: Test");
+                action_utils.record(&[action_entry], &Ok(()))?;
+            }
+            ActionType::Recheck => {
+                warn!(Category::HandleOrphaned, "Recheck action not supported on orphaned files since files cannot be rechecked");
             }
             ActionType::Stop => {
                 warn!(Category::HandleOrphaned, "Stop action not supported on orphaned files since files cannot be stopped");
             }
             ActionType::Delete => {
-                if path.is_file() {
+                if action_utils.is_deferred(std::slice::from_ref(&action_entry))? {
+                    return Ok(());
+                }
+                let (result, action_entry) = if path.is_file() {
                     info!(Category::HandleOrphaned, "Action: Delete (file)");
-                    if let Err(e) = fs::remove_file(path) {
-                        anyhow::bail!("Error deleting orphaned file ({}): {:#}", path.display(), e);
-                    }
+                    let size = fs::metadata(path).map(|metadata| metadata.len() as i64).unwrap_or(0);
+                    let result = fs::remove_file(path).map_err(|e| anyhow::anyhow!("Error deleting orphaned file ({}): {:#}", path.display(), e));
+                    (result, action_entry.with_bytes_reclaimed(size))
                 } else if path.is_dir() {
                     info!(Category::HandleOrphaned, "Action: Delete (folder)");
-                    if let Err(e) = fs::remove_dir(path) {
-                        anyhow::bail!("Error deleting orphaned dir ({}): {:#}", path.display(), e);
-                    }
+                    let result = fs::remove_dir(path).map_err(|e| anyhow::anyhow!("Error deleting orphaned dir ({}): {:#}", path.display(), e));
+                    (result, action_entry)
                 } else {
-                    anyhow::bail!("Path is neither file or dir: {}", path.display());
-                }
+                    (Err(anyhow::anyhow!("Path is neither file or dir: {}", path.display())), action_entry)
+                };
+                action_utils.record(&[action_entry], &result)?;
+                result?;
             }
         }
 
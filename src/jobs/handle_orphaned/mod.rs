@@ -1,5 +1,5 @@
 mod action_taker;
 mod notifier;
-mod receiver;
+pub mod receiver;
 pub mod runner;
 mod striker;
@@ -1,12 +1,15 @@
 use std::{fs, path::Path};
 
 use anyhow::Context;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Utc};
 
 use crate::{
-    config::Config,
+    jobs::models::rule::Rule,
     logger::enums::category::Category,
-    utils::discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+    utils::{
+        date_utils::DateUtils,
+        discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+    },
     warn,
 };
 
@@ -16,7 +19,7 @@ impl Notifier {
     /**
      * Send notification
      */
-    pub async fn send_notification(discord_webhook_utils: &mut DiscordWebhookUtils, path_str: &str, path: &Path, config: &Config) -> Result<(), anyhow::Error> {
+    pub async fn send_notification(discord_webhook_utils: &mut DiscordWebhookUtils, path_str: &str, path: &Path, rule: &Rule) -> Result<(), anyhow::Error> {
         if !discord_webhook_utils.is_notifications_enabled() {
             return Ok(());
         }
@@ -25,8 +28,8 @@ impl Notifier {
         let file_size_gb_string = format!("{:.2}GB", (metadata.len() / 1000 / 1000) as f32 / 1000.0);
         let modified_time = metadata.modified().context("Failed to get file modified SystemTime")?;
 
-        let modified_time: DateTime<Local> = modified_time.into();
-        let modified_time: String = modified_time.format("%Y-%m-%d %H:%M:%S").to_string();
+        let modified_time: DateTime<Utc> = modified_time.into();
+        let modified_time: String = DateUtils::convert_utc_datetime_to_local_string(&modified_time);
 
         let description = if path.is_file() {
             "Found orphaned **file**"
@@ -40,7 +43,12 @@ impl Notifier {
         let fields: Vec<EmbedField> = vec![
             EmbedField {
                 name: String::from("Action"),
-                value: config.jobs().handle_orphaned().action().to_string(),
+                value: rule.action().to_string(),
+                inline: false,
+            },
+            EmbedField {
+                name: String::from("Rule"),
+                value: rule.name().to_string(),
                 inline: false,
             },
             EmbedField {
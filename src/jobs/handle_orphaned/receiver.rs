@@ -1,6 +1,6 @@
 use std::{
-    collections::HashSet,
-    fs,
+    collections::{HashMap, HashSet},
+    fs::{self, FileType},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -8,27 +8,23 @@ use std::{
 use anyhow::Context;
 use walkdir::WalkDir;
 
-use crate::{debug, info, jobs::utils::file_utils::FileUtils, logger::enums::category::Category, torrent_clients::torrent_manager::TorrentManager, warn};
+use crate::{
+    debug, info,
+    jobs::utils::file_utils::FileUtils,
+    logger::enums::category::Category,
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    warn,
+};
 
 pub struct Receiver;
 
 impl Receiver {
     /**
      * Get all paths that are not in torrent_paths
+     * Files with external hardlinks are only protected if known_hardlinks_option is set (protect_external_hardlinks)
      * Returns HashSet of path strings
      */
-    pub async fn get_orphaned_path_strings(torrent_paths: &HashSet<PathBuf>, torrents_path: &str, protect_external_hardlinks: bool) -> Result<HashSet<String>, anyhow::Error> {
-        // Get known_hardlinks only if protect_external_hardlinks is true
-        let known_hardlinks_option = protect_external_hardlinks
-            .then(|| {
-                debug!(Category::HandleOrphaned, "Getting known torrent hardlinks...");
-                FileUtils::get_known_hardlinks(torrents_path)
-            })
-            .transpose()?
-            .inspect(|kh| {
-                debug!(Category::HandleOrphaned, "Found {} unique files ({} total) in torrent folder", kh.len(), kh.values().sum::<u64>());
-            });
-
+    pub async fn get_orphaned_path_strings(torrent_paths: &HashSet<PathBuf>, torrents_path: &str, known_hardlinks_option: Option<&HashMap<(u64, u64), u64>>) -> Result<HashSet<String>, anyhow::Error> {
         // Get paths not present in any torrents
         debug!(Category::HandleOrphaned, "Getting orphaned paths (files/folders that are not part of any torrent)...");
         let mut orphaned_path_strings: HashSet<String> = HashSet::new();
@@ -36,44 +32,7 @@ impl Receiver {
             let entry_result = entry.context("Failed to get entry_result")?;
             let path = entry_result.path();
 
-            if torrent_paths.contains(path) {
-                continue;
-            }
-
-            let file_type = entry_result.file_type();
-            let mut is_orphan = false;
-
-            // Check for file
-            if file_type.is_file() {
-                if protect_external_hardlinks {
-                    let path_str = path.to_str().ok_or(anyhow::anyhow!("Failed to get string from path (may due to non-UTF8 path: {:?}", path))?;
-                    let known_hardlinks = known_hardlinks_option.as_ref().ok_or(anyhow::anyhow!("known_hardlinks_option is None which should be impossible"))?;
-
-                    let has_external_hardlinks = FileUtils::has_external_hardlinks(known_hardlinks, path_str).context("get_orphaned_path_strings: Failed to get external hardlinks")?;
-                    if has_external_hardlinks {
-                        debug!(Category::HandleOrphaned, "Ignoring path (has external hardlinks) {}", path_str);
-                    } else {
-                        is_orphan = true;
-                    }
-                }
-                // Check for external hardlinks
-                else {
-                    is_orphan = true;
-                }
-            }
-            // Check for empty dir
-            else if file_type.is_dir() {
-                let mut entries = fs::read_dir(path).context("Failed to read dir")?;
-                if entries.next().is_none() {
-                    is_orphan = true;
-                }
-            }
-            // Handle edge case not file or dir (should not happen)
-            else {
-                anyhow::bail!("path is neither file or dir: {:?}", path);
-            }
-
-            if is_orphan {
+            if Receiver::is_orphaned_path(path, &entry_result.file_type(), torrent_paths, known_hardlinks_option)? {
                 if let Some(path_str) = path.to_str() {
                     debug!(Category::HandleOrphaned, "Path is orphaned: {}", path_str);
                     orphaned_path_strings.insert(path_str.to_string());
@@ -88,14 +47,43 @@ impl Receiver {
     }
 
     /**
-     * Get dirs & files (including content) of all torrents
+     * True if the path is not part of any torrent and is a file or an empty dir
+     * Files with external hardlinks are only protected if known_hardlinks_option is set (protect_external_hardlinks)
      */
-    pub async fn get_torrent_paths(torrent_manager: Arc<TorrentManager>) -> Result<HashSet<PathBuf>, anyhow::Error> {
-        // Get torrents from torrent client
-        debug!(Category::HandleOrphaned, "Getting torrents...");
-        let torrents = torrent_manager.get_all_torrents().await.context("Failed to get all torrents")?;
-        debug!(Category::HandleOrphaned, "Received {} torrents", torrents.len());
+    pub fn is_orphaned_path(path: &Path, file_type: &FileType, torrent_paths: &HashSet<PathBuf>, known_hardlinks_option: Option<&HashMap<(u64, u64), u64>>) -> Result<bool, anyhow::Error> {
+        if torrent_paths.contains(path) {
+            return Ok(false);
+        }
 
+        // Check for file
+        if file_type.is_file() {
+            // Check for external hardlinks
+            if let Some(known_hardlinks) = known_hardlinks_option {
+                let path_str = path.to_str().ok_or(anyhow::anyhow!("Failed to get string from path (may due to non-UTF8 path: {:?}", path))?;
+
+                let has_external_hardlinks = FileUtils::has_external_hardlinks(known_hardlinks, path_str).context("is_orphaned_path: Failed to get external hardlinks")?;
+                if has_external_hardlinks {
+                    debug!(Category::HandleOrphaned, "Ignoring path (has external hardlinks) {}", path_str);
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        // Check for empty dir
+        else if file_type.is_dir() {
+            let mut entries = fs::read_dir(path).context("Failed to read dir")?;
+            Ok(entries.next().is_none())
+        }
+        // Handle edge case not file or dir (should not happen)
+        else {
+            anyhow::bail!("path is neither file or dir: {:?}", path);
+        }
+    }
+
+    /**
+     * Get dirs & files (including content) of all torrents
+     */
+    pub async fn get_torrent_paths(torrent_manager: Arc<TorrentManager>, torrents: &[Torrent]) -> Result<HashSet<PathBuf>, anyhow::Error> {
         // Get torrent paths
         debug!(Category::HandleOrphaned, "Getting all paths in all torrents...");
         let mut torrent_paths: HashSet<PathBuf> = HashSet::new();
@@ -1,4 +1,8 @@
-use std::{collections::HashSet, path::Path, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
 
 use anyhow::Context;
 use reqwest::Url;
@@ -7,27 +11,36 @@ use crate::{
     config::Config,
     debug, info,
     jobs::{
+        deferred_actions::notifier::DeferralNotifier,
         enums::strike_type::StrikeType,
         handle_orphaned::{action_taker::ActionTaker, notifier::Notifier, receiver::Receiver, striker::Striker},
-        utils::strike_utils::StrikeUtils,
+        models::{job_run_stats::JobRunStats, pipeline_context::PipelineContext, quiet_windows::QuietWindows, rule::Rule},
+        utils::{action_utils::ActionUtils, rule_utils::RuleUtils, strike_utils::StrikeUtils},
     },
     logger::enums::category::Category,
     torrent_clients::torrent_manager::TorrentManager,
-    utils::discord_webhook_utils::DiscordWebhookUtils,
+    utils::{date_utils::DateUtils, db_manager::DbManager, discord_webhook_utils::DiscordWebhookUtils},
+    warn,
 };
 
 pub struct HandleOrphaned {
     torrent_manager: Arc<TorrentManager>,
+    db_manager: DbManager,
     config: Config,
     torrents_path: String,
 }
 
 impl HandleOrphaned {
-    pub fn new(torrent_manager: Arc<TorrentManager>, config: Config, torrents_path: String) -> Self {
-        Self { torrent_manager, config, torrents_path }
+    pub fn new(torrent_manager: Arc<TorrentManager>, db_manager: DbManager, config: Config, torrents_path: String) -> Self {
+        Self {
+            torrent_manager,
+            db_manager,
+            config,
+            torrents_path,
+        }
     }
 
-    pub async fn run(&self) -> Result<(), anyhow::Error> {
+    pub async fn run(&self, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
         let discord_webhook_url: Option<Url> = match self.config.notification().discord_webhook_url().len() > 1 {
             true => Some(Url::parse(self.config.notification().discord_webhook_url()).context("Failed to parse discord_webhook_url")?),
             false => None,
@@ -37,37 +50,96 @@ impl HandleOrphaned {
         // Login
         self.torrent_manager.login().await.context("Failed to login to torrent client")?;
 
+        // Get torrents from torrent client
+        debug!(Category::HandleOrphaned, "Getting torrents...");
+        let torrents = pipeline_context.get_torrents(&self.torrent_manager).await?;
+        debug!(Category::HandleOrphaned, "Received {} torrents", torrents.len());
+
         // Get torrent_paths
-        let torrent_paths = Receiver::get_torrent_paths(self.torrent_manager.clone()).await?;
+        let torrent_paths = Receiver::get_torrent_paths(self.torrent_manager.clone(), &torrents).await?;
+
+        // Get known_hardlinks only if protect_external_hardlinks is true
+        let known_hardlinks_option: Option<HashMap<(u64, u64), u64>> = match *self.config.jobs().handle_orphaned().protect_external_hardlinks() {
+            true => {
+                debug!(Category::HandleOrphaned, "Getting known torrent hardlinks...");
+                let known_hardlinks = pipeline_context.get_known_hardlinks(&self.torrents_path, self.config.paths().link_paths())?;
+                debug!(
+                    Category::HandleOrphaned,
+                    "Found {} unique files ({} total) in torrent folder and link paths",
+                    known_hardlinks.len(),
+                    known_hardlinks.values().sum::<u64>()
+                );
+                Some(known_hardlinks)
+            }
+            false => None,
+        };
 
         // Get orphaned_path_strings
-        let orphaned_path_strings = Receiver::get_orphaned_path_strings(&torrent_paths, &self.torrents_path, *self.config.jobs().handle_orphaned().protect_external_hardlinks()).await?;
+        let mut orphaned_path_strings = Receiver::get_orphaned_path_strings(&torrent_paths, &self.torrents_path, known_hardlinks_option.as_ref()).await?;
+        let orphaned_count = orphaned_path_strings.len();
+
+        // Resolve rules and skip protected paths
+        let base_rule = Rule::from_handle_orphaned(self.config.jobs().handle_orphaned());
+        let mut path_rules: HashMap<String, Rule> = HashMap::new();
+        for path_string in &orphaned_path_strings {
+            path_rules.insert(
+                path_string.clone(),
+                RuleUtils::get_path_rule(path_string, &base_rule, self.config.jobs().handle_orphaned().overrides(), Category::HandleOrphaned),
+            );
+        }
+        orphaned_path_strings.retain(|path_string| match path_rules.get(path_string) {
+            Some(rule) if rule.protected() => {
+                debug!(Category::HandleOrphaned, "Ignoring path (protected by rule {}) {}", rule.name(), path_string);
+                false
+            }
+            _ => true,
+        });
 
-        let mut strike_utils = StrikeUtils::new()?;
+        let mut strike_utils = StrikeUtils::new(self.db_manager.clone(), self.config.jobs().handle_orphaned().strike_policy().clone()).with_job_schedule(pipeline_context.job_schedule());
 
         // Strike orphaned paths
         debug!(Category::HandleOrphaned, "Striking orphaned paths...");
-        let limit_reached_path_strings = Striker::strike_paths(&mut strike_utils, orphaned_path_strings.iter().cloned().collect(), &self.config)?;
+        let limit_reached_path_strings = Striker::strike_paths(&mut strike_utils, orphaned_path_strings.iter().cloned().collect(), &path_rules)?;
         debug!(Category::HandleOrphaned, "Done striking paths");
 
         info!(Category::HandleOrphaned, "{} paths have reached their strike limits", limit_reached_path_strings.len());
+        let job_run_stats = JobRunStats::new(torrent_paths.len(), orphaned_count, limit_reached_path_strings.len());
 
         // Go through paths
+        let quiet_windows = pipeline_context.get_quiet_windows(QuietWindows::from_config(self.config.quiet_hours(), self.config.jobs().handle_orphaned().quiet_hours()).context("Failed to get quiet hours")?);
+        let mut action_utils = ActionUtils::new(self.db_manager.clone(), Category::HandleOrphaned, quiet_windows);
         for path_string in &limit_reached_path_strings {
             let path = Path::new(path_string.as_str());
+            let Some(rule) = path_rules.get(path_string) else {
+                warn!(Category::HandleOrphaned, "Didn't find rule for path: {}", path_string);
+                continue;
+            };
 
             // Log
-            info!(Category::HandleOrphaned, "Orphaned path: {}", path_string);
+            info!(Category::HandleOrphaned, "Orphaned path (rule {}): {}", rule.name(), path_string);
 
             // Notification
             if *self.config.notification().on_job_action() {
-                Notifier::send_notification(&mut discord_webhook_utils, path_string.as_str(), path, &self.config)
-                    .await
-                    .context("Failed to send notification")?;
+                Notifier::send_notification(&mut discord_webhook_utils, path_string.as_str(), path, rule).await.context("Failed to send notification")?;
             }
 
             // Take action
-            ActionTaker::take_action(path, &self.config)?;
+            ActionTaker::take_action(&mut action_utils, path, rule)?;
+        }
+
+        // Deferred actions
+        if let Some((deferred_count, deferral_end)) = action_utils.get_deferred() {
+            info!(
+                Category::HandleOrphaned,
+                "{} actions deferred until {} (quiet hours)",
+                deferred_count,
+                DateUtils::convert_utc_datetime_to_local_string(&deferral_end)
+            );
+            if *self.config.notification().on_job_action() {
+                DeferralNotifier::send_deferral_notification(&mut discord_webhook_utils, &Category::HandleOrphaned.to_string(), deferred_count, &deferral_end)
+                    .await
+                    .context("Failed to send deferral notification")?;
+            }
         }
 
         // Clean db
@@ -78,7 +150,7 @@ impl HandleOrphaned {
         // Logout
         self.torrent_manager.logout().await.context("Failed to logout of torrent client")?;
 
-        Ok(())
+        Ok(job_run_stats)
     }
 
     /**
@@ -86,6 +158,7 @@ impl HandleOrphaned {
      */
     fn clean_db(&self, strike_utils: &mut StrikeUtils, orphaned_path_strings: &HashSet<String>, limit_reached_path_strings: Vec<String>) -> Result<(), anyhow::Error> {
         let mut hashes_to_remove: Vec<String> = Vec::new();
+        let mut hashes_to_reset: Vec<String> = Vec::new();
 
         // Paths that reached limit and were handled from db
         hashes_to_remove.extend(limit_reached_path_strings);
@@ -94,13 +167,14 @@ impl HandleOrphaned {
         for strike_record in strike_records {
             // Paths that not orphaned anymore
             if !orphaned_path_strings.contains(strike_record.hash()) {
-                hashes_to_remove.push(strike_record.hash().to_string());
+                hashes_to_reset.push(strike_record.hash().to_string());
             }
         }
 
-        debug!(Category::HandleOrphaned, "Deleting {} paths from strike db", hashes_to_remove.len());
+        debug!(Category::HandleOrphaned, "Deleting {} paths from strike db, resetting {} paths", hashes_to_remove.len(), hashes_to_reset.len());
 
-        strike_utils.delete(StrikeType::HandleOrphaned, hashes_to_remove).context("Failed to delete paths from strike db")?;
+        strike_utils.delete(StrikeType::HandleOrphaned, &hashes_to_remove).context("Failed to delete paths from strike db")?;
+        strike_utils.reset(StrikeType::HandleOrphaned, &hashes_to_reset).context("Failed to reset paths in strike db")?;
 
         Ok(())
     }
@@ -1,8 +1,11 @@
+use std::collections::HashMap;
+
 use anyhow::Context;
 
 use crate::{
-    config::Config,
-    jobs::{enums::strike_type::StrikeType, utils::strike_utils::StrikeUtils},
+    jobs::{enums::strike_type::StrikeType, models::rule::Rule, utils::strike_utils::StrikeUtils},
+    logger::enums::category::Category,
+    warn,
 };
 
 pub struct Striker;
@@ -11,16 +14,18 @@ impl Striker {
     /**
      * Strike paths
      */
-    pub fn strike_paths(strike_utils: &mut StrikeUtils, orphaned_path_strings: Vec<String>, config: &Config) -> Result<Vec<String>, anyhow::Error> {
-        strike_utils
-            .strike(&StrikeType::HandleOrphaned, orphaned_path_strings.clone())
-            .context("[handle_orphaned] Failed to strike orhaned paths")?;
+    pub fn strike_paths(strike_utils: &mut StrikeUtils, orphaned_path_strings: Vec<String>, path_rules: &HashMap<String, Rule>) -> Result<Vec<String>, anyhow::Error> {
+        strike_utils.strike(&StrikeType::HandleOrphaned, &orphaned_path_strings).context("[handle_orphaned] Failed to strike orhaned paths")?;
 
-        let strike_records = strike_utils.get_strikes(&StrikeType::HandleOrphaned, Some(orphaned_path_strings)).context("[handle_orphaned] Failed get strikes")?;
+        let strike_records = strike_utils.get_strikes(&StrikeType::HandleOrphaned, Some(&orphaned_path_strings)).context("[handle_orphaned] Failed get strikes")?;
 
         let mut limit_reached_path_strings: Vec<String> = Vec::new();
         for strike_record in strike_records {
-            if strike_record.is_limit_reached(config.jobs().handle_orphaned().required_strikes(), config.jobs().handle_orphaned().min_strike_days()) {
+            let Some(rule) = path_rules.get(strike_record.hash()) else {
+                warn!(Category::HandleOrphaned, "Didn't find rule for striked path: {}", strike_record.hash());
+                continue;
+            };
+            if strike_record.is_limit_reached(rule.required_strikes(), rule.min_strike_days(), strike_utils.strike_policy_type()) {
                 limit_reached_path_strings.push(strike_record.hash().to_string());
             }
         }
@@ -0,0 +1,74 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+
+use crate::{
+    config::Config,
+    info,
+    jobs::{
+        enums::action_type::ActionType,
+        utils::action_utils::{ActionEntry, ActionUtils},
+    },
+    logger::enums::category::Category,
+    torrent_clients::{enums::tracker_status::TrackerStatus, models::tracker::Tracker, torrent_manager::TorrentManager},
+    utils::domain_utils::DomainUtils,
+};
+
+/**
+ * Recorded action of an edited announce url, it's never configured on a job so it's not an ActionType
+ */
+const EDIT_ANNOUNCE_URL_ACTION: &str = "edit_announce_url";
+
+pub struct ActionTaker;
+
+impl ActionTaker {
+    /**
+     * Replace announce urls of not working trackers by the first matching announce_url_edit
+     * Urls are never logged or recorded since they usually contain the passkey
+     * Returns: HashMap<String, usize> | HashMap<domain, edited_count>
+     */
+    pub async fn edit_announce_urls(
+        torrent_manager: Arc<TorrentManager>,
+        action_utils: &mut ActionUtils,
+        torrent_names: &HashMap<String, String>,
+        torrent_trackers: &HashMap<String, Vec<Tracker>>,
+        tracker_errors: &HashMap<(String, String), String>,
+        config: &Config,
+    ) -> Result<HashMap<String, usize>, anyhow::Error> {
+        let handle_tracker_errors = config.jobs().handle_tracker_errors();
+        let mut edited_counts: HashMap<String, usize> = HashMap::new();
+
+        for (hash, domain) in tracker_errors.keys() {
+            let Some(trackers) = torrent_trackers.get(hash) else {
+                continue;
+            };
+            for tracker in trackers.iter().filter(|tracker| tracker.domain() == *domain && *tracker.status() == TrackerStatus::NotWorking.to_i8()) {
+                let Some(announce_url_edit) = handle_tracker_errors
+                    .announce_url_edits()
+                    .iter()
+                    .find(|announce_url_edit| !announce_url_edit.from().is_empty() && DomainUtils::matches(domain, announce_url_edit.tracker()) && tracker.url().contains(announce_url_edit.from()))
+                else {
+                    continue;
+                };
+                let new_url = tracker.url().replace(announce_url_edit.from(), announce_url_edit.to());
+                if new_url == tracker.url() {
+                    continue;
+                }
+
+                let torrent_name = torrent_names.get(hash).map(|torrent_name| torrent_name.as_str()).unwrap_or_default();
+                if *handle_tracker_errors.edit_announce_urls() {
+                    info!(Category::HandleTrackerErrors, "Action: Editing announce url of {}: ({}) {}", domain, hash, torrent_name);
+                    let result = torrent_manager.edit_tracker(hash, tracker.url(), &new_url).await.context("Failed to edit tracker");
+                    action_utils.record(&[ActionEntry::new(EDIT_ANNOUNCE_URL_ACTION, hash, torrent_name, domain, Vec::new(), announce_url_edit.tracker())], &result)?;
+                    result?;
+                } else {
+                    info!(Category::HandleTrackerErrors, "Action: Test, would edit announce url of {}: ({}) {}", domain, hash, torrent_name);
+                    action_utils.record(&[ActionEntry::new(&ActionType::Test.to_string(), hash, torrent_name, domain, Vec::new(), announce_url_edit.tracker())], &Ok(()))?;
+                }
+                *edited_counts.entry(domain.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        Ok(edited_counts)
+    }
+}
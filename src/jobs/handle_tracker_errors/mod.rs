@@ -0,0 +1,4 @@
+mod action_taker;
+mod notifier;
+mod receiver;
+pub mod runner;
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use crate::{
+    jobs::utils::tracker_error_utils::TrackerErrorRecord,
+    utils::{
+        date_utils::DateUtils,
+        discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+    },
+};
+
+pub struct Notifier;
+
+impl Notifier {
+    /**
+     * Send 1 notification for a tracker domain that has been not working for long
+     */
+    pub async fn send_domain_notification(
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        domain: &str,
+        tracker_error_records: &[&TrackerErrorRecord],
+        torrent_names: &HashMap<String, String>,
+        min_not_working_days: i32,
+    ) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        // Most common messages first
+        let mut msg_counts: HashMap<&str, usize> = HashMap::new();
+        for tracker_error_record in tracker_error_records {
+            *msg_counts.entry(tracker_error_record.msg()).or_insert(0) += 1;
+        }
+        let mut msg_counts: Vec<(&str, usize)> = msg_counts.into_iter().collect();
+        msg_counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let msgs_str = msg_counts.iter().take(5).map(|(msg, count)| format!("{}x {}", count, msg)).collect::<Vec<String>>().join("\n");
+
+        let since_str = match tracker_error_records.iter().map(|tracker_error_record| tracker_error_record.first_seen()).min() {
+            Some(first_seen) => DateUtils::convert_utc_datetime_to_local_string(first_seen),
+            None => String::from("Unknown"),
+        };
+        let examples_str = tracker_error_records
+            .iter()
+            .take(5)
+            .map(|tracker_error_record| torrent_names.get(tracker_error_record.hash()).map(|torrent_name| torrent_name.as_str()).unwrap_or(tracker_error_record.hash()))
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        let fields: Vec<EmbedField> = vec![
+            EmbedField {
+                name: String::from("Affected torrents"),
+                value: tracker_error_records.len().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Not working since"),
+                value: since_str,
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Messages"),
+                value: msgs_str.chars().take(1024).collect(),
+                inline: false,
+            },
+            EmbedField {
+                name: String::from("Examples"),
+                value: examples_str.chars().take(1024).collect(),
+                inline: false,
+            },
+        ];
+
+        discord_webhook_utils
+            .send_webhook_embed(
+                domain,
+                format!("Tracker has been not working for at least {} days (e.g. passkey reset, renamed tracker or dead announce url)", min_not_working_days).as_str(),
+                fields,
+            )
+            .await
+    }
+
+    /**
+     * Send notification about edited announce urls of a tracker domain
+     */
+    pub async fn send_edit_notification(discord_webhook_utils: &mut DiscordWebhookUtils, domain: &str, edited_count: usize, is_applied: bool) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let fields: Vec<EmbedField> = vec![
+            EmbedField {
+                name: String::from("Action"),
+                value: match is_applied {
+                    true => String::from("edit"),
+                    false => String::from("test (edit_announce_urls is disabled)"),
+                },
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Torrents"),
+                value: edited_count.to_string(),
+                inline: true,
+            },
+        ];
+
+        discord_webhook_utils.send_webhook_embed(domain, "Edited announce urls of not working trackers", fields).await
+    }
+}
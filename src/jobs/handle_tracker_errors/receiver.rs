@@ -0,0 +1,195 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Context;
+
+use crate::{
+    config::StateFilter,
+    jobs::{
+        models::unregistered_matcher::UnregisteredMatcher,
+        utils::{state_filter_utils::StateFilterUtils, tracker_error_utils::TrackerErrorRecord},
+    },
+    logger::enums::category::Category,
+    torrent_clients::{
+        enums::tracker_status::TrackerStatus,
+        models::{torrent::Torrent, tracker::Tracker},
+        torrent_manager::TorrentManager,
+    },
+    trace,
+};
+
+pub struct Receiver;
+
+impl Receiver {
+    /**
+     * Get torrent trackers, DHT, PeX and LSD are skipped since they have no domain
+     */
+    pub async fn get_torrent_trackers(torrent_manager: Arc<TorrentManager>, torrents: &Vec<Torrent>) -> Result<HashMap<String, Vec<Tracker>>, anyhow::Error> {
+        let mut torrent_trackers: HashMap<String, Vec<Tracker>> = HashMap::new();
+        for torrent in torrents {
+            let trackers: Vec<Tracker> = torrent_manager
+                .get_torrent_trackers(torrent.hash())
+                .await
+                .context(format!("Failed to get trackers for torrent: ({}) {}", torrent.hash(), torrent.name()))?
+                .into_iter()
+                .filter(|tracker| !tracker.domain().is_empty())
+                .collect();
+            torrent_trackers.insert(torrent.hash().to_string(), trackers);
+        }
+
+        Ok(torrent_trackers)
+    }
+
+    /**
+     * Get trackers of torrents without any working tracker that are not working for a reason other than unregistered
+     * Returns: HashMap<(String, String), String> | HashMap<(torrent_hash, domain), msg>
+     */
+    pub fn get_tracker_errors(torrents: &Vec<Torrent>, torrent_trackers: &HashMap<String, Vec<Tracker>>, unregistered_matcher: &UnregisteredMatcher, state_filter: &StateFilter) -> HashMap<(String, String), String> {
+        let mut tracker_errors: HashMap<(String, String), String> = HashMap::new();
+        for torrent in torrents {
+            if !StateFilterUtils::is_allowed(torrent.state(), state_filter) {
+                trace!(Category::HandleTrackerErrors, "Skipping torrent (state {} is filtered): ({}) {}", torrent.state(), torrent.hash(), torrent.name());
+                continue;
+            }
+            let Some(trackers) = torrent_trackers.get(torrent.hash()) else {
+                continue;
+            };
+            // The torrent still works as long as 1 tracker does
+            if trackers.iter().any(|tracker| tracker.is_working()) {
+                continue;
+            }
+            for tracker in trackers.iter().filter(|tracker| *tracker.status() == TrackerStatus::NotWorking.to_i8()) {
+                // Unregistered torrents are handled by handle_unregistered
+                if let Some(matched_rule) = unregistered_matcher.get_matched_rule(tracker) {
+                    trace!(Category::HandleTrackerErrors, "Tracker {} is unregistered ({}): ({}) {}", tracker.domain(), matched_rule, torrent.hash(), torrent.name());
+                    continue;
+                }
+                trace!(Category::HandleTrackerErrors, "Tracker {} is not working ({}): ({}) {}", tracker.domain(), tracker.msg(), torrent.hash(), torrent.name());
+                tracker_errors.entry((torrent.hash().to_string(), tracker.domain())).or_insert(tracker.msg().trim().to_string());
+            }
+        }
+
+        tracker_errors
+    }
+
+    /**
+     * Get updating or not contacted trackers, they keep their history until their status is known again
+     * Returns: HashSet<(String, String)> | HashSet<(torrent_hash, domain)>
+     */
+    pub fn get_pending_trackers(torrent_trackers: &HashMap<String, Vec<Tracker>>) -> HashSet<(String, String)> {
+        let mut pending_trackers: HashSet<(String, String)> = HashSet::new();
+        for (hash, trackers) in torrent_trackers {
+            if trackers.iter().any(|tracker| tracker.is_working()) {
+                continue;
+            }
+            for tracker in trackers.iter().filter(|tracker| !tracker.is_working() && *tracker.status() != TrackerStatus::NotWorking.to_i8()) {
+                pending_trackers.insert((hash.to_string(), tracker.domain()));
+            }
+        }
+
+        pending_trackers
+    }
+
+    /**
+     * Tracker errors of this run whose trackers have been not working for at least min_not_working_days
+     * Returns: HashMap<(String, String), String> | HashMap<(torrent_hash, domain), msg>
+     */
+    pub fn get_long_tracker_errors(tracker_errors: &HashMap<(String, String), String>, tracker_error_records: &[TrackerErrorRecord], min_not_working_days: i32) -> HashMap<(String, String), String> {
+        let long_keys: HashSet<(&str, &str)> = tracker_error_records
+            .iter()
+            .filter(|tracker_error_record| tracker_error_record.not_working_days() >= min_not_working_days as f64)
+            .map(|tracker_error_record| (tracker_error_record.hash(), tracker_error_record.domain()))
+            .collect();
+        tracker_errors
+            .iter()
+            .filter(|((hash, domain), _)| long_keys.contains(&(hash.as_str(), domain.as_str())))
+            .map(|(key, msg)| (key.clone(), msg.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use rusqlite::params;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        jobs::utils::tracker_error_utils::TrackerErrorUtils,
+        utils::{date_utils::DateUtils, db_manager::DbManager},
+    };
+
+    fn get_tracker(url: &str, status: i8, msg: &str) -> Tracker {
+        serde_json::from_value(json!({ "url": url, "status": status, "msg": msg })).unwrap()
+    }
+
+    fn get_unregistered_matcher() -> UnregisteredMatcher {
+        UnregisteredMatcher::new(Config::default().jobs().handle_unregistered().unregistered_messages()).unwrap()
+    }
+
+    fn get_tracker_error(hash: &str, domain: &str) -> ((String, String), String) {
+        ((hash.to_string(), domain.to_string()), String::from("timed out"))
+    }
+
+    #[test]
+    fn only_torrents_without_working_tracker_have_errors() {
+        let torrents = vec![Torrent::from_test_fields(json!({ "hash": "a" })), Torrent::from_test_fields(json!({ "hash": "b" }))];
+        let torrent_trackers = HashMap::from([
+            (String::from("a"), vec![get_tracker("https://a.example.org/announce", 4, "timed out")]),
+            (
+                String::from("b"),
+                vec![get_tracker("https://a.example.org/announce", 4, "timed out"), get_tracker("https://b.example.org/announce", 2, "")],
+            ),
+        ]);
+        let tracker_errors = Receiver::get_tracker_errors(&torrents, &torrent_trackers, &get_unregistered_matcher(), &StateFilter::default());
+        assert_eq!(tracker_errors, HashMap::from([get_tracker_error("a", "a.example.org")]));
+    }
+
+    #[test]
+    fn unregistered_trackers_are_left_to_handle_unregistered() {
+        let torrents = vec![Torrent::from_test_fields(json!({ "hash": "a" }))];
+        let torrent_trackers = HashMap::from([(String::from("a"), vec![get_tracker("https://a.example.org/announce", 4, "Torrent not registered with this tracker")])]);
+        assert!(Receiver::get_tracker_errors(&torrents, &torrent_trackers, &get_unregistered_matcher(), &StateFilter::default()).is_empty());
+    }
+
+    #[test]
+    fn updating_trackers_are_pending() {
+        let torrent_trackers = HashMap::from([
+            (String::from("a"), vec![get_tracker("https://a.example.org/announce", 3, "")]),
+            (String::from("b"), vec![get_tracker("https://a.example.org/announce", 3, ""), get_tracker("https://b.example.org/announce", 2, "")]),
+        ]);
+        assert_eq!(Receiver::get_pending_trackers(&torrent_trackers), HashSet::from([(String::from("a"), String::from("a.example.org"))]));
+    }
+
+    #[test]
+    fn only_long_tracker_errors_are_returned() {
+        let db_manager = DbManager::open_in_memory().unwrap();
+        let mut tracker_error_utils = TrackerErrorUtils::new(db_manager.clone(), Category::HandleTrackerErrors);
+        let tracker_errors = HashMap::from([get_tracker_error("a", "a.example.org"), get_tracker_error("b", "a.example.org")]);
+        tracker_error_utils.record(&tracker_errors).unwrap();
+        // a has been not working for 20 days, b only since this run
+        let first_seen = DateUtils::get_current_utc_datetime() - Duration::days(20);
+        db_manager
+            .conn()
+            .unwrap()
+            .execute("UPDATE tracker_errors SET first_seen = ?1 WHERE hash = 'a'", params![DateUtils::convert_utc_datetime_to_string(&first_seen)])
+            .unwrap();
+        let tracker_error_records = tracker_error_utils.get_tracker_errors().unwrap();
+        assert_eq!(Receiver::get_long_tracker_errors(&tracker_errors, &tracker_error_records, 14), HashMap::from([get_tracker_error("a", "a.example.org")]));
+        assert_eq!(Receiver::get_long_tracker_errors(&tracker_errors, &tracker_error_records, 0).len(), 2);
+    }
+
+    #[test]
+    fn resolved_tracker_errors_are_deleted() {
+        let mut tracker_error_utils = TrackerErrorUtils::new(DbManager::open_in_memory().unwrap(), Category::HandleTrackerErrors);
+        tracker_error_utils.record(&HashMap::from([get_tracker_error("a", "a.example.org"), get_tracker_error("b", "a.example.org")])).unwrap();
+        let deleted_count = tracker_error_utils.delete_resolved(&HashSet::from([(String::from("a"), String::from("a.example.org"))])).unwrap();
+        assert_eq!(deleted_count, 1);
+        let hashes: Vec<String> = tracker_error_utils.get_tracker_errors().unwrap().iter().map(|tracker_error_record| tracker_error_record.hash().to_string()).collect();
+        assert_eq!(hashes, vec!["a"]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use reqwest::Url;
+
+use crate::{
+    config::Config,
+    debug, info,
+    jobs::{
+        handle_tracker_errors::{action_taker::ActionTaker, notifier::Notifier, receiver::Receiver},
+        models::{job_run_stats::JobRunStats, pipeline_context::PipelineContext, quiet_windows::QuietWindows, unregistered_matcher::UnregisteredMatcher},
+        utils::{
+            action_utils::ActionUtils,
+            tracker_error_utils::{TrackerErrorRecord, TrackerErrorUtils},
+        },
+    },
+    logger::enums::category::Category,
+    torrent_clients::{models::tracker::Tracker, torrent_manager::TorrentManager},
+    utils::{db_manager::DbManager, discord_webhook_utils::DiscordWebhookUtils},
+    warn,
+};
+
+pub struct HandleTrackerErrors {
+    torrent_manager: Arc<TorrentManager>,
+    db_manager: DbManager,
+    config: Config,
+}
+
+impl HandleTrackerErrors {
+    pub fn new(torrent_manager: Arc<TorrentManager>, db_manager: DbManager, config: Config) -> Self {
+        Self { torrent_manager, db_manager, config }
+    }
+
+    /**
+     * Run
+     */
+    pub async fn run(&self, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
+        let discord_webhook_url: Option<Url> = match self.config.notification().discord_webhook_url().len() > 1 {
+            true => Some(Url::parse(self.config.notification().discord_webhook_url()).context("Failed to parse discord_webhook_url")?),
+            false => None,
+        };
+        let mut discord_webhook_utils = DiscordWebhookUtils::new(discord_webhook_url);
+
+        // Login
+        self.torrent_manager.login().await.context("Failed to login to torrent client")?;
+
+        // Get torrents from torrent client
+        debug!(Category::HandleTrackerErrors, "Getting torrents...");
+        let torrents = pipeline_context.get_torrents(&self.torrent_manager).await?;
+        debug!(Category::HandleTrackerErrors, "Received {} torrents", torrents.len());
+        let torrent_names: HashMap<String, String> = torrents.iter().map(|torrent| (torrent.hash().to_string(), torrent.name().to_string())).collect();
+
+        // Unregistered torrents are left to handle_unregistered
+        let unregistered_matcher = UnregisteredMatcher::new(self.config.jobs().handle_unregistered().unregistered_messages()).context("Failed to compile unregistered messages")?;
+
+        // Get torrent trackers
+        debug!(Category::HandleTrackerErrors, "Getting torrent trackers...");
+        let torrent_trackers: HashMap<String, Vec<Tracker>> = Receiver::get_torrent_trackers(self.torrent_manager.clone(), &torrents).await?;
+        debug!(Category::HandleTrackerErrors, "Received torrent trackers");
+
+        let tracker_errors: HashMap<(String, String), String> = Receiver::get_tracker_errors(&torrents, &torrent_trackers, &unregistered_matcher, self.config.jobs().handle_tracker_errors().state_filter());
+        info!(Category::HandleTrackerErrors, "{} trackers of torrents without a working tracker are not working", tracker_errors.len());
+        let job_run_stats = JobRunStats::new(torrents.len(), tracker_errors.len(), 0);
+
+        // Update history
+        debug!(Category::HandleTrackerErrors, "Recording tracker errors...");
+        let mut tracker_error_utils = TrackerErrorUtils::new(self.db_manager.clone(), Category::HandleTrackerErrors);
+        tracker_error_utils.record(&tracker_errors).context("Failed to record tracker errors")?;
+        let mut keep: HashSet<(String, String)> = Receiver::get_pending_trackers(&torrent_trackers);
+        keep.extend(tracker_errors.keys().cloned());
+        let deleted_count = tracker_error_utils.delete_resolved(&keep).context("Failed to delete resolved tracker errors")?;
+        debug!(Category::HandleTrackerErrors, "Done recording tracker errors, {} resolved", deleted_count);
+
+        // Report trackers per domain that have been not working for long enough
+        let min_not_working_days = self.config.jobs().handle_tracker_errors().min_not_working_days();
+        let tracker_error_records: Vec<TrackerErrorRecord> = tracker_error_utils.get_tracker_errors().context("Failed to get tracker errors")?;
+        let mut domain_records: BTreeMap<&str, Vec<&TrackerErrorRecord>> = BTreeMap::new();
+        for tracker_error_record in &tracker_error_records {
+            if tracker_error_record.not_working_days() >= min_not_working_days as f64 {
+                domain_records.entry(tracker_error_record.domain()).or_default().push(tracker_error_record);
+            }
+        }
+        for (domain, records) in &domain_records {
+            // Only report a domain when it first crosses min_not_working_days or its torrent count changed since
+            if records.iter().all(|record| *record.notified_count() == records.len() as i64) {
+                debug!(Category::HandleTrackerErrors, "Tracker {} is still not working on {} torrents, already notified", domain, records.len());
+                continue;
+            }
+
+            // Log
+            warn!(
+                Category::HandleTrackerErrors,
+                "Tracker {} has been not working for at least {} days on {} torrents",
+                domain,
+                min_not_working_days,
+                records.len()
+            );
+            for record in records {
+                debug!(
+                    Category::HandleTrackerErrors,
+                    "  -> {:.2} days ({}): {}",
+                    record.not_working_days(),
+                    record.msg(),
+                    torrent_names.get(record.hash()).map(|torrent_name| torrent_name.as_str()).unwrap_or(record.hash())
+                );
+            }
+
+            // Notification
+            if *self.config.notification().on_job_action() {
+                Notifier::send_domain_notification(&mut discord_webhook_utils, domain, records, &torrent_names, min_not_working_days)
+                    .await
+                    .context("Failed to send tracker errors notification")?;
+            }
+            tracker_error_utils.set_notified(records).context("Failed to set tracker errors notified")?;
+        }
+
+        // Edit announce urls, only of trackers that have been not working for long enough as well
+        if !self.config.jobs().handle_tracker_errors().announce_url_edits().is_empty() {
+            let long_tracker_errors: HashMap<(String, String), String> = Receiver::get_long_tracker_errors(&tracker_errors, &tracker_error_records, min_not_working_days);
+            // Announce url edits only fix trackers, so they are never deferred
+            let mut action_utils = ActionUtils::new(self.db_manager.clone(), Category::HandleTrackerErrors, QuietWindows::default());
+            let edited_counts: HashMap<String, usize> = ActionTaker::edit_announce_urls(self.torrent_manager.clone(), &mut action_utils, &torrent_names, &torrent_trackers, &long_tracker_errors, &self.config).await?;
+            for (domain, edited_count) in &edited_counts {
+                info!(Category::HandleTrackerErrors, "{} announce urls of {} matched announce_url_edits", edited_count, domain);
+                if *self.config.notification().on_job_action() {
+                    Notifier::send_edit_notification(&mut discord_webhook_utils, domain, *edited_count, *self.config.jobs().handle_tracker_errors().edit_announce_urls())
+                        .await
+                        .context("Failed to send edit notification")?;
+                }
+            }
+        }
+
+        // Logout
+        self.torrent_manager.logout().await.context("Failed to logout of torrent client")?;
+
+        Ok(job_run_stats)
+    }
+}
@@ -1,5 +1,6 @@
 mod action_taker;
 mod notifier;
-mod receiver;
+pub mod receiver;
+pub mod retention;
 pub mod runner;
 mod striker;
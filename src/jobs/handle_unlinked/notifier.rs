@@ -1,9 +1,16 @@
-use chrono::{Local, TimeZone};
+use std::{collections::HashMap, path::Path};
 
 use crate::{
     config::Config,
+    jobs::{
+        models::{rule::Rule, torrent_links::TorrentLinks},
+        utils::{hit_and_run_utils::HitAndRunUtils, tracker_domain_utils::TrackerDomainUtils},
+    },
     torrent_clients::models::torrent::Torrent,
-    utils::discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+    utils::{
+        date_utils::DateUtils,
+        discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+    },
 };
 
 pub struct Notifier;
@@ -12,7 +19,15 @@ impl Notifier {
     /**
      * Send notification
      */
-    pub async fn send_notification(discord_webhook_utils: &mut DiscordWebhookUtils, torrent: &Torrent, config: &Config) -> Result<(), anyhow::Error> {
+    pub async fn send_notification(
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        torrent: &Torrent,
+        torrent_links: Option<&TorrentLinks>,
+        rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+        config: &Config,
+    ) -> Result<(), anyhow::Error> {
         if !discord_webhook_utils.is_notifications_enabled() {
             return Ok(());
         }
@@ -22,19 +37,24 @@ impl Notifier {
 
         let seeding_days = format!("{:.2}", (torrent.seeding_time() / 60 / 60) as f32 / 24.0);
 
-        let added_on_str = match Local.timestamp_opt(*torrent.added_on(), 0).single() {
-            Some(datetime_local) => datetime_local.format("%Y-%m-%d %H:%M:%S").to_string(),
+        let added_on_str = match DateUtils::convert_timestamp_to_local_string(*torrent.added_on()) {
+            Some(local_datetime_str) => local_datetime_str,
             None => String::from("Failed getting datetime"),
         };
         let completed_on_str = match *torrent.completion_on() {
             -1 => String::from("Not completed"),
-            _ => match Local.timestamp_opt(*torrent.completion_on(), 0).single() {
-                Some(datetime_local) => datetime_local.format("%Y-%m-%d %H:%M:%S").to_string(),
+            _ => match DateUtils::convert_timestamp_to_local_string(*torrent.completion_on()) {
+                Some(local_datetime_str) => local_datetime_str,
                 None => String::from("Failed getting datetime"),
             },
         };
 
-        let fields = vec![
+        let last_activity_str = match DateUtils::convert_timestamp_to_local_string(*torrent.last_activity()) {
+            Some(local_datetime_str) => local_datetime_str,
+            None => String::from("Failed getting datetime"),
+        };
+
+        let mut fields = vec![
             EmbedField {
                 name: String::from("Tracker"),
                 value: torrent.tracker().to_string(),
@@ -42,7 +62,12 @@ impl Notifier {
             },
             EmbedField {
                 name: String::from("Action"),
-                value: config.jobs().handle_unlinked().action().to_string(),
+                value: rule.action().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Rule"),
+                value: rule.name().to_string(),
                 inline: true,
             },
             EmbedField {
@@ -70,6 +95,21 @@ impl Notifier {
                 value: seeding_days.to_string(),
                 inline: true,
             },
+            EmbedField {
+                name: String::from("Swarm"),
+                value: format!("{} seeders | {} leechers", torrent.num_complete(), torrent.num_incomplete()),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Uploaded"),
+                value: format!("{:.2}GB ({:.2}KB/s)", (*torrent.uploaded() / 1000 / 1000) as f32 / 1000.0, *torrent.upspeed() as f32 / 1000.0),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Last activity"),
+                value: last_activity_str,
+                inline: true,
+            },
             EmbedField {
                 name: String::from("Added"),
                 value: added_on_str,
@@ -81,6 +121,212 @@ impl Notifier {
                 inline: true,
             },
         ];
+        fields.push(EmbedField {
+            name: String::from("Linked"),
+            value: Notifier::get_linked_str(torrent_links),
+            inline: false,
+        });
+        if !config.paths().library_paths().is_empty() {
+            fields.push(EmbedField {
+                name: String::from("Library links"),
+                value: Notifier::get_library_links_str(torrent_links),
+                inline: false,
+            });
+        }
+        if let Some(risk_str) = hit_and_run_utils.get_risk_str(torrent, tracker_domain_utils) {
+            fields.push(EmbedField {
+                name: String::from("Hit and run risk"),
+                value: format!("{} (stop/delete is skipped)", risk_str),
+                inline: false,
+            });
+        }
+
         discord_webhook_utils.send_webhook_embed(torrent.name(), "Found unlinked torrent", fields).await
     }
+
+    /**
+     * Send 1 notification for a cross-seed group where every member is eligible
+     */
+    pub async fn send_group_notification(
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        group_torrents: &[Torrent],
+        torrents_links: &HashMap<String, TorrentLinks>,
+        group_rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        // Members share their data, so the biggest member is the size of the group
+        let max_size = group_torrents.iter().map(|torrent| *torrent.total_size()).max().unwrap_or(0);
+        let total_size_gib = format!("{:.2}", (max_size / 1024 / 1024) as f32 / 1024.0);
+        let total_size_gb = format!("{:.2}", (max_size / 1000 / 1000) as f32 / 1000.0);
+
+        let mut fields = vec![
+            EmbedField {
+                name: String::from("Action"),
+                value: group_rule.action().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Rule"),
+                value: group_rule.name().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Total Size"),
+                value: format!("{total_size_gib}GiB | {total_size_gb}GB"),
+                inline: true,
+            },
+        ];
+        // Discord allows 25 fields per embed
+        for (i, torrent) in group_torrents.iter().enumerate() {
+            if i >= 20 {
+                fields.push(EmbedField {
+                    name: String::from("..."),
+                    value: format!("and {} more", group_torrents.len() - i),
+                    inline: false,
+                });
+                break;
+            }
+            fields.push(EmbedField {
+                name: torrent.name().chars().take(256).collect(),
+                value: format!(
+                    "Tracker: {}\nCategory: {}\nRatio: {:.2} | Seeding days: {:.2}\nLinked: {}",
+                    torrent.tracker(),
+                    torrent.category(),
+                    torrent.ratio(),
+                    (torrent.seeding_time() / 60 / 60) as f32 / 24.0,
+                    Notifier::get_linked_str(torrents_links.get(torrent.hash()))
+                ) + Notifier::get_risk_line(torrent, hit_and_run_utils, tracker_domain_utils).as_str(),
+                inline: false,
+            });
+        }
+
+        discord_webhook_utils
+            .send_webhook_embed(format!("Cross-seed group ({} torrents)", group_torrents.len()).as_str(), "Found unlinked cross-seed group", fields)
+            .await
+    }
+
+    /**
+     * Send notification for torrents that are removed to meet a storage quota
+     */
+    pub async fn send_retention_notification(
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        unit_torrents: &[Torrent],
+        score: f64,
+        quota_str: &str,
+        tracker_domain_utils: &TrackerDomainUtils,
+        config: &Config,
+    ) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let max_size = unit_torrents.iter().map(|torrent| *torrent.total_size()).max().unwrap_or(0);
+        let total_size_gib = format!("{:.2}", (max_size / 1024 / 1024) as f32 / 1024.0);
+        let total_size_gb = format!("{:.2}", (max_size / 1000 / 1000) as f32 / 1000.0);
+
+        let torrent_lines: Vec<String> = unit_torrents
+            .iter()
+            .take(20)
+            .map(|torrent| format!("{} ({})", torrent.name(), tracker_domain_utils.get_tracker_domain(torrent)))
+            .collect();
+
+        let fields = vec![
+            EmbedField {
+                name: String::from("Quota"),
+                value: quota_str.to_string(),
+                inline: false,
+            },
+            EmbedField {
+                name: String::from("Action"),
+                value: config.jobs().handle_unlinked().retention().action().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Score"),
+                value: format!("{:.2}", score),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Total Size"),
+                value: format!("{total_size_gib}GiB | {total_size_gb}GB"),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Torrents"),
+                value: torrent_lines.join("\n").chars().take(1000).collect(),
+                inline: false,
+            },
+        ];
+
+        let title = match unit_torrents {
+            [torrent] => torrent.name().to_string(),
+            _ => format!("Cross-seed group ({} torrents)", unit_torrents.len()),
+        };
+        discord_webhook_utils.send_webhook_embed(title.as_str(), "Removing lowest scored torrent to meet storage quota", fields).await
+    }
+
+    /**
+     * Linked files and bytes compared to the total
+     */
+    fn get_linked_str(torrent_links: Option<&TorrentLinks>) -> String {
+        match torrent_links {
+            Some(torrent_links) => format!(
+                "{}/{} files | {:.2}GiB/{:.2}GiB ({:.2}%)",
+                torrent_links.files_linked(),
+                torrent_links.files_total(),
+                (torrent_links.bytes_linked() / 1024 / 1024) as f32 / 1024.0,
+                (torrent_links.bytes_total() / 1024 / 1024) as f32 / 1024.0,
+                torrent_links.linked_percent()
+            ),
+            None => String::from("Unknown"),
+        }
+    }
+
+    /**
+     * One line per file that is linked into the library, cut off to stay below the discord field value limit
+     */
+    fn get_library_links_str(torrent_links: Option<&TorrentLinks>) -> String {
+        let torrent_links = match torrent_links {
+            Some(torrent_links) => torrent_links,
+            None => return String::from("Unknown"),
+        };
+        let lines: Vec<String> = torrent_links
+            .files()
+            .iter()
+            .filter(|file_links| !file_links.library_paths().is_empty())
+            .map(|file_links| {
+                let file_name = Path::new(file_links.path()).file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or(file_links.path().to_string());
+                format!("{} -> {}", file_name, file_links.library_paths().join(", "))
+            })
+            .collect();
+        if lines.is_empty() {
+            return String::from("None");
+        }
+
+        let mut library_links_str = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            if library_links_str.len() + line.len() > 900 {
+                library_links_str.push_str(format!("... and {} more", lines.len() - i).as_str());
+                break;
+            }
+            library_links_str.push_str(line);
+            library_links_str.push('\n');
+        }
+        library_links_str
+    }
+
+    /**
+     * Extra line for a cross-seed group member that is at risk of a hit-and-run
+     */
+    fn get_risk_line(torrent: &Torrent, hit_and_run_utils: &HitAndRunUtils, tracker_domain_utils: &TrackerDomainUtils) -> String {
+        match hit_and_run_utils.get_risk_str(torrent, tracker_domain_utils) {
+            Some(risk_str) => format!("\nHit and run risk: {}", risk_str),
+            None => String::new(),
+        }
+    }
 }
@@ -1,14 +1,19 @@
-use std::{collections::HashMap, sync::Arc};
+use std::collections::HashMap;
 
 use anyhow::Context;
 
 use crate::{
     config::Config,
     debug,
-    jobs::utils::file_utils::FileUtils,
+    jobs::{
+        models::{rule::Rule, torrent_links::TorrentLinks},
+        utils::{file_utils::FileUtils, state_filter_utils::StateFilterUtils, upload_history_utils::UploadHistoryUtils},
+    },
     logger::enums::category::Category,
-    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    torrent_clients::models::torrent::Torrent,
     trace,
+    utils::db_manager::DbManager,
+    warn,
 };
 
 pub struct Receiver;
@@ -17,52 +22,87 @@ impl Receiver {
     /**
      * Get torrents and if they match criteria
      * Returns: HashMap<String, (Torrent, bool)> | HashMap<torrent_hash, (Torrent, is_criteria_met))>
+     *          HashMap<String, TorrentLinks> | HashMap<torrent_hash, TorrentLinks> (only for torrents that got to the link check)
      */
-    pub async fn get_torrents_criteria(torrent_manager: Arc<TorrentManager>, config: &Config, torrents_path: &str) -> Result<HashMap<String, (Torrent, bool)>, anyhow::Error> {
-        // Get torrents from torrent client
-        debug!(Category::HandleUnlinked, "Getting torrents...");
-        let torrents = torrent_manager.get_all_torrents().await.context("Failed to get all torrents")?;
-        debug!(Category::HandleUnlinked, "Received {} torrents", torrents.len());
-
-        // Get known hardlinks
-        debug!(Category::HandleUnlinked, "Getting known torrent hardlinks...");
-        let known_hardlinks: HashMap<u64, u64> = FileUtils::get_known_hardlinks(torrents_path)?;
-        debug!(
-            Category::HandleUnlinked,
-            "Found {} unique files ({} total) in torrent folder",
-            known_hardlinks.len(),
-            known_hardlinks.values().sum::<u64>()
-        );
+    pub async fn get_torrents_criteria(
+        torrents: &[Torrent],
+        torrent_rules: &HashMap<String, Rule>,
+        db_manager: &DbManager,
+        config: &Config,
+        known_hardlinks: &HashMap<(u64, u64), u64>,
+    ) -> Result<(HashMap<String, (Torrent, bool)>, HashMap<String, TorrentLinks>), anyhow::Error> {
+        // Get library index
+        let library_index_option: Option<HashMap<(u64, u64), Vec<String>>> = match config.paths().library_paths().is_empty() {
+            true => None,
+            false => {
+                debug!(Category::HandleUnlinked, "Getting library index...");
+                let library_index = FileUtils::get_library_index(config.paths().library_paths()).context("Failed to get library index")?;
+                debug!(Category::HandleUnlinked, "Found {} linked files in library paths", library_index.len());
+                Some(library_index)
+            }
+        };
+
+        // Get recent uploads
+        let recent_upload_days = config.jobs().handle_unlinked().recent_upload_days();
+        let recent_uploads: HashMap<String, i64> = match config.jobs().handle_unlinked().protect_recent_upload_gb() > 0.0 && recent_upload_days > 0 {
+            true => {
+                debug!(Category::HandleUnlinked, "Getting uploads of the last {} days...", recent_upload_days);
+                let mut upload_history_utils = UploadHistoryUtils::new(db_manager.clone(), Category::HandleUnlinked);
+                let recent_uploads = upload_history_utils.get_recent_uploads(torrents, recent_upload_days).context("Failed to get recent uploads")?;
+                upload_history_utils.record(torrents, recent_upload_days).context("Failed to record upload history")?;
+                debug!(Category::HandleUnlinked, "Received uploads of {} torrents", recent_uploads.len());
+                recent_uploads
+            }
+            false => HashMap::new(),
+        };
 
         // Check torrents for criteria
         debug!(Category::HandleUnlinked, "Checking torrents for criteria...");
         let mut torrents_criteria: HashMap<String, (Torrent, bool)> = HashMap::new();
-        for torrent in &torrents {
-            let is_criteria_met = Receiver::is_criteria_met(torrent, &known_hardlinks, config)?;
+        let mut torrents_links: HashMap<String, TorrentLinks> = HashMap::new();
+        for torrent in torrents {
+            let Some(rule) = torrent_rules.get(torrent.hash()) else {
+                warn!(Category::HandleUnlinked, "Didn't find rule for torrent: ({}) {}", torrent.hash(), torrent.name());
+                continue;
+            };
+            let is_criteria_met = Receiver::is_criteria_met(torrent, rule, known_hardlinks, library_index_option.as_ref(), &recent_uploads, &mut torrents_links, config)?;
             torrents_criteria.insert(torrent.hash().to_string(), (torrent.clone(), is_criteria_met));
         }
         debug!(Category::HandleUnlinked, "Done checking torrents for criteria");
 
-        Ok(torrents_criteria)
+        Ok((torrents_criteria, torrents_links))
     }
 
     /**
      * Is criteria met
      */
-    fn is_criteria_met(torrent: &Torrent, known_hardlinks: &HashMap<u64, u64>, config: &Config) -> Result<bool, anyhow::Error> {
+    pub fn is_criteria_met(
+        torrent: &Torrent,
+        rule: &Rule,
+        known_hardlinks: &HashMap<(u64, u64), u64>,
+        library_index_option: Option<&HashMap<(u64, u64), Vec<String>>>,
+        recent_uploads: &HashMap<String, i64>,
+        torrents_links: &mut HashMap<String, TorrentLinks>,
+        config: &Config,
+    ) -> Result<bool, anyhow::Error> {
         // Uncompleted
         if *torrent.completion_on() == -1 {
             trace!(Category::HandleUnlinked, "Torrent doesn't meet criteria (uncompleted): ({}) {}", torrent.hash(), torrent.name());
             return Ok(false);
         }
         // Protection tag
-        if torrent.tags().contains(config.jobs().handle_unlinked().protection_tag()) {
-            trace!(Category::HandleUnlinked, "Torrent doesn't meet criteria (protection tag): ({}) {}", torrent.hash(), torrent.name());
+        if rule.is_protected(torrent.tags()) {
+            trace!(Category::HandleUnlinked, "Torrent doesn't meet criteria (protected by rule {}): ({}) {}", rule.name(), torrent.hash(), torrent.name());
+            return Ok(false);
+        }
+        // State filter
+        if !StateFilterUtils::is_allowed(torrent.state(), config.jobs().handle_unlinked().state_filter()) {
+            trace!(Category::HandleUnlinked, "Torrent doesn't meet criteria (state {} is filtered): ({}) {}", torrent.state(), torrent.hash(), torrent.name());
             return Ok(false);
         }
         // Seed time
         let seeding_days = torrent.seeding_time() / 60 / 60 / 24;
-        let min_seeding_days = config.jobs().handle_unlinked().min_seeding_days() as i64;
+        let min_seeding_days = rule.min_seeding_days() as i64;
         if seeding_days < min_seeding_days {
             trace!(
                 Category::HandleUnlinked,
@@ -75,14 +115,124 @@ impl Receiver {
             return Ok(false);
         }
         // Media library
-        let has_external_hardlinks = FileUtils::has_external_hardlinks(known_hardlinks, torrent.content_path())?;
-        if has_external_hardlinks {
-            trace!(Category::HandleUnlinked, "Torrent doesn't meet criteria (has external hardlink): ({}) {}", torrent.hash(), torrent.name(),);
+        let torrent_links = TorrentLinks::new(FileUtils::get_file_links(known_hardlinks, library_index_option, torrent.content_path())?);
+        let min_linked_percent = config.jobs().handle_unlinked().min_linked_percent();
+        let is_linked = torrent_links.is_linked(min_linked_percent);
+        let linked_percent = torrent_links.linked_percent();
+        let files_linked = torrent_links.files_linked();
+        let files_total = torrent_links.files_total();
+        torrents_links.insert(torrent.hash().to_string(), torrent_links);
+        if is_linked {
+            match library_index_option {
+                Some(_) => trace!(Category::HandleUnlinked, "Torrent doesn't meet criteria (linked in library): ({}) {}", torrent.hash(), torrent.name()),
+                None => trace!(Category::HandleUnlinked, "Torrent doesn't meet criteria (has external hardlink): ({}) {}", torrent.hash(), torrent.name()),
+            }
             return Ok(false);
         }
+        if files_linked > 0 {
+            debug!(
+                Category::HandleUnlinked,
+                "Torrent is partially linked ({}/{} files, {:.2}% of bytes) which is below the minimum of {:.2}%: ({}) {}",
+                files_linked,
+                files_total,
+                linked_percent,
+                min_linked_percent,
+                torrent.hash(),
+                torrent.name()
+            );
+        }
+        // Swarm seeders
+        let min_swarm_seeders = config.jobs().handle_unlinked().min_swarm_seeders();
+        if min_swarm_seeders > 0 && *torrent.num_complete() < min_swarm_seeders {
+            trace!(
+                Category::HandleUnlinked,
+                "Torrent doesn't meet criteria (swarm seeders {}/{}): ({}) {}",
+                torrent.num_complete(),
+                min_swarm_seeders,
+                torrent.hash(),
+                torrent.name(),
+            );
+            return Ok(false);
+        }
+        // Upload speed, uploads that started since the last run aren't in the upload history yet
+        let protect_upspeed_kbps = config.jobs().handle_unlinked().protect_upspeed_kbps();
+        let upspeed_kbps = *torrent.upspeed() as f64 / 1000.0;
+        if protect_upspeed_kbps > 0.0 && upspeed_kbps > protect_upspeed_kbps {
+            trace!(
+                Category::HandleUnlinked,
+                "Torrent doesn't meet criteria (uploading at {:.2}KB/s, limit {:.2}KB/s): ({}) {}",
+                upspeed_kbps,
+                protect_upspeed_kbps,
+                torrent.hash(),
+                torrent.name(),
+            );
+            return Ok(false);
+        }
+        // Recent uploads
+        let protect_recent_upload_gb = config.jobs().handle_unlinked().protect_recent_upload_gb();
+        if protect_recent_upload_gb > 0.0
+            && let Some(recent_upload) = recent_uploads.get(torrent.hash())
+        {
+            let recent_upload_gb = *recent_upload as f64 / 1000.0 / 1000.0 / 1000.0;
+            if recent_upload_gb > protect_recent_upload_gb {
+                trace!(
+                    Category::HandleUnlinked,
+                    "Torrent doesn't meet criteria (uploaded {:.2}GB in the last {} days, limit {:.2}GB): ({}) {}",
+                    recent_upload_gb,
+                    config.jobs().handle_unlinked().recent_upload_days(),
+                    protect_recent_upload_gb,
+                    torrent.hash(),
+                    torrent.name(),
+                );
+                return Ok(false);
+            }
+        }
 
         debug!(Category::HandleUnlinked, "Torrent meets criteria: ({}) {}", torrent.hash(), torrent.name());
 
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::MetadataExt, process};
+
+    use serde_json::json;
+
+    use super::*;
+
+    /**
+     * Torrent that meets every other criteria, its only file has no other hardlinks
+     */
+    fn is_criteria_met(upspeed: i64, recent_upload: i64) -> bool {
+        let config = Config::from_test_fields(json!({ "jobs": { "handle_unlinked": { "min_seeding_days": 0, "protect_upspeed_kbps": 100.0, "protect_recent_upload_gb": 1.0 } } }));
+        let test_dir = std::env::temp_dir().join(format!("torrent-cleaner-unlinked-{}-{}-{}", process::id(), upspeed, recent_upload));
+        fs::create_dir_all(&test_dir).unwrap();
+        fs::write(test_dir.join("movie.mkv"), "movie").unwrap();
+        let metadata = fs::metadata(test_dir.join("movie.mkv")).unwrap();
+        let known_hardlinks = HashMap::from([((metadata.dev(), metadata.ino()), 1)]);
+
+        let torrent = Torrent::from_test_fields(json!({ "hash": "a", "name": "a", "content_path": test_dir.to_str().unwrap(), "upspeed": upspeed }));
+        let recent_uploads = HashMap::from([(String::from("a"), recent_upload)]);
+        let rule = Rule::from_handle_unlinked(config.jobs().handle_unlinked());
+        let is_criteria_met = Receiver::is_criteria_met(&torrent, &rule, &known_hardlinks, None, &recent_uploads, &mut HashMap::new(), &config).unwrap();
+        fs::remove_dir_all(&test_dir).unwrap();
+        is_criteria_met
+    }
+
+    #[test]
+    fn idle_torrent_meets_criteria() {
+        assert!(is_criteria_met(0, 0));
+    }
+
+    #[test]
+    fn uploading_torrent_is_kept_without_upload_history() {
+        assert!(!is_criteria_met(200_000, 0));
+    }
+
+    #[test]
+    fn recently_uploaded_torrent_is_kept() {
+        assert!(!is_criteria_met(0, 2_000_000_000));
+    }
+}
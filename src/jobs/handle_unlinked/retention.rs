@@ -0,0 +1,355 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Context;
+
+use crate::{
+    config::{Config, Quota},
+    debug, info,
+    jobs::{
+        handle_unlinked::{action_taker::ActionTaker, notifier::Notifier},
+        models::{rule::Rule, torrent_links::TorrentLinks},
+        utils::{action_utils::ActionUtils, cross_seed_utils::CrossSeedGroups, hit_and_run_utils::HitAndRunUtils, tracker_domain_utils::TrackerDomainUtils},
+    },
+    logger::enums::category::Category,
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    trace,
+    utils::{date_utils::DateUtils, discord_webhook_utils::DiscordWebhookUtils, domain_utils::DomainUtils},
+    warn,
+};
+
+/**
+ * Torrents that are kept or removed together (a cross-seed group or a single torrent)
+ */
+struct RetentionUnit {
+    torrents: Vec<Torrent>,
+    size: i64,
+    /**
+     * Bytes freed by removing the unit, files that are still linked stay on disk
+     */
+    reclaimable_size: i64,
+    score: f64,
+    is_eligible: bool,
+}
+
+/**
+ * Torrents that have to be removed to meet a quota
+ */
+pub struct RetentionRemoval {
+    quota_str: String,
+    score: f64,
+    torrents: Vec<Torrent>,
+}
+
+pub struct Retention;
+
+impl Retention {
+    /**
+     * Get the lowest scored eligible torrents of every quota that have to be removed until the quota is met
+     * torrents should not contain torrents that were already deleted in this run since they don't use storage anymore
+     */
+    pub fn get_removals(
+        torrents: &HashMap<String, Torrent>,
+        torrents_links: &HashMap<String, TorrentLinks>,
+        torrent_rules: &HashMap<String, Rule>,
+        cross_seed_groups: &CrossSeedGroups,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+        config: &Config,
+    ) -> Vec<RetentionRemoval> {
+        let mut removals: Vec<RetentionRemoval> = Vec::new();
+        let mut removed_hashes: HashSet<String> = HashSet::new();
+
+        for quota in config.jobs().handle_unlinked().retention().quotas() {
+            let quota_str = Retention::get_quota_str(quota);
+            let max_size = (quota.max_size_gb() * 1000.0 * 1000.0 * 1000.0) as i64;
+
+            // Build units that have at least 1 torrent in the quota
+            let mut units: Vec<RetentionUnit> = Vec::new();
+            for cross_seed_group in cross_seed_groups.groups() {
+                let torrents: Vec<Torrent> = cross_seed_group.hashes().iter().filter(|hash| !removed_hashes.contains(*hash)).filter_map(|hash| torrents.get(hash)).cloned().collect();
+                if !torrents.iter().any(|torrent| Retention::is_in_quota(torrent, quota, tracker_domain_utils)) {
+                    continue;
+                }
+                let size = torrents.iter().map(|torrent| *torrent.total_size()).max().unwrap_or(0);
+                let bytes_linked = torrents
+                    .iter()
+                    .filter_map(|torrent| torrents_links.get(torrent.hash()))
+                    .map(|torrent_links| torrent_links.bytes_linked() as i64)
+                    .max()
+                    .unwrap_or(0);
+                units.push(RetentionUnit {
+                    size,
+                    reclaimable_size: (size - bytes_linked).max(0),
+                    score: torrents
+                        .iter()
+                        .map(|torrent| Retention::get_score(torrent, torrents_links.get(torrent.hash()), tracker_domain_utils, config))
+                        .fold(f64::MIN, f64::max),
+                    is_eligible: torrents
+                        .iter()
+                        .all(|torrent| Retention::is_eligible(torrent, torrent_rules.get(torrent.hash()), torrents_links.get(torrent.hash()), hit_and_run_utils, tracker_domain_utils, config)),
+                    torrents,
+                });
+            }
+
+            let mut usage: i64 = units.iter().map(|unit| unit.size).sum();
+            info!(Category::HandleUnlinked, "Quota {} uses {:.2}GB of {:.2}GB", quota_str, usage as f64 / 1000.0 / 1000.0 / 1000.0, quota.max_size_gb());
+            if usage <= max_size {
+                continue;
+            }
+
+            // Remove lowest scored eligible units first
+            let units_count = units.len();
+            let mut eligible_units: Vec<RetentionUnit> = units.into_iter().filter(|unit| unit.is_eligible).collect();
+            eligible_units.sort_by(|a, b| a.score.total_cmp(&b.score));
+            debug!(Category::HandleUnlinked, "{} of {} torrent units are eligible for quota {}", eligible_units.len(), units_count, quota_str);
+
+            for unit in eligible_units {
+                if usage <= max_size {
+                    break;
+                }
+                usage -= unit.reclaimable_size;
+                removed_hashes.extend(unit.torrents.iter().map(|torrent| torrent.hash().to_string()));
+                removals.push(RetentionRemoval {
+                    quota_str: quota_str.clone(),
+                    score: unit.score,
+                    torrents: unit.torrents,
+                });
+            }
+
+            if usage > max_size {
+                warn!(
+                    Category::HandleUnlinked,
+                    "Quota {} is still exceeded ({:.2}GB of {:.2}GB) but no eligible torrents are left",
+                    quota_str,
+                    usage as f64 / 1000.0 / 1000.0 / 1000.0,
+                    quota.max_size_gb()
+                );
+            }
+        }
+
+        removals
+    }
+
+    /**
+     * Notify about and take action on torrents removed for a quota
+     */
+    pub async fn remove(
+        torrent_manager: Arc<TorrentManager>,
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        action_utils: &mut ActionUtils,
+        removals: &[RetentionRemoval],
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+        config: &Config,
+    ) -> Result<(), anyhow::Error> {
+        for removal in removals {
+            // Log
+            info!(Category::HandleUnlinked, "Removing for quota {} (score {:.2}):", removal.quota_str, removal.score);
+            for torrent in &removal.torrents {
+                info!(Category::HandleUnlinked, "  -> {}", torrent.name());
+            }
+
+            // Notification
+            if *config.notification().on_job_action() {
+                Notifier::send_retention_notification(discord_webhook_utils, &removal.torrents, removal.score, &removal.quota_str, tracker_domain_utils, config)
+                    .await
+                    .context("Failed to send retention notification")?;
+            }
+
+            // Take action
+            ActionTaker::take_retention_action(torrent_manager.clone(), action_utils, &removal.torrents, &removal.quota_str, hit_and_run_utils, tracker_domain_utils, config).await?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Higher score = kept longer
+     * The most specific tracker weight wins if several match (tracker.example.org over example.org)
+     */
+    fn get_score(torrent: &Torrent, torrent_links: Option<&TorrentLinks>, tracker_domain_utils: &TrackerDomainUtils, config: &Config) -> f64 {
+        let retention = config.jobs().handle_unlinked().retention();
+        let weights = retention.weights();
+
+        let age_days = (DateUtils::get_current_utc_datetime().timestamp() - torrent.added_on()).max(0) as f64 / 60.0 / 60.0 / 24.0;
+        let size_gb = *torrent.total_size() as f64 / 1000.0 / 1000.0 / 1000.0;
+        let linked_percent = torrent_links.map(|torrent_links| torrent_links.linked_percent()).unwrap_or(0.0);
+        let tracker_domain = tracker_domain_utils.get_tracker_domain(torrent);
+        let tracker_weight = retention
+            .tracker_weights()
+            .iter()
+            .filter(|(configured_domain, _)| DomainUtils::matches(&tracker_domain, configured_domain))
+            .max_by_key(|(configured_domain, _)| configured_domain.len())
+            .map(|(_, weight)| *weight)
+            .unwrap_or(0.0);
+
+        let score =
+            age_days * weights.age_days() + *torrent.ratio() as f64 * weights.ratio() + *torrent.num_complete() as f64 * weights.seeders() + size_gb * weights.size_gb() + linked_percent / 100.0 * weights.linked() + tracker_weight;
+        trace!(Category::HandleUnlinked, "Torrent has retention score {:.2}: ({}) {}", score, torrent.hash(), torrent.name());
+        score
+    }
+
+    /**
+     * Completed, not linked, not protected, seeded for at least min_seeding_days of its rule and not at risk of a hit-and-run
+     * Removing a linked torrent wouldn't free its linked files, so it never counts towards a quota
+     */
+    pub fn is_eligible(torrent: &Torrent, rule_option: Option<&Rule>, torrent_links: Option<&TorrentLinks>, hit_and_run_utils: &HitAndRunUtils, tracker_domain_utils: &TrackerDomainUtils, config: &Config) -> bool {
+        let is_linked = torrent_links.is_some_and(|torrent_links| torrent_links.is_linked(config.jobs().handle_unlinked().min_linked_percent()));
+        match rule_option {
+            Some(rule) => {
+                *torrent.completion_on() != -1
+                    && !is_linked
+                    && !rule.is_protected(torrent.tags())
+                    && torrent.seeding_time() / 60 / 60 / 24 >= rule.min_seeding_days() as i64
+                    && !hit_and_run_utils.is_at_risk(torrent, tracker_domain_utils)
+            }
+            None => false,
+        }
+    }
+
+    /**
+     * Rule name actions taken for a quota are recorded with
+     */
+    pub fn get_rule_name(quota_str: &str) -> String {
+        format!("retention {}", quota_str)
+    }
+
+    pub fn is_retention_rule_name(rule_name: &str) -> bool {
+        rule_name.starts_with("retention [")
+    }
+
+    fn is_in_quota(torrent: &Torrent, quota: &Quota, tracker_domain_utils: &TrackerDomainUtils) -> bool {
+        (quota.category().is_empty() || torrent.category() == quota.category()) && (quota.tracker().is_empty() || DomainUtils::matches(&tracker_domain_utils.get_tracker_domain(torrent), quota.tracker()))
+    }
+
+    fn get_quota_str(quota: &Quota) -> String {
+        match (quota.category().is_empty(), quota.tracker().is_empty()) {
+            (true, true) => String::from("[all torrents]"),
+            (false, true) => format!("[category {}]", quota.category()),
+            (true, false) => format!("[tracker {}]", quota.tracker()),
+            (false, false) => format!("[category {}, tracker {}]", quota.category(), quota.tracker()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::Berlin;
+    use serde_json::json;
+
+    use super::*;
+    use crate::jobs::{models::torrent_links::FileLinks, utils::cross_seed_utils::CrossSeedUtils};
+
+    const GB: i64 = 1000 * 1000 * 1000;
+
+    fn get_config(handle_unlinked: serde_json::Value) -> Config {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        Config::from_test_fields(json!({ "jobs": { "handle_unlinked": handle_unlinked } }))
+    }
+
+    /**
+     * Completed torrent that seeded for 30 days, so it's only protected by links
+     */
+    fn get_torrent(hash: &str, size_gb: i64, ratio: f64, tracker: &str) -> Torrent {
+        Torrent::from_test_fields(json!({
+            "hash": hash,
+            "name": hash,
+            "content_path": format!("/missing/{}", hash),
+            "total_size": size_gb * GB,
+            "ratio": ratio,
+            "seeding_time": 30 * 24 * 60 * 60,
+            "tracker": format!("https://{}/announce", tracker),
+        }))
+    }
+
+    fn get_torrent_links(linked_gb: i64, unlinked_gb: i64) -> TorrentLinks {
+        TorrentLinks::new(vec![
+            FileLinks::new(String::from("/missing/linked"), (linked_gb * GB) as u64, true, Vec::new()),
+            FileLinks::new(String::from("/missing/unlinked"), (unlinked_gb * GB) as u64, false, Vec::new()),
+        ])
+    }
+
+    /**
+     * Hashes of every removal in order
+     */
+    fn get_removed_hashes(torrents: &[Torrent], torrents_links: &HashMap<String, TorrentLinks>, config: &Config) -> Vec<Vec<String>> {
+        let torrents_by_hash: HashMap<String, Torrent> = torrents.iter().map(|torrent| (torrent.hash().to_string(), torrent.clone())).collect();
+        let rule = Rule::from_handle_unlinked(config.jobs().handle_unlinked());
+        let torrent_rules: HashMap<String, Rule> = torrents.iter().map(|torrent| (torrent.hash().to_string(), rule.clone())).collect();
+        let cross_seed_groups = CrossSeedUtils::get_cross_seed_groups(&torrents.iter().collect::<Vec<&Torrent>>()).unwrap();
+        Retention::get_removals(
+            &torrents_by_hash,
+            torrents_links,
+            &torrent_rules,
+            &cross_seed_groups,
+            &HitAndRunUtils::new(config),
+            &TrackerDomainUtils::default(),
+            config,
+        )
+        .iter()
+        .map(|removal| removal.torrents.iter().map(|torrent| torrent.hash().to_string()).collect())
+        .collect()
+    }
+
+    #[test]
+    fn nothing_is_removed_within_the_quota() {
+        let config = get_config(json!({ "retention": { "quotas": [{ "max_size_gb": 20.0 }] } }));
+        let torrents = [get_torrent("a", 10, 1.0, "tracker.example.org"), get_torrent("b", 10, 2.0, "tracker.example.org")];
+        assert!(get_removed_hashes(&torrents, &HashMap::new(), &config).is_empty());
+    }
+
+    #[test]
+    fn lowest_scores_are_removed_until_the_quota_is_met() {
+        let config = get_config(json!({ "retention": { "quotas": [{ "max_size_gb": 15.0 }] } }));
+        let torrents = [
+            get_torrent("a", 10, 3.0, "tracker.example.org"),
+            get_torrent("b", 10, 1.0, "tracker.example.org"),
+            get_torrent("c", 10, 2.0, "tracker.example.org"),
+        ];
+        assert_eq!(get_removed_hashes(&torrents, &HashMap::new(), &config), vec![vec!["b"], vec!["c"]]);
+    }
+
+    #[test]
+    fn quota_only_counts_torrents_of_its_tracker() {
+        let config = get_config(json!({ "retention": { "quotas": [{ "tracker": "example.org", "max_size_gb": 10.0 }] } }));
+        let torrents = [get_torrent("a", 10, 1.0, "tracker.example.org"), get_torrent("b", 10, 2.0, "tracker.example.org"), get_torrent("c", 10, 0.0, "other.org")];
+        assert_eq!(get_removed_hashes(&torrents, &HashMap::new(), &config), vec![vec!["a"]]);
+    }
+
+    #[test]
+    fn linked_torrents_are_never_removed() {
+        let config = get_config(json!({ "retention": { "quotas": [{ "max_size_gb": 10.0 }] } }));
+        let torrents = [get_torrent("a", 10, 1.0, "tracker.example.org"), get_torrent("b", 10, 2.0, "tracker.example.org")];
+        let torrents_links = HashMap::from([(String::from("a"), get_torrent_links(10, 0))]);
+        assert_eq!(get_removed_hashes(&torrents, &torrents_links, &config), vec![vec!["b"]]);
+    }
+
+    #[test]
+    fn only_freed_bytes_count_towards_the_quota() {
+        let config = get_config(json!({ "min_linked_percent": 50.0, "retention": { "quotas": [{ "max_size_gb": 13.0 }], "weights": { "linked": 0.0 } } }));
+        let torrents = [get_torrent("a", 10, 1.0, "tracker.example.org"), get_torrent("b", 10, 2.0, "tracker.example.org")];
+        // Removing a only frees its 6GB that are not linked, so b has to be removed as well
+        let torrents_links = HashMap::from([(String::from("a"), get_torrent_links(4, 6))]);
+        assert_eq!(get_removed_hashes(&torrents, &torrents_links, &config), vec![vec!["a"], vec!["b"]]);
+    }
+
+    #[test]
+    fn most_specific_tracker_weight_wins() {
+        let config = get_config(json!({ "retention": { "weights": { "ratio": 0.0, "seeders": 0.0, "size_gb": 0.0 }, "tracker_weights": { "example.org": 10.0, "tracker.example.org": -10.0 } } }));
+        let tracker_domain_utils = TrackerDomainUtils::default();
+        assert_eq!(Retention::get_score(&get_torrent("a", 10, 1.0, "tracker.example.org"), None, &tracker_domain_utils, &config), -10.0);
+        assert_eq!(Retention::get_score(&get_torrent("b", 10, 1.0, "other.example.org"), None, &tracker_domain_utils, &config), 10.0);
+    }
+
+    #[test]
+    fn linked_percent_raises_the_score() {
+        let config = get_config(json!({ "retention": { "weights": { "ratio": 0.0, "seeders": 0.0, "size_gb": 0.0, "linked": 100.0 } } }));
+        let tracker_domain_utils = TrackerDomainUtils::default();
+        let torrent = get_torrent("a", 10, 1.0, "tracker.example.org");
+        assert_eq!(Retention::get_score(&torrent, None, &tracker_domain_utils, &config), 0.0);
+        assert_eq!(Retention::get_score(&torrent, Some(&get_torrent_links(5, 5)), &tracker_domain_utils, &config), 50.0);
+    }
+}
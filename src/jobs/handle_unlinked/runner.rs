@@ -1,4 +1,7 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use anyhow::Context;
 use reqwest::Url;
@@ -7,30 +10,39 @@ use crate::{
     config::Config,
     debug, info,
     jobs::{
-        enums::strike_type::StrikeType,
-        handle_unlinked::{action_taker::ActionTaker, notifier::Notifier, receiver::Receiver, striker::Striker},
-        utils::strike_utils::StrikeUtils,
+        deferred_actions::notifier::DeferralNotifier,
+        enums::{action_outcome::ActionOutcome, action_type::ActionType, strike_type::StrikeType},
+        handle_unlinked::{action_taker::ActionTaker, notifier::Notifier, receiver::Receiver, retention::Retention, striker::Striker},
+        models::{job_run_stats::JobRunStats, pipeline_context::PipelineContext, quiet_windows::QuietWindows, rule::Rule, torrent_links::TorrentLinks},
+        utils::{action_utils::ActionUtils, cross_seed_utils::CrossSeedGroups, hit_and_run_utils::HitAndRunUtils, rule_utils::RuleUtils, strike_utils::StrikeUtils, tracker_domain_utils::TrackerDomainUtils},
     },
     logger::enums::category::Category,
     torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
-    utils::discord_webhook_utils::DiscordWebhookUtils,
+    utils::{date_utils::DateUtils, db_manager::DbManager, discord_webhook_utils::DiscordWebhookUtils},
+    warn,
 };
 
 pub struct HandleUnlinked {
     torrent_manager: Arc<TorrentManager>,
+    db_manager: DbManager,
     config: Config,
     torrents_path: String,
 }
 
 impl HandleUnlinked {
-    pub fn new(torrent_manager: Arc<TorrentManager>, config: Config, torrents_path: String) -> Self {
-        Self { torrent_manager, config, torrents_path }
+    pub fn new(torrent_manager: Arc<TorrentManager>, db_manager: DbManager, config: Config, torrents_path: String) -> Self {
+        Self {
+            torrent_manager,
+            db_manager,
+            config,
+            torrents_path,
+        }
     }
 
     /**
      * Run
      */
-    pub async fn run(&self) -> Result<(), anyhow::Error> {
+    pub async fn run(&self, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
         let discord_webhook_url: Option<Url> = match self.config.notification().discord_webhook_url().len() > 1 {
             true => Some(Url::parse(self.config.notification().discord_webhook_url()).context("Failed to parse discord_webhook_url")?),
             false => None,
@@ -40,53 +52,209 @@ impl HandleUnlinked {
         // Login
         self.torrent_manager.login().await.context("Failed to login to torrent client")?;
 
-        // Get torrents from torrent client with criteria
-        let torrents_criteria: HashMap<String, (Torrent, bool)> = Receiver::get_torrents_criteria(self.torrent_manager.clone(), &self.config, &self.torrents_path).await?;
+        // Get torrents from torrent client
+        debug!(Category::HandleUnlinked, "Getting torrents...");
+        let torrents = pipeline_context.get_torrents(&self.torrent_manager).await?;
+        debug!(Category::HandleUnlinked, "Received {} torrents", torrents.len());
+
+        // Resolve tracker domains of every torrent if tracker overrides or the storage quotas need them
+        let hit_and_run_utils = HitAndRunUtils::new(&self.config);
+        let mut tracker_domain_utils = TrackerDomainUtils::default();
+        if RuleUtils::has_tracker_overrides(self.config.jobs().handle_unlinked().overrides()) || *self.config.jobs().handle_unlinked().retention().enabled() {
+            debug!(Category::HandleUnlinked, "Resolving tracker domains...");
+            tracker_domain_utils.resolve_tracker_domains(&self.torrent_manager, &torrents).await?;
+            debug!(Category::HandleUnlinked, "Done resolving tracker domains");
+        }
+
+        // Resolve rules
+        let base_rule = Rule::from_handle_unlinked(self.config.jobs().handle_unlinked());
+        let torrent_rules: HashMap<String, Rule> = RuleUtils::get_torrent_rules(&torrents, &base_rule, self.config.jobs().handle_unlinked().overrides(), &tracker_domain_utils, Category::HandleUnlinked);
 
-        info!(Category::HandleUnlinked, "{} torrents meet criteria", torrents_criteria.values().filter(|(_, is_criteria_met)| *is_criteria_met).count(),);
+        // Get known hardlinks
+        debug!(Category::HandleUnlinked, "Getting known torrent hardlinks...");
+        let known_hardlinks: HashMap<(u64, u64), u64> = pipeline_context.get_known_hardlinks(&self.torrents_path, self.config.paths().link_paths())?;
+        debug!(
+            Category::HandleUnlinked,
+            "Found {} unique files ({} total) in torrent folder and link paths",
+            known_hardlinks.len(),
+            known_hardlinks.values().sum::<u64>()
+        );
+
+        // Get torrents with criteria
+        let (torrents_criteria, torrents_links): (HashMap<String, (Torrent, bool)>, HashMap<String, TorrentLinks>) =
+            Receiver::get_torrents_criteria(&torrents, &torrent_rules, &self.db_manager, &self.config, &known_hardlinks).await?;
+
+        let criteria_met_count = torrents_criteria.values().filter(|(_, is_criteria_met)| *is_criteria_met).count();
+        info!(Category::HandleUnlinked, "{} torrents meet criteria", criteria_met_count);
 
         // Striking
         debug!(Category::HandleUnlinked, "Striking torrents...");
-        let mut strike_utils = StrikeUtils::new()?;
-        let limit_reached_torrents = Striker::strike_torrents(&mut strike_utils, &torrents_criteria, &self.config)?;
+        let mut strike_utils = StrikeUtils::new(self.db_manager.clone(), self.config.jobs().handle_unlinked().strike_policy().clone()).with_job_schedule(pipeline_context.job_schedule());
+        let limit_reached_torrents = Striker::strike_torrents(&mut strike_utils, &torrents_criteria, &torrent_rules)?;
         debug!(Category::HandleUnlinked, "Done striking torrents");
 
         info!(Category::HandleUnlinked, "{} torrents that meet criteria have reached their strike limits", limit_reached_torrents.len(),);
+        let job_run_stats = JobRunStats::new(torrents.len(), criteria_met_count, limit_reached_torrents.len());
 
-        // Go through torrents
-        for torrent in &limit_reached_torrents {
-            // Log
-            info!(Category::HandleUnlinked, "Torrent unlinked: {}", torrent.name());
+        // Resolve tracker domains for the hit-and-run policies, already resolved ones aren't requested again
+        debug!(Category::HandleUnlinked, "Resolving tracker domains...");
+        tracker_domain_utils.resolve_tracker_domains(&self.torrent_manager, &limit_reached_torrents).await?;
+        debug!(Category::HandleUnlinked, "Done resolving tracker domains");
 
-            // Notification
-            if *self.config.notification().on_job_action() {
-                Notifier::send_notification(&mut discord_webhook_utils, torrent, &self.config).await.context("Failed to send notification")?;
+        // Group cross-seeds, files are only walked if there is a torrent to act on
+        let cross_seed_groups = match limit_reached_torrents.is_empty() && !*self.config.jobs().handle_unlinked().retention().enabled() {
+            true => CrossSeedGroups::default(),
+            false => {
+                debug!(Category::HandleUnlinked, "Grouping cross-seeded torrents...");
+                let cross_seed_groups = pipeline_context.get_cross_seed_groups(&torrents)?;
+                debug!(Category::HandleUnlinked, "Done grouping cross-seeded torrents");
+                cross_seed_groups
             }
+        };
 
-            // Take action
-            ActionTaker::take_action(self.torrent_manager.clone(), &torrents_criteria, torrent, &self.config).await?;
+        let quiet_windows = pipeline_context.get_quiet_windows(QuietWindows::from_config(self.config.quiet_hours(), self.config.jobs().handle_unlinked().quiet_hours()).context("Failed to get quiet hours")?);
+        let mut action_utils = ActionUtils::new(self.db_manager.clone(), Category::HandleUnlinked, quiet_windows);
+        let limit_reached_hashes: Vec<String> = limit_reached_torrents.iter().map(|torrent| torrent.hash().to_string()).collect();
+        let eligible_hashes: HashSet<String> = limit_reached_hashes.iter().cloned().collect();
+        // Torrents skipped for a hit-and-run requirement keep their strikes
+        let mut acted_hashes: HashSet<String> = HashSet::new();
+        // Torrents handled here only free storage for the quotas if they were actually deleted or their delete is deferred
+        let mut deleted_hashes: HashSet<String> = HashSet::new();
+
+        // Go through cross-seed groups
+        for cross_seed_group in cross_seed_groups.get_groups_of_hashes(&limit_reached_hashes) {
+            // Every member is eligible, handle them and their data together
+            if cross_seed_group.is_cross_seeded() && cross_seed_group.is_all_eligible(&eligible_hashes) {
+                let group_torrents: Vec<Torrent> = cross_seed_group.hashes().iter().filter_map(|hash| torrents_criteria.get(hash)).map(|(torrent, _)| torrent.clone()).collect();
+                let group_rule = RuleUtils::get_group_rule(cross_seed_group.hashes(), &torrent_rules)?;
+
+                // Log
+                info!(Category::HandleUnlinked, "Cross-seed group unlinked ({} torrents, rule {}):", group_torrents.len(), group_rule.name());
+                for torrent in &group_torrents {
+                    info!(Category::HandleUnlinked, "  -> {}", torrent.name());
+                }
+
+                // Notification
+                if *self.config.notification().on_job_action() {
+                    Notifier::send_group_notification(&mut discord_webhook_utils, &group_torrents, &torrents_links, &group_rule, &hit_and_run_utils, &tracker_domain_utils)
+                        .await
+                        .context("Failed to send group notification")?;
+                }
+
+                // Take action
+                let action_outcome = ActionTaker::take_group_action(self.torrent_manager.clone(), &mut action_utils, &group_torrents, &group_rule, &hit_and_run_utils, &tracker_domain_utils).await?;
+                if action_outcome != ActionOutcome::Skipped {
+                    acted_hashes.extend(cross_seed_group.hashes().iter().cloned());
+                }
+                if action_outcome == ActionOutcome::Deleted {
+                    deleted_hashes.extend(cross_seed_group.hashes().iter().cloned());
+                }
+                continue;
+            }
+
+            // Go through eligible torrents of the group
+            for torrent in limit_reached_torrents.iter().filter(|torrent| cross_seed_group.hashes().iter().any(|hash| hash == torrent.hash())) {
+                let Some(rule) = torrent_rules.get(torrent.hash()) else {
+                    warn!(Category::HandleUnlinked, "Didn't find rule for torrent: ({}) {}", torrent.hash(), torrent.name());
+                    continue;
+                };
+
+                // Log
+                info!(Category::HandleUnlinked, "Torrent unlinked (rule {}): {}", rule.name(), torrent.name());
+
+                // Notification
+                if *self.config.notification().on_job_action() {
+                    Notifier::send_notification(&mut discord_webhook_utils, torrent, torrents_links.get(torrent.hash()), rule, &hit_and_run_utils, &tracker_domain_utils, &self.config)
+                        .await
+                        .context("Failed to send notification")?;
+                }
+
+                // Take action
+                let action_outcome = ActionTaker::take_action(
+                    self.torrent_manager.clone(),
+                    &mut action_utils,
+                    !cross_seed_group.is_all_eligible(&eligible_hashes),
+                    torrent,
+                    rule,
+                    &hit_and_run_utils,
+                    &tracker_domain_utils,
+                )
+                .await?;
+                if action_outcome != ActionOutcome::Skipped {
+                    acted_hashes.insert(torrent.hash().to_string());
+                }
+                if action_outcome == ActionOutcome::Deleted {
+                    deleted_hashes.insert(torrent.hash().to_string());
+                }
+            }
+        }
+
+        // Storage quotas
+        if *self.config.jobs().handle_unlinked().retention().enabled() {
+            debug!(Category::HandleUnlinked, "Enforcing storage quotas...");
+            // Deletes queued during quiet hours (of this or earlier runs) free their storage once they are taken
+            let deferred_action_records = action_utils.get_deferred_actions().context("Failed to get deferred actions")?;
+            deleted_hashes.extend(
+                deferred_action_records
+                    .iter()
+                    .filter(|deferred_action_record| deferred_action_record.job() == Category::HandleUnlinked.to_string() && deferred_action_record.action_entry().action() == ActionType::Delete.to_string())
+                    .map(|deferred_action_record| deferred_action_record.action_entry().hash().to_string()),
+            );
+            let remaining_torrents: HashMap<String, Torrent> = torrents
+                .into_iter()
+                .filter(|torrent| !deleted_hashes.contains(torrent.hash()))
+                .map(|torrent| (torrent.hash().to_string(), torrent))
+                .collect();
+            let removals = Retention::get_removals(&remaining_torrents, &torrents_links, &torrent_rules, &cross_seed_groups, &hit_and_run_utils, &tracker_domain_utils, &self.config);
+            Retention::remove(
+                self.torrent_manager.clone(),
+                &mut discord_webhook_utils,
+                &mut action_utils,
+                &removals,
+                &hit_and_run_utils,
+                &tracker_domain_utils,
+                &self.config,
+            )
+            .await
+            .context("Failed to enforce storage quotas")?;
+            debug!(Category::HandleUnlinked, "Done enforcing storage quotas");
+        }
+
+        // Deferred actions
+        if let Some((deferred_count, deferral_end)) = action_utils.get_deferred() {
+            info!(
+                Category::HandleUnlinked,
+                "{} actions deferred until {} (quiet hours)",
+                deferred_count,
+                DateUtils::convert_utc_datetime_to_local_string(&deferral_end)
+            );
+            if *self.config.notification().on_job_action() {
+                DeferralNotifier::send_deferral_notification(&mut discord_webhook_utils, &Category::HandleUnlinked.to_string(), deferred_count, &deferral_end)
+                    .await
+                    .context("Failed to send deferral notification")?;
+            }
         }
 
         // Clean db
         debug!(Category::HandleUnlinked, "Cleaning db...");
-        self.clean_db(&mut strike_utils, &torrents_criteria, &limit_reached_torrents)?;
+        self.clean_db(&mut strike_utils, &torrents_criteria, &acted_hashes)?;
         debug!(Category::HandleUnlinked, "Cleaned db");
 
         // Logout
         self.torrent_manager.logout().await.context("Failed to logout of torrent client")?;
 
-        Ok(())
+        Ok(job_run_stats)
     }
 
     /**
      * Clean db
      */
-    fn clean_db(&self, strike_utils: &mut StrikeUtils, torrents_criteria: &HashMap<String, (Torrent, bool)>, limit_reached_torrents: &[Torrent]) -> Result<(), anyhow::Error> {
+    fn clean_db(&self, strike_utils: &mut StrikeUtils, torrents_criteria: &HashMap<String, (Torrent, bool)>, acted_hashes: &HashSet<String>) -> Result<(), anyhow::Error> {
         let mut hashes_to_remove: Vec<String> = Vec::new();
+        let mut hashes_to_reset: Vec<String> = Vec::new();
 
         // Torrents that reached limit and were handled
-        let limit_reached_torrent_hashes: Vec<String> = limit_reached_torrents.iter().map(|torrent| torrent.hash().to_string()).collect();
-        hashes_to_remove.extend(limit_reached_torrent_hashes);
+        hashes_to_remove.extend(acted_hashes.iter().cloned());
 
         let strike_records = strike_utils.get_strikes(&StrikeType::HandleUnlinked, None).context("Failed to get all strikes for HandleUnlinked")?;
         for strike_record in strike_records {
@@ -94,7 +262,7 @@ impl HandleUnlinked {
                 // Check for stuff that doesn't meet criteria
                 Some((_, is_criteria_met)) => {
                     if !*is_criteria_met {
-                        hashes_to_remove.push(strike_record.hash().to_string());
+                        hashes_to_reset.push(strike_record.hash().to_string());
                     }
                 }
                 // Check for stuff that doesn't exist in torrents anymore
@@ -104,9 +272,10 @@ impl HandleUnlinked {
             }
         }
 
-        debug!(Category::HandleUnlinked, "Deleting {} hashes", hashes_to_remove.len());
+        debug!(Category::HandleUnlinked, "Deleting {} hashes, resetting {} hashes", hashes_to_remove.len(), hashes_to_reset.len());
 
-        strike_utils.delete(StrikeType::HandleUnlinked, hashes_to_remove).context("Failed to delete hashes")?;
+        strike_utils.delete(StrikeType::HandleUnlinked, &hashes_to_remove).context("Failed to delete hashes")?;
+        strike_utils.reset(StrikeType::HandleUnlinked, &hashes_to_reset).context("Failed to reset hashes")?;
 
         Ok(())
     }
@@ -3,8 +3,7 @@ use std::collections::HashMap;
 use anyhow::Context;
 
 use crate::{
-    config::Config,
-    jobs::{enums::strike_type::StrikeType, utils::strike_utils::StrikeUtils},
+    jobs::{enums::strike_type::StrikeType, models::rule::Rule, utils::strike_utils::StrikeUtils},
     logger::enums::category::Category,
     torrent_clients::models::torrent::Torrent,
     warn,
@@ -17,20 +16,24 @@ impl Striker {
      * Strike torrents
      * Returns: Vec of Torrents that reached the strike limit
      */
-    pub fn strike_torrents(strike_utils: &mut StrikeUtils, torrents_criteria: &HashMap<String, (Torrent, bool)>, config: &Config) -> Result<Vec<Torrent>, anyhow::Error> {
+    pub fn strike_torrents(strike_utils: &mut StrikeUtils, torrents_criteria: &HashMap<String, (Torrent, bool)>, torrent_rules: &HashMap<String, Rule>) -> Result<Vec<Torrent>, anyhow::Error> {
         // Get torrent hashes of torrents that meet criteria
         let criteria_met_hashes: Vec<String> = torrents_criteria.values().filter(|(_, met)| *met).map(|(torrent, _)| torrent.hash().to_string()).collect();
 
         // Strike torrents that meet criteria
-        strike_utils.strike(&StrikeType::HandleUnlinked, criteria_met_hashes.clone()).context("Failed to strike hashes")?;
+        strike_utils.strike(&StrikeType::HandleUnlinked, &criteria_met_hashes).context("Failed to strike hashes")?;
 
         // Get all strike stuff from the db for this job
-        let strike_records = strike_utils.get_strikes(&StrikeType::HandleUnlinked, Some(criteria_met_hashes)).context("Failed get strikes")?;
+        let strike_records = strike_utils.get_strikes(&StrikeType::HandleUnlinked, Some(&criteria_met_hashes)).context("Failed get strikes")?;
 
         // Get torrents that reached the strike limits
         let mut limit_reached_torrents: Vec<Torrent> = Vec::new();
         for strike_record in strike_records {
-            if strike_record.is_limit_reached(config.jobs().handle_unlinked().required_strikes(), config.jobs().handle_unlinked().min_strike_days()) {
+            let Some(rule) = torrent_rules.get(strike_record.hash()) else {
+                warn!(Category::HandleUnlinked, "Didn't find rule for striked torrent: {}", strike_record.hash());
+                continue;
+            };
+            if strike_record.is_limit_reached(rule.required_strikes(), rule.min_strike_days(), strike_utils.strike_policy_type()) {
                 if let Some(torrent_criteria) = torrents_criteria.get(strike_record.hash()) {
                     limit_reached_torrents.push(torrent_criteria.clone().0);
                 } else {
@@ -1,54 +1,125 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use anyhow::Context;
 
 use crate::{
-    config::Config,
     debug, info,
-    jobs::enums::action_type::ActionType,
+    jobs::{
+        enums::{action_outcome::ActionOutcome, action_type::ActionType},
+        models::rule::Rule,
+        utils::{
+            action_utils::{ActionEntry, ActionUtils},
+            hit_and_run_utils::HitAndRunUtils,
+            tracker_domain_utils::TrackerDomainUtils,
+        },
+    },
     logger::enums::category::Category,
     torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    warn,
 };
 
 pub struct ActionTaker;
 
 impl ActionTaker {
     /**
-     * Take action
+     * Take action, is_any_not_eligible is true if another torrent of its cross-seed group depends on its files
      */
-    pub async fn take_action(torrent_manager: Arc<TorrentManager>, torrents_criteria: &HashMap<String, (Torrent, bool)>, torrent: &Torrent, config: &Config) -> Result<(), anyhow::Error> {
-        let mut is_any_not_meeting_criteria = false;
-        for (t, is_criteria_met) in torrents_criteria.values() {
-            if !*is_criteria_met && torrent.content_path() == t.content_path() {
-                is_any_not_meeting_criteria = true;
-                break;
-            }
+    pub async fn take_action(
+        torrent_manager: Arc<TorrentManager>,
+        action_utils: &mut ActionUtils,
+        is_any_not_eligible: bool,
+        torrent: &Torrent,
+        rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<ActionOutcome, anyhow::Error> {
+        let action_type = ActionType::from_str(rule.action())?;
+        // Hit-and-run requirements override every job config
+        if action_type != ActionType::Test
+            && let Some(risk_str) = hit_and_run_utils.get_risk_str(torrent, tracker_domain_utils)
+        {
+            info!(Category::HandleUnregistered, "Action: Skipped, hit-and-run requirement not met ({})", risk_str);
+            let action_entry = ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), rule.name());
+            action_utils.record_skipped(&[action_entry], &format!("hit-and-run requirement not met ({})", risk_str))?;
+            return Ok(ActionOutcome::Skipped);
         }
-        let action_type = ActionType::from_str(config.jobs().handle_unregistered().action())?;
         match action_type {
             ActionType::Test => {
                 info!(Category::HandleUnregistered, "Action: Test");
-                if is_any_not_meeting_criteria {
+                if is_any_not_eligible {
                     debug!(Category::HandleUnregistered, "  -> At least 1 other torrent depends this torrents files");
                 }
+                action_utils.record(&[ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), rule.name())], &Ok(()))?;
+            }
+            ActionType::Recheck => {
+                warn!(Category::HandleUnregistered, "Recheck action not supported on handle_unregistered");
+                return Ok(ActionOutcome::Skipped);
             }
             ActionType::Stop => {
+                let action_entry = ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), rule.name());
+                if action_utils.is_deferred(std::slice::from_ref(&action_entry))? {
+                    return Ok(ActionOutcome::Deferred);
+                }
                 info!(Category::HandleUnregistered, "Action: Stopping torrent");
-                if is_any_not_meeting_criteria {
+                if is_any_not_eligible {
                     debug!(Category::HandleUnregistered, "  -> At least 1 other torrent depends this torrents files");
                 }
-                torrent_manager.stop_torrent(torrent.hash()).await.context("Failed to stop torrent")?;
+                let result = torrent_manager.stop_torrent(torrent.hash()).await.context("Failed to stop torrent");
+                action_utils.record(&[action_entry], &result)?;
+                result?;
             }
             ActionType::Delete => {
-                if is_any_not_meeting_criteria {
+                let action_entry = ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), rule.name()).with_delete_files(!is_any_not_eligible);
+                let action_entry = match is_any_not_eligible {
+                    true => action_entry.with_kept_for_cross_seeds(true),
+                    false => action_entry.with_bytes_reclaimed(*torrent.total_size()),
+                };
+                if action_utils.is_deferred(std::slice::from_ref(&action_entry))? {
+                    return Ok(ActionOutcome::Deferred);
+                }
+                let result = if is_any_not_eligible {
                     info!(Category::HandleUnregistered, "Action: Deleting torrent but keeping files (at least 1 other torrent depends on them)");
-                    torrent_manager.delete_torrent(torrent.hash(), false).await.context("Failed to delete torrent")?;
+                    torrent_manager.delete_torrent(torrent.hash(), false).await.context("Failed to delete torrent")
                 } else {
                     info!(Category::HandleUnregistered, "Action: Deleting torrent + files");
-                    torrent_manager.delete_torrent(torrent.hash(), true).await.context("Failed to delete torrent")?;
-                }
+                    torrent_manager.delete_torrent(torrent.hash(), true).await.context("Failed to delete torrent")
+                };
+                action_utils.record(&[action_entry], &result)?;
+                result?;
+                return Ok(ActionOutcome::Deleted);
             }
         }
-        Ok(())
+        Ok(ActionOutcome::Taken)
+    }
+
+    /**
+     * Take action on a cross-seed group where every member is eligible
+     * group_rule is the member rule with the least destructive action
+     */
+    pub async fn take_group_action(
+        torrent_manager: Arc<TorrentManager>,
+        action_utils: &mut ActionUtils,
+        group_torrents: &[Torrent],
+        group_rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<ActionOutcome, anyhow::Error> {
+        let action_type = ActionType::from_str(group_rule.action())?;
+        if action_type != ActionType::Test && hit_and_run_utils.is_any_at_risk(group_torrents, tracker_domain_utils, Category::HandleUnregistered) {
+            info!(Category::HandleUnregistered, "Action: Skipped cross-seed group of {} torrents, hit-and-run requirement not met", group_torrents.len());
+            let action_entries: Vec<ActionEntry> = group_torrents
+                .iter()
+                .map(|torrent| ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), group_rule.name()))
+                .collect();
+            action_utils.record_skipped(&action_entries, "hit-and-run requirement not met")?;
+            return Ok(ActionOutcome::Skipped);
+        }
+        match action_type {
+            ActionType::Test => info!(Category::HandleUnregistered, "Action: Test (cross-seed group of {} torrents)", group_torrents.len()),
+            ActionType::Recheck => {}
+            ActionType::Stop => info!(Category::HandleUnregistered, "Action: Stopping cross-seed group of {} torrents", group_torrents.len()),
+            ActionType::Delete => info!(Category::HandleUnregistered, "Action: Deleting cross-seed group of {} torrents + files", group_torrents.len()),
+        }
+        action_utils.take_group_action(&torrent_manager, &action_type, group_torrents, group_rule.name(), tracker_domain_utils).await
     }
 }
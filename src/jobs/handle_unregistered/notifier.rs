@@ -1,13 +1,21 @@
-use chrono::{Local, TimeZone};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
 
 use crate::{
-    config::Config,
+    jobs::{
+        models::{rule::Rule, unregistered_matcher::UnregisteredMatcher},
+        utils::{hit_and_run_utils::HitAndRunUtils, tracker_domain_utils::TrackerDomainUtils, tracker_message_utils::TrackerMessageRecord},
+    },
     logger::enums::category::Category,
     torrent_clients::{
         enums::tracker_status::TrackerStatus,
         models::{torrent::Torrent, tracker::Tracker},
     },
-    utils::discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+    utils::{
+        date_utils::DateUtils,
+        discord_webhook_utils::{DiscordWebhookUtils, EmbedField},
+    },
     warn,
 };
 
@@ -17,7 +25,15 @@ impl Notifier {
     /**
      * Send notification
      */
-    pub async fn send_notification(discord_webhook_utils: &mut DiscordWebhookUtils, torrent: &Torrent, trackers: &Vec<Tracker>, config: &Config) -> Result<(), anyhow::Error> {
+    pub async fn send_notification(
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        torrent: &Torrent,
+        trackers: &Vec<Tracker>,
+        unregistered_matcher: &UnregisteredMatcher,
+        rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<(), anyhow::Error> {
         if !discord_webhook_utils.is_notifications_enabled() {
             return Ok(());
         }
@@ -27,14 +43,14 @@ impl Notifier {
 
         let seeding_days = format!("{:.2}", (*torrent.seeding_time() / 60 / 60) as f32 / 24.0);
 
-        let added_on_str = match Local.timestamp_opt(*torrent.added_on(), 0).single() {
-            Some(datetime_local) => datetime_local.format("%Y-%m-%d %H:%M:%S").to_string(),
+        let added_on_str = match DateUtils::convert_timestamp_to_local_string(*torrent.added_on()) {
+            Some(local_datetime_str) => local_datetime_str,
             None => String::from("Failed getting datetime"),
         };
         let completed_on_str = match *torrent.completion_on() {
             -1 => String::from("Not completed"),
-            _ => match Local.timestamp_opt(*torrent.completion_on(), 0).single() {
-                Some(datetime_local) => datetime_local.format("%Y-%m-%d %H:%M:%S").to_string(),
+            _ => match DateUtils::convert_timestamp_to_local_string(*torrent.completion_on()) {
+                Some(local_datetime_str) => local_datetime_str,
                 None => String::from("Failed getting datetime"),
             },
         };
@@ -50,14 +66,25 @@ impl Notifier {
             };
             fields.push(EmbedField {
                 name: String::from("Tracker"),
-                value: format!("URL: {}\nStatus: {}\nMessage: {}", tracker.url(), tracker_status_str, tracker.msg()),
+                value: format!(
+                    "URL: {}\nStatus: {}\nMessage: {}\nMatched rule: {}",
+                    tracker.url(),
+                    tracker_status_str,
+                    tracker.msg(),
+                    unregistered_matcher.get_matched_rule(tracker).unwrap_or(String::from("None"))
+                ),
                 inline: false,
             });
         }
         fields.extend(vec![
             EmbedField {
                 name: String::from("Action"),
-                value: config.jobs().handle_unregistered().action().to_string(),
+                value: rule.action().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Rule"),
+                value: rule.name().to_string(),
                 inline: true,
             },
             EmbedField {
@@ -97,6 +124,187 @@ impl Notifier {
             },
         ]);
 
+        if let Some(risk_str) = hit_and_run_utils.get_risk_str(torrent, tracker_domain_utils) {
+            fields.push(EmbedField {
+                name: String::from("Hit and run risk"),
+                value: format!("{} (stop/delete is skipped)", risk_str),
+                inline: false,
+            });
+        }
+
         discord_webhook_utils.send_webhook_embed(torrent.name(), "Found unregistered torrent", fields).await
     }
+
+    /**
+     * Send 1 notification for a cross-seed group where every member is eligible
+     */
+    pub async fn send_group_notification(
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        group_torrents: &[Torrent],
+        torrent_trackers: &HashMap<String, Vec<Tracker>>,
+        unregistered_matcher: &UnregisteredMatcher,
+        group_rule: &Rule,
+        hit_and_run_utils: &HitAndRunUtils,
+        tracker_domain_utils: &TrackerDomainUtils,
+    ) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        // Members share their data, so the biggest member is the size of the group
+        let max_size = group_torrents.iter().map(|torrent| *torrent.total_size()).max().unwrap_or(0);
+        let total_size_gib = format!("{:.2}", (max_size / 1024 / 1024) as f32 / 1024.0);
+        let total_size_gb = format!("{:.2}", (max_size / 1000 / 1000) as f32 / 1000.0);
+
+        let mut fields = vec![
+            EmbedField {
+                name: String::from("Action"),
+                value: group_rule.action().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Rule"),
+                value: group_rule.name().to_string(),
+                inline: true,
+            },
+            EmbedField {
+                name: String::from("Total Size"),
+                value: format!("{total_size_gib}GiB | {total_size_gb}GB"),
+                inline: true,
+            },
+        ];
+        // Discord allows 25 fields per embed
+        for (i, torrent) in group_torrents.iter().enumerate() {
+            if i >= 20 {
+                fields.push(EmbedField {
+                    name: String::from("..."),
+                    value: format!("and {} more", group_torrents.len() - i),
+                    inline: false,
+                });
+                break;
+            }
+            let tracker_msgs: Vec<String> = match torrent_trackers.get(torrent.hash()) {
+                Some(trackers) => trackers
+                    .iter()
+                    .map(|tracker| match unregistered_matcher.get_matched_rule(tracker) {
+                        Some(matched_rule) => format!("{} (matched {})", tracker.msg(), matched_rule),
+                        None => tracker.msg().to_string(),
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            fields.push(EmbedField {
+                name: torrent.name().chars().take(256).collect(),
+                value: format!(
+                    "Tracker: {}\nMessage: {}\nCategory: {}\nRatio: {:.2} | Seeding days: {:.2}",
+                    torrent.tracker(),
+                    tracker_msgs.join(" | "),
+                    torrent.category(),
+                    torrent.ratio(),
+                    (*torrent.seeding_time() / 60 / 60) as f32 / 24.0,
+                ) + Notifier::get_risk_line(torrent, hit_and_run_utils, tracker_domain_utils).as_str(),
+                inline: false,
+            });
+        }
+
+        discord_webhook_utils
+            .send_webhook_embed(format!("Cross-seed group ({} torrents)", group_torrents.len()).as_str(), "Found unregistered cross-seed group", fields)
+            .await
+    }
+
+    /**
+     * Extra line for a cross-seed group member that is at risk of a hit-and-run
+     */
+    fn get_risk_line(torrent: &Torrent, hit_and_run_utils: &HitAndRunUtils, tracker_domain_utils: &TrackerDomainUtils) -> String {
+        match hit_and_run_utils.get_risk_str(torrent, tracker_domain_utils) {
+            Some(risk_str) => format!("\nHit and run risk: {}", risk_str),
+            None => String::new(),
+        }
+    }
+
+    /**
+     * Send 1 notification listing new messages of non working trackers that match no unregistered rule
+     */
+    pub async fn send_unknown_messages_notification(discord_webhook_utils: &mut DiscordWebhookUtils, tracker_message_records: &[TrackerMessageRecord]) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let mut fields: Vec<EmbedField> = Vec::new();
+        // Discord allows 25 fields per embed
+        for (i, tracker_message_record) in tracker_message_records.iter().enumerate() {
+            if i >= 20 {
+                fields.push(EmbedField {
+                    name: String::from("..."),
+                    value: format!("and {} more", tracker_message_records.len() - i),
+                    inline: false,
+                });
+                break;
+            }
+            let value = format!(
+                "Message: {}\nSeen: {} times from {} to {}\nExamples: {}",
+                tracker_message_record.msg(),
+                tracker_message_record.count(),
+                DateUtils::convert_utc_datetime_to_local_string(tracker_message_record.first_seen()),
+                DateUtils::convert_utc_datetime_to_local_string(tracker_message_record.last_seen()),
+                tracker_message_record.examples().join(" | ")
+            );
+            fields.push(EmbedField {
+                name: tracker_message_record.domain().chars().take(256).collect(),
+                value: value.chars().take(1024).collect(),
+                inline: false,
+            });
+        }
+
+        discord_webhook_utils
+            .send_webhook_embed(
+                "Unknown tracker messages",
+                "Found messages of non working trackers that match no unregistered rule, add them to unregistered_messages if they mean unregistered",
+                fields,
+            )
+            .await
+    }
+
+    /**
+     * Send notification for a tracker that looks like it is down
+     */
+    pub async fn send_outage_notification(discord_webhook_utils: &mut DiscordWebhookUtils, domain: &str, reason: &str, affected_count: usize) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let fields: Vec<EmbedField> = vec![
+            EmbedField {
+                name: String::from("Reason"),
+                value: reason.chars().take(1024).collect(),
+                inline: false,
+            },
+            EmbedField {
+                name: String::from("Paused torrents"),
+                value: affected_count.to_string(),
+                inline: false,
+            },
+        ];
+
+        discord_webhook_utils
+            .send_webhook_embed(domain, "Tracker looks like it is down, strikes of its torrents are paused until it is back", fields)
+            .await
+    }
+
+    /**
+     * Send notification for a tracker that is back after looking down
+     */
+    pub async fn send_outage_end_notification(discord_webhook_utils: &mut DiscordWebhookUtils, domain: &str, started_at: &DateTime<Utc>) -> Result<(), anyhow::Error> {
+        if !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let fields: Vec<EmbedField> = vec![EmbedField {
+            name: String::from("Down since"),
+            value: DateUtils::convert_utc_datetime_to_local_string(started_at),
+            inline: false,
+        }];
+
+        discord_webhook_utils.send_webhook_embed(domain, "Tracker is back, strikes of its torrents continue", fields).await
+    }
 }
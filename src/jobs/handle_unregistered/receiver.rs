@@ -1,13 +1,20 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use anyhow::Context;
 
 use crate::{
-    config::Config,
+    config::{Config, StateFilter},
     debug,
+    jobs::{
+        models::{rule::Rule, unregistered_matcher::UnregisteredMatcher},
+        utils::state_filter_utils::StateFilterUtils,
+    },
     logger::enums::category::Category,
     torrent_clients::{
-        enums::torrent_state::TorrentState,
+        enums::tracker_status::TrackerStatus,
         models::{torrent::Torrent, tracker::Tracker},
         torrent_manager::TorrentManager,
     },
@@ -42,16 +49,139 @@ impl Receiver {
         Ok(torrent_trackers)
     }
 
+    /**
+     * Get tracker domains that look like they are down in this run
+     * Trackers whose message matches an unregistered rule answered, so they are not counted at all
+     * Returns: HashMap<String, String> | HashMap<tracker_domain, reason>
+     */
+    pub fn get_outage_domains(torrent_trackers: &HashMap<String, Vec<Tracker>>, unregistered_matcher: &UnregisteredMatcher, config: &Config) -> HashMap<String, String> {
+        let outage_detection = config.jobs().handle_unregistered().outage_detection();
+        let mut outage_domains: HashMap<String, String> = HashMap::new();
+        if !*outage_detection.enabled() {
+            return outage_domains;
+        }
+
+        // Count contacted and not working trackers per domain
+        let mut domain_counts: HashMap<String, (i64, i64)> = HashMap::new();
+        for trackers in torrent_trackers.values() {
+            for tracker in trackers {
+                let domain = tracker.domain();
+                if domain.is_empty() {
+                    continue;
+                }
+                let is_not_working = *tracker.status() == TrackerStatus::NotWorking.to_i8();
+                if (!is_not_working && !tracker.is_working()) || (is_not_working && unregistered_matcher.get_matched_rule(tracker).is_some()) {
+                    continue;
+                }
+                let (contacted_count, not_working_count) = domain_counts.entry(domain.clone()).or_insert((0, 0));
+                *contacted_count += 1;
+                if !is_not_working {
+                    continue;
+                }
+                *not_working_count += 1;
+
+                // Connection errors are never torrent specific
+                let msg = tracker.msg().to_lowercase();
+                if !outage_domains.contains_key(&domain)
+                    && let Some(error_pattern) = outage_detection
+                        .error_patterns()
+                        .iter()
+                        .find(|error_pattern| !error_pattern.is_empty() && msg.contains(error_pattern.to_lowercase().as_str()))
+                {
+                    outage_domains.insert(domain, format!("Message looks like a connection error ({}): {}", error_pattern, tracker.msg()));
+                }
+            }
+        }
+
+        for (domain, (contacted_count, not_working_count)) in domain_counts {
+            if outage_domains.contains_key(&domain) || contacted_count < outage_detection.min_torrents() {
+                continue;
+            }
+            let failure_percent = not_working_count as f64 / contacted_count as f64 * 100.0;
+            if failure_percent > outage_detection.max_failure_percent() {
+                outage_domains.insert(
+                    domain,
+                    format!(
+                        "{}/{} torrents ({:.2}%) are not working, limit is {:.2}%",
+                        not_working_count,
+                        contacted_count,
+                        failure_percent,
+                        outage_detection.max_failure_percent()
+                    ),
+                );
+            }
+        }
+
+        outage_domains
+    }
+
+    /**
+     * Get messages of non working trackers that match no unregistered rule
+     * Returns: HashMap<(String, String), Vec<String>> | HashMap<(tracker_domain, msg), Vec<torrent_name>>
+     */
+    pub fn get_unknown_messages(
+        torrents: &Vec<Torrent>,
+        torrent_trackers: &HashMap<String, Vec<Tracker>>,
+        outage_domains: &HashMap<String, String>,
+        unregistered_matcher: &UnregisteredMatcher,
+    ) -> HashMap<(String, String), Vec<String>> {
+        let mut unknown_messages: HashMap<(String, String), Vec<String>> = HashMap::new();
+        for torrent in torrents {
+            let Some(trackers) = torrent_trackers.get(torrent.hash()) else {
+                continue;
+            };
+            for tracker in trackers {
+                let domain = tracker.domain();
+                let msg = tracker.msg().trim();
+                // DHT, PeX and LSD have no domain, messages of trackers that are down are just connection errors
+                if *tracker.status() != TrackerStatus::NotWorking.to_i8() || domain.is_empty() || msg.is_empty() || outage_domains.contains_key(&domain) {
+                    continue;
+                }
+                if unregistered_matcher.get_matched_rule(tracker).is_none() {
+                    unknown_messages.entry((domain, msg.to_string())).or_default().push(torrent.name().to_string());
+                }
+            }
+        }
+        unknown_messages
+    }
+
+    /**
+     * Get torrents whose trackers haven't settled yet after reannouncing, none is working and at least one is still updating or not contacted
+     * Their fresh trackers say nothing about being unregistered, so their strikes are kept until the status is final
+     */
+    pub fn get_pending_hashes(torrent_trackers: &HashMap<String, Vec<Tracker>>) -> HashSet<String> {
+        torrent_trackers
+            .iter()
+            .filter(|(_, trackers)| {
+                !trackers.iter().any(|tracker| tracker.is_working())
+                    && trackers
+                        .iter()
+                        .any(|tracker| *tracker.status() == TrackerStatus::Updating.to_i8() || *tracker.status() == TrackerStatus::NotContacted.to_i8())
+            })
+            .map(|(hash, _)| hash.clone())
+            .collect()
+    }
+
     /**
      * Get torrents and if they match criteria
      * Returns: HashMap<String, (Torrent, bool)> | HashMap<torrent_hash, (Torrent, is_criteria_met))>
      */
-    pub async fn get_torrents_criteria(torrents: &Vec<Torrent>, torrent_trackers: &HashMap<String, Vec<Tracker>>, config: &Config) -> Result<HashMap<String, (Torrent, bool)>, anyhow::Error> {
+    pub async fn get_torrents_criteria(
+        torrents: &Vec<Torrent>,
+        torrent_trackers: &HashMap<String, Vec<Tracker>>,
+        torrent_rules: &HashMap<String, Rule>,
+        unregistered_matcher: &UnregisteredMatcher,
+        state_filter: &StateFilter,
+    ) -> Result<HashMap<String, (Torrent, bool)>, anyhow::Error> {
         // Check torrents for criteria
         let mut torrents_criteria: HashMap<String, (Torrent, bool)> = HashMap::new();
         for torrent in torrents {
+            let Some(rule) = torrent_rules.get(torrent.hash()) else {
+                warn!(Category::HandleUnregistered, "Didn't find rule for torrent: ({}) {}", torrent.hash(), torrent.name());
+                continue;
+            };
             if let Some(trackers) = torrent_trackers.get(torrent.hash()) {
-                let is_criteria_met = Receiver::is_criteria_met(torrent, trackers, config).await.context("Failed to get criteria")?;
+                let is_criteria_met = Receiver::is_criteria_met(torrent, trackers, rule, unregistered_matcher, state_filter).await.context("Failed to get criteria")?;
                 torrents_criteria.insert(torrent.hash().to_string(), (torrent.clone(), is_criteria_met));
             } else {
                 warn!(Category::HandleUnregistered, "Cannot get tracker for torrent: ({}) {}", torrent.hash(), torrent.name());
@@ -64,39 +194,67 @@ impl Receiver {
     /**
      * Is criteria met
      */
-    async fn is_criteria_met(torrent: &Torrent, trackers: &Vec<Tracker>, config: &Config) -> Result<bool, anyhow::Error> {
+    pub async fn is_criteria_met(torrent: &Torrent, trackers: &Vec<Tracker>, rule: &Rule, unregistered_matcher: &UnregisteredMatcher, state_filter: &StateFilter) -> Result<bool, anyhow::Error> {
         // Uncompleted
         if *torrent.completion_on() == -1 {
             trace!(Category::HandleUnregistered, "Torrent doesn't meet criteria (uncompleted): ({}) {}", torrent.hash(), torrent.name(),);
             return Ok(false);
         }
         // Protection tag
-        if torrent.tags().contains(config.jobs().handle_unregistered().protection_tag()) {
-            trace!(Category::HandleUnregistered, "Torrent doesn't meet criteria (protection tag): ({}) {}", torrent.hash(), torrent.name(),);
+        if rule.is_protected(torrent.tags()) {
+            trace!(Category::HandleUnregistered, "Torrent doesn't meet criteria (protected by rule {}): ({}) {}", rule.name(), torrent.hash(), torrent.name(),);
+            return Ok(false);
+        }
+        // Seed time, only set by overrides since unregistered torrents can't be seeded anyway
+        let seeding_days = torrent.seeding_time() / 60 / 60 / 24;
+        let min_seeding_days = rule.min_seeding_days() as i64;
+        if seeding_days < min_seeding_days {
+            trace!(
+                Category::HandleUnregistered,
+                "Torrent doesn't meet criteria (minimum seed day limit {}/{}): ({}) {}",
+                seeding_days,
+                min_seeding_days,
+                torrent.hash(),
+                torrent.name(),
+            );
             return Ok(false);
         }
         // Stopped torrent
-        if [
-            TorrentState::PausedUP.to_string(),
-            TorrentState::PausedDL.to_string(),
-            TorrentState::StoppedUP.to_string(),
-            TorrentState::StoppedDL.to_string(),
-        ]
-        .contains(&torrent.state().to_string())
-        {
+        if torrent.state().is_stopped() {
             trace!(Category::HandleUnregistered, "Torrent doesn't meet criteria (stopped): ({}) {}", torrent.hash(), torrent.name(),);
             return Ok(false);
         }
+        // State filter
+        if !StateFilterUtils::is_allowed(torrent.state(), state_filter) {
+            trace!(
+                Category::HandleUnregistered,
+                "Torrent doesn't meet criteria (state {} is filtered): ({}) {}",
+                torrent.state(),
+                torrent.hash(),
+                torrent.name()
+            );
+            return Ok(false);
+        }
         // Working trackers
         for tracker in trackers {
-            if !tracker.is_unregistered() {
-                trace!(
+            match unregistered_matcher.get_matched_rule(tracker) {
+                Some(matched_rule) => trace!(
                     Category::HandleUnregistered,
-                    "Torrent doesn't meet criteria (at least 1 tracker is not unregistered): ({}) {}",
+                    "Tracker {} matched unregistered rule {}: ({}) {}",
+                    tracker.url(),
+                    matched_rule,
                     torrent.hash(),
-                    torrent.name(),
-                );
-                return Ok(false);
+                    torrent.name()
+                ),
+                None => {
+                    trace!(
+                        Category::HandleUnregistered,
+                        "Torrent doesn't meet criteria (at least 1 tracker is not unregistered): ({}) {}",
+                        torrent.hash(),
+                        torrent.name(),
+                    );
+                    return Ok(false);
+                }
             }
         }
         // All good
@@ -104,3 +262,100 @@ impl Receiver {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const URL: &str = "https://tracker.example.org/announce";
+
+    fn get_config(outage_detection: serde_json::Value) -> Config {
+        Config::from_test_fields(json!({ "jobs": { "handle_unregistered": { "outage_detection": outage_detection } } }))
+    }
+
+    fn get_outage_domains(torrent_trackers: &HashMap<String, Vec<Tracker>>, config: &Config) -> HashMap<String, String> {
+        let unregistered_matcher = UnregisteredMatcher::new(config.jobs().handle_unregistered().unregistered_messages()).unwrap();
+        Receiver::get_outage_domains(torrent_trackers, &unregistered_matcher, config)
+    }
+
+    /**
+     * One torrent per tracker status
+     */
+    fn get_torrent_trackers(statuses: &[(i8, &str)]) -> HashMap<String, Vec<Tracker>> {
+        statuses
+            .iter()
+            .enumerate()
+            .map(|(i, (status, msg))| (i.to_string(), vec![serde_json::from_value(json!({ "url": URL, "status": status, "msg": msg })).unwrap()]))
+            .collect()
+    }
+
+    #[test]
+    fn share_above_max_failure_percent_is_an_outage() {
+        let config = get_config(json!({ "max_failure_percent": 50.0, "min_torrents": 4, "error_patterns": [] }));
+        let torrent_trackers = get_torrent_trackers(&[(4, "bad gateway"), (4, "bad gateway"), (4, "bad gateway"), (2, "")]);
+        let outage_domains = get_outage_domains(&torrent_trackers, &config);
+        assert_eq!(outage_domains.get("tracker.example.org").map(|reason| reason.as_str()), Some("3/4 torrents (75.00%) are not working, limit is 50.00%"));
+    }
+
+    #[test]
+    fn share_at_max_failure_percent_is_no_outage() {
+        let config = get_config(json!({ "max_failure_percent": 50.0, "min_torrents": 4, "error_patterns": [] }));
+        let torrent_trackers = get_torrent_trackers(&[(4, "bad gateway"), (4, "bad gateway"), (2, ""), (2, "")]);
+        assert!(get_outage_domains(&torrent_trackers, &config).is_empty());
+    }
+
+    #[test]
+    fn share_is_only_checked_from_min_torrents() {
+        let config = get_config(json!({ "max_failure_percent": 50.0, "min_torrents": 4, "error_patterns": [] }));
+        let torrent_trackers = get_torrent_trackers(&[(4, "bad gateway"), (4, "bad gateway"), (4, "bad gateway")]);
+        assert!(get_outage_domains(&torrent_trackers, &config).is_empty());
+    }
+
+    #[test]
+    fn trackers_that_were_not_contacted_are_not_counted() {
+        let config = get_config(json!({ "max_failure_percent": 50.0, "min_torrents": 4, "error_patterns": [] }));
+        let torrent_trackers = get_torrent_trackers(&[(4, "bad gateway"), (4, "bad gateway"), (4, "bad gateway"), (1, ""), (3, "")]);
+        assert!(get_outage_domains(&torrent_trackers, &config).is_empty());
+    }
+
+    #[test]
+    fn unregistered_messages_are_no_outage() {
+        let config = get_config(json!({ "max_failure_percent": 50.0, "min_torrents": 4, "error_patterns": [] }));
+        let torrent_trackers = get_torrent_trackers(&[(4, "unregistered torrent"), (4, "torrent not registered with this tracker"), (4, "bad gateway"), (2, ""), (2, "")]);
+        assert!(get_outage_domains(&torrent_trackers, &config).is_empty());
+    }
+
+    #[test]
+    fn connection_error_is_an_outage_below_min_torrents() {
+        let config = get_config(json!({ "min_torrents": 4, "error_patterns": ["timed out"] }));
+        let torrent_trackers = get_torrent_trackers(&[(4, "Connection Timed Out"), (2, "")]);
+        let outage_domains = get_outage_domains(&torrent_trackers, &config);
+        assert_eq!(
+            outage_domains.get("tracker.example.org").map(|reason| reason.as_str()),
+            Some("Message looks like a connection error (timed out): Connection Timed Out")
+        );
+    }
+
+    #[test]
+    fn updating_and_not_contacted_trackers_are_pending() {
+        let torrent_trackers = get_torrent_trackers(&[(3, ""), (1, ""), (4, "unregistered"), (2, "")]);
+        let pending_hashes = Receiver::get_pending_hashes(&torrent_trackers);
+        assert_eq!(pending_hashes, HashSet::from([String::from("0"), String::from("1")]));
+    }
+
+    #[test]
+    fn working_tracker_is_never_pending() {
+        let tracker = |status: i8, msg: &str| -> Tracker { serde_json::from_value(json!({ "url": URL, "status": status, "msg": msg })).unwrap() };
+        let torrent_trackers = HashMap::from([(String::from("a"), vec![tracker(3, ""), tracker(2, "")]), (String::from("b"), vec![tracker(3, ""), tracker(4, "unregistered")])]);
+        assert_eq!(Receiver::get_pending_hashes(&torrent_trackers), HashSet::from([String::from("b")]));
+    }
+
+    #[test]
+    fn disabled_outage_detection_finds_nothing() {
+        let config = get_config(json!({ "enabled": false, "min_torrents": 1, "error_patterns": ["timed out"] }));
+        let torrent_trackers = get_torrent_trackers(&[(4, "timed out")]);
+        assert!(get_outage_domains(&torrent_trackers, &config).is_empty());
+    }
+}
@@ -1,38 +1,50 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Context;
 use reqwest::Url;
+use tokio::time::sleep;
 
 use crate::{
     config::Config,
     debug, info,
     jobs::{
-        enums::strike_type::StrikeType,
+        deferred_actions::notifier::DeferralNotifier,
+        enums::{action_outcome::ActionOutcome, strike_type::StrikeType},
         handle_unregistered::{action_taker::ActionTaker, notifier::Notifier, receiver::Receiver, striker::Striker},
-        utils::strike_utils::StrikeUtils,
+        models::{job_run_stats::JobRunStats, pipeline_context::PipelineContext, quiet_windows::QuietWindows, rule::Rule, unregistered_matcher::UnregisteredMatcher},
+        utils::{
+            action_utils::ActionUtils, cross_seed_utils::CrossSeedGroups, hit_and_run_utils::HitAndRunUtils, rule_utils::RuleUtils, strike_utils::StrikeUtils, tracker_domain_utils::TrackerDomainUtils,
+            tracker_message_utils::TrackerMessageUtils, tracker_outage_utils::TrackerOutageUtils,
+        },
     },
     logger::enums::category::Category,
     torrent_clients::{
         models::{torrent::Torrent, tracker::Tracker},
         torrent_manager::TorrentManager,
     },
-    utils::discord_webhook_utils::DiscordWebhookUtils,
+    utils::{date_utils::DateUtils, db_manager::DbManager, discord_webhook_utils::DiscordWebhookUtils},
+    warn,
 };
 
 pub struct HandleUnregistered {
     torrent_manager: Arc<TorrentManager>,
+    db_manager: DbManager,
     config: Config,
 }
 
 impl HandleUnregistered {
-    pub fn new(torrent_manager: Arc<TorrentManager>, config: Config) -> Self {
-        Self { torrent_manager, config }
+    pub fn new(torrent_manager: Arc<TorrentManager>, db_manager: DbManager, config: Config) -> Self {
+        Self { torrent_manager, db_manager, config }
     }
 
     /**
      * Run
      */
-    pub async fn run(&self) -> Result<(), anyhow::Error> {
+    pub async fn run(&self, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
         let discord_webhook_url: Option<Url> = match self.config.notification().discord_webhook_url().len() > 1 {
             true => Some(Url::parse(self.config.notification().discord_webhook_url()).context("Failed to parse discord_webhook_url")?),
             false => None,
@@ -44,79 +56,334 @@ impl HandleUnregistered {
 
         // Get torrents from torrent client
         debug!(Category::HandleUnregistered, "Getting torrents...");
-        let torrents = self.torrent_manager.get_all_torrents().await.context("Failed to get all torrents")?;
+        let torrents = pipeline_context.get_torrents(&self.torrent_manager).await?;
         debug!(Category::HandleUnregistered, "Received {} torrents", torrents.len());
 
+        // Compile unregistered message rules
+        let unregistered_matcher = UnregisteredMatcher::new(self.config.jobs().handle_unregistered().unregistered_messages()).context("Failed to compile unregistered messages")?;
+
         // Get torrent trackers
         debug!(Category::HandleUnregistered, "Getting torrent trackers...");
-        let torrent_trackers: HashMap<String, Vec<Tracker>> = Receiver::get_torrent_trackers(self.torrent_manager.clone(), &torrents, &self.config).await?;
+        let mut torrent_trackers: HashMap<String, Vec<Tracker>> = Receiver::get_torrent_trackers(self.torrent_manager.clone(), &torrents, &self.config).await?;
         debug!(Category::HandleUnregistered, "Received torrent trackers");
 
+        // The trackers of every torrent are known already, so they resolve tracker overrides and the hit-and-run policies
+        let hit_and_run_utils = HitAndRunUtils::new(&self.config);
+        let mut tracker_domain_utils = TrackerDomainUtils::default();
+        for (hash, trackers) in &torrent_trackers {
+            tracker_domain_utils.add_tracker_domains(hash, trackers);
+        }
+
+        // Resolve rules
+        let base_rule = Rule::from_handle_unregistered(self.config.jobs().handle_unregistered());
+        let torrent_rules: HashMap<String, Rule> = RuleUtils::get_torrent_rules(&torrents, &base_rule, self.config.jobs().handle_unregistered().overrides(), &tracker_domain_utils, Category::HandleUnregistered);
+
+        // Detect tracker outages, strikes of their torrents are paused while they are down (not reset)
+        let outage_domains: HashMap<String, String> = Receiver::get_outage_domains(&torrent_trackers, &unregistered_matcher, &self.config);
+        let mut tracker_outage_utils = TrackerOutageUtils::new(self.db_manager.clone(), Category::HandleUnregistered);
+        let (started_domains, ended_outages) = tracker_outage_utils.update(&outage_domains).context("Failed to update tracker outages")?;
+        let mut paused_hashes: HashSet<String> = HashSet::new();
+        for (domain, reason) in &outage_domains {
+            let affected_hashes: Vec<String> = torrents
+                .iter()
+                .filter(|torrent| torrent_trackers.get(torrent.hash()).is_some_and(|trackers| trackers.iter().any(|tracker| tracker.domain() == *domain)))
+                .map(|torrent| torrent.hash().to_string())
+                .collect();
+
+            // Log
+            warn!(
+                Category::HandleUnregistered,
+                "Tracker {} looks like it is down, pausing strikes of {} torrents: {}",
+                domain,
+                affected_hashes.len(),
+                reason
+            );
+
+            // Notification
+            if *self.config.notification().on_job_action() && started_domains.contains(domain) {
+                Notifier::send_outage_notification(&mut discord_webhook_utils, domain, reason, affected_hashes.len())
+                    .await
+                    .context("Failed to send outage notification")?;
+            }
+
+            paused_hashes.extend(affected_hashes);
+        }
+        for ended_outage in &ended_outages {
+            // Log
+            info!(
+                Category::HandleUnregistered,
+                "Tracker {} is back, it looked down since {}",
+                ended_outage.domain(),
+                DateUtils::convert_utc_datetime_to_local_string(ended_outage.started_at())
+            );
+
+            // Notification
+            if *self.config.notification().on_job_action() {
+                Notifier::send_outage_end_notification(&mut discord_webhook_utils, ended_outage.domain(), ended_outage.started_at())
+                    .await
+                    .context("Failed to send outage end notification")?;
+            }
+        }
+
+        // Collect unknown tracker messages
+        debug!(Category::HandleUnregistered, "Recording unknown tracker messages...");
+        self.handle_unknown_messages(&mut discord_webhook_utils, &torrents, &torrent_trackers, &outage_domains, &unregistered_matcher).await?;
+        debug!(Category::HandleUnregistered, "Done recording unknown tracker messages");
+
         // Get torrents from torrent client with criteria
         debug!(Category::HandleUnregistered, "Checking torrents for criteria...");
-        let torrents_criteria: HashMap<String, (Torrent, bool)> = Receiver::get_torrents_criteria(&torrents, &torrent_trackers, &self.config).await?;
+        let mut torrents_criteria: HashMap<String, (Torrent, bool)> =
+            Receiver::get_torrents_criteria(&torrents, &torrent_trackers, &torrent_rules, &unregistered_matcher, self.config.jobs().handle_unregistered().state_filter()).await?;
         debug!(Category::HandleUnregistered, "Done checking torrents for criteria");
 
-        info!(
-            Category::HandleUnregistered,
-            "{} torrents meet criteria",
-            torrents_criteria.values().filter(|(_, is_criteria_met)| *is_criteria_met).count(),
-        );
+        // Reannounce torrents that meet criteria since tracker messages can be stale for hours
+        let candidate_torrents: Vec<Torrent> = torrents_criteria
+            .values()
+            .filter(|(torrent, is_criteria_met)| *is_criteria_met && !paused_hashes.contains(torrent.hash()))
+            .map(|(torrent, _)| torrent.clone())
+            .collect();
+        let reannounce = self.config.jobs().handle_unregistered().reannounce();
+        if *reannounce.enabled() && !candidate_torrents.is_empty() {
+            debug!(Category::HandleUnregistered, "Reannouncing {} torrents and waiting {} seconds...", candidate_torrents.len(), reannounce.delay_secs());
+            let candidate_hashes: Vec<String> = candidate_torrents.iter().map(|torrent| torrent.hash().to_string()).collect();
+            self.torrent_manager.reannounce(&candidate_hashes).await.context("Failed to reannounce torrents")?;
+            sleep(Duration::from_secs(reannounce.delay_secs())).await;
+
+            // Only torrents that are still unregistered with fresh trackers keep meeting criteria
+            let fresh_torrent_trackers: HashMap<String, Vec<Tracker>> = Receiver::get_torrent_trackers(self.torrent_manager.clone(), &candidate_torrents, &self.config).await?;
+            // Trackers that are still updating keep the strikes they had before, as if the reannounce didn't happen
+            let pending_hashes = Receiver::get_pending_hashes(&fresh_torrent_trackers);
+            if !pending_hashes.is_empty() {
+                debug!(Category::HandleUnregistered, "Pausing strikes of {} torrents whose trackers are still updating", pending_hashes.len());
+            }
+            let fresh_torrent_trackers: HashMap<String, Vec<Tracker>> = fresh_torrent_trackers.into_iter().filter(|(hash, _)| !pending_hashes.contains(hash)).collect();
+            let settled_torrents: Vec<Torrent> = candidate_torrents.iter().filter(|torrent| !pending_hashes.contains(torrent.hash())).cloned().collect();
+            paused_hashes.extend(pending_hashes);
+            let fresh_torrents_criteria: HashMap<String, (Torrent, bool)> =
+                Receiver::get_torrents_criteria(&settled_torrents, &fresh_torrent_trackers, &torrent_rules, &unregistered_matcher, self.config.jobs().handle_unregistered().state_filter()).await?;
+            info!(
+                Category::HandleUnregistered,
+                "{} of {} torrents are still unregistered after reannouncing",
+                fresh_torrents_criteria.values().filter(|(_, is_criteria_met)| *is_criteria_met).count(),
+                settled_torrents.len()
+            );
+            for (hash, trackers) in &fresh_torrent_trackers {
+                tracker_domain_utils.add_tracker_domains(hash, trackers);
+            }
+            torrent_trackers.extend(fresh_torrent_trackers);
+            torrents_criteria.extend(fresh_torrents_criteria);
+        }
+
+        let criteria_met_count = torrents_criteria.values().filter(|(_, is_criteria_met)| *is_criteria_met).count();
+        info!(Category::HandleUnregistered, "{} torrents meet criteria", criteria_met_count);
 
         // Striking
         debug!(Category::HandleUnregistered, "Striking torrents...");
-        let mut strike_utils = StrikeUtils::new()?;
-        let limit_reached_torrents = Striker::strike_torrents(&mut strike_utils, &torrents_criteria, &self.config)?;
+        let mut strike_utils = StrikeUtils::new(self.db_manager.clone(), self.config.jobs().handle_unregistered().strike_policy().clone()).with_job_schedule(pipeline_context.job_schedule());
+        let strikable_torrents_criteria: HashMap<String, (Torrent, bool)> = torrents_criteria
+            .iter()
+            .filter(|(hash, _)| !paused_hashes.contains(*hash))
+            .map(|(hash, torrent_criteria)| (hash.clone(), torrent_criteria.clone()))
+            .collect();
+        let limit_reached_torrents = Striker::strike_torrents(&mut strike_utils, &strikable_torrents_criteria, &torrent_rules)?;
         debug!(Category::HandleUnregistered, "Done striking torrents");
+        let job_run_stats = JobRunStats::new(torrents.len(), criteria_met_count, limit_reached_torrents.len());
 
         info!(Category::HandleUnregistered, "{} torrents that meet criteria have reached their strike limits", limit_reached_torrents.len(),);
 
-        // Go through torrents
-        for torrent in &limit_reached_torrents {
-            // Log
-            info!(Category::HandleUnregistered, "Torrent unregistered: {}", torrent.name());
+        // Group cross-seeds, files are only walked if there is a torrent to act on
+        let cross_seed_groups = match limit_reached_torrents.is_empty() {
+            true => CrossSeedGroups::default(),
+            false => {
+                debug!(Category::HandleUnregistered, "Grouping cross-seeded torrents...");
+                let cross_seed_groups = pipeline_context.get_cross_seed_groups(&torrents)?;
+                debug!(Category::HandleUnregistered, "Done grouping cross-seeded torrents");
+                cross_seed_groups
+            }
+        };
 
-            // Notification
-            if *self.config.notification().on_job_action() {
-                let trackers = match torrent_trackers.get(torrent.hash()) {
-                    Some(trackers) => trackers,
-                    None => &Vec::new(),
+        let quiet_windows = pipeline_context.get_quiet_windows(QuietWindows::from_config(self.config.quiet_hours(), self.config.jobs().handle_unregistered().quiet_hours()).context("Failed to get quiet hours")?);
+        let mut action_utils = ActionUtils::new(self.db_manager.clone(), Category::HandleUnregistered, quiet_windows);
+        let limit_reached_hashes: Vec<String> = limit_reached_torrents.iter().map(|torrent| torrent.hash().to_string()).collect();
+        let eligible_hashes: HashSet<String> = limit_reached_hashes.iter().cloned().collect();
+        // Torrents skipped for a hit-and-run requirement keep their strikes
+        let mut acted_hashes: HashSet<String> = HashSet::new();
+
+        // Go through cross-seed groups
+        for cross_seed_group in cross_seed_groups.get_groups_of_hashes(&limit_reached_hashes) {
+            // Every member is eligible, handle them and their data together
+            if cross_seed_group.is_cross_seeded() && cross_seed_group.is_all_eligible(&eligible_hashes) {
+                let group_torrents: Vec<Torrent> = cross_seed_group.hashes().iter().filter_map(|hash| torrents_criteria.get(hash)).map(|(torrent, _)| torrent.clone()).collect();
+                let group_rule = RuleUtils::get_group_rule(cross_seed_group.hashes(), &torrent_rules)?;
+
+                // Log
+                info!(Category::HandleUnregistered, "Cross-seed group unregistered ({} torrents, rule {}):", group_torrents.len(), group_rule.name());
+                for torrent in &group_torrents {
+                    info!(Category::HandleUnregistered, "  -> {}", torrent.name());
+                }
+
+                // Notification
+                if *self.config.notification().on_job_action() {
+                    Notifier::send_group_notification(
+                        &mut discord_webhook_utils,
+                        &group_torrents,
+                        &torrent_trackers,
+                        &unregistered_matcher,
+                        &group_rule,
+                        &hit_and_run_utils,
+                        &tracker_domain_utils,
+                    )
+                    .await
+                    .context("Failed to send group notification")?;
+                }
+
+                // Take action
+                let action_outcome = ActionTaker::take_group_action(self.torrent_manager.clone(), &mut action_utils, &group_torrents, &group_rule, &hit_and_run_utils, &tracker_domain_utils).await?;
+                if action_outcome != ActionOutcome::Skipped {
+                    acted_hashes.extend(cross_seed_group.hashes().iter().cloned());
+                }
+                continue;
+            }
+
+            // Go through eligible torrents of the group
+            for torrent in limit_reached_torrents.iter().filter(|torrent| cross_seed_group.hashes().iter().any(|hash| hash == torrent.hash())) {
+                let Some(rule) = torrent_rules.get(torrent.hash()) else {
+                    warn!(Category::HandleUnregistered, "Didn't find rule for torrent: ({}) {}", torrent.hash(), torrent.name());
+                    continue;
                 };
-                Notifier::send_notification(&mut discord_webhook_utils, torrent, trackers, &self.config).await.context("Failed to send notification")?;
+
+                // Log
+                info!(Category::HandleUnregistered, "Torrent unregistered (rule {}): {}", rule.name(), torrent.name());
+
+                // Notification
+                if *self.config.notification().on_job_action() {
+                    let trackers = match torrent_trackers.get(torrent.hash()) {
+                        Some(trackers) => trackers,
+                        None => &Vec::new(),
+                    };
+                    Notifier::send_notification(&mut discord_webhook_utils, torrent, trackers, &unregistered_matcher, rule, &hit_and_run_utils, &tracker_domain_utils)
+                        .await
+                        .context("Failed to send notification")?;
+                }
+
+                // Take action
+                let action_outcome = ActionTaker::take_action(
+                    self.torrent_manager.clone(),
+                    &mut action_utils,
+                    !cross_seed_group.is_all_eligible(&eligible_hashes),
+                    torrent,
+                    rule,
+                    &hit_and_run_utils,
+                    &tracker_domain_utils,
+                )
+                .await?;
+                if action_outcome != ActionOutcome::Skipped {
+                    acted_hashes.insert(torrent.hash().to_string());
+                }
             }
+        }
 
-            // Take action
-            ActionTaker::take_action(self.torrent_manager.clone(), &torrents_criteria, torrent, &self.config).await?;
+        // Deferred actions
+        if let Some((deferred_count, deferral_end)) = action_utils.get_deferred() {
+            info!(
+                Category::HandleUnregistered,
+                "{} actions deferred until {} (quiet hours)",
+                deferred_count,
+                DateUtils::convert_utc_datetime_to_local_string(&deferral_end)
+            );
+            if *self.config.notification().on_job_action() {
+                DeferralNotifier::send_deferral_notification(&mut discord_webhook_utils, &Category::HandleUnregistered.to_string(), deferred_count, &deferral_end)
+                    .await
+                    .context("Failed to send deferral notification")?;
+            }
         }
 
         // Clean db
         debug!(Category::HandleUnregistered, "Cleaning db...");
-        self.clean_db(&mut strike_utils, &torrents_criteria, &limit_reached_torrents)?;
+        self.clean_db(&mut strike_utils, &torrents_criteria, &paused_hashes, &acted_hashes)?;
         debug!(Category::HandleUnregistered, "Cleaned db");
 
         // Logout
         self.torrent_manager.logout().await.context("Failed to logout of torrent client")?;
 
+        Ok(job_run_stats)
+    }
+
+    /**
+     * Record messages of non working trackers that match no unregistered rule and report new ones
+     */
+    async fn handle_unknown_messages(
+        &self,
+        discord_webhook_utils: &mut DiscordWebhookUtils,
+        torrents: &Vec<Torrent>,
+        torrent_trackers: &HashMap<String, Vec<Tracker>>,
+        outage_domains: &HashMap<String, String>,
+        unregistered_matcher: &UnregisteredMatcher,
+    ) -> Result<(), anyhow::Error> {
+        let unknown_messages = Receiver::get_unknown_messages(torrents, torrent_trackers, outage_domains, unregistered_matcher);
+
+        let mut tracker_message_utils = TrackerMessageUtils::new(self.db_manager.clone(), Category::HandleUnregistered);
+        tracker_message_utils.record(&unknown_messages).context("Failed to record unknown tracker messages")?;
+        let deleted_count = tracker_message_utils.delete_classified(unregistered_matcher).context("Failed to delete classified tracker messages")?;
+        let stale_count = tracker_message_utils.delete_stale().context("Failed to delete stale tracker messages")?;
+        debug!(
+            Category::HandleUnregistered,
+            "Recorded {} unknown tracker messages, deleted {} that match a rule by now and {} that weren't seen for a while",
+            unknown_messages.len(),
+            deleted_count,
+            stale_count
+        );
+
+        // Messages stay unreported until a notification about them was actually sent
+        if !*self.config.jobs().handle_unregistered().unregistered_messages().report_unknown() || !*self.config.notification().on_job_action() || !discord_webhook_utils.is_notifications_enabled() {
+            return Ok(());
+        }
+
+        let tracker_message_records = tracker_message_utils.get_tracker_messages(true).context("Failed to get unreported tracker messages")?;
+        if tracker_message_records.is_empty() {
+            return Ok(());
+        }
+
+        // Log
+        info!(Category::HandleUnregistered, "Found {} new unknown tracker messages:", tracker_message_records.len());
+        for tracker_message_record in &tracker_message_records {
+            info!(Category::HandleUnregistered, "  -> {}: {}", tracker_message_record.domain(), tracker_message_record.msg());
+        }
+
+        // Notification
+        Notifier::send_unknown_messages_notification(discord_webhook_utils, &tracker_message_records)
+            .await
+            .context("Failed to send unknown messages notification")?;
+
+        let ids: Vec<i64> = tracker_message_records.iter().map(|tracker_message_record| *tracker_message_record.id()).collect();
+        tracker_message_utils.mark_reported(&ids).context("Failed to mark tracker messages as reported")?;
+
         Ok(())
     }
 
     /**
      * Clean db
      */
-    fn clean_db(&self, strike_utils: &mut StrikeUtils, torrents_criteria: &HashMap<String, (Torrent, bool)>, limit_reached_torrents: &[Torrent]) -> Result<(), anyhow::Error> {
+    fn clean_db(&self, strike_utils: &mut StrikeUtils, torrents_criteria: &HashMap<String, (Torrent, bool)>, paused_hashes: &HashSet<String>, acted_hashes: &HashSet<String>) -> Result<(), anyhow::Error> {
         let mut hashes_to_remove: Vec<String> = Vec::new();
+        let mut hashes_to_reset: Vec<String> = Vec::new();
+        let mut hashes_to_pause: Vec<String> = Vec::new();
 
         // Torrents that reached limit and were handled
-        let limit_reached_torrent_hashes: Vec<String> = limit_reached_torrents.iter().map(|torrent| torrent.hash().to_string()).collect();
-        hashes_to_remove.extend(limit_reached_torrent_hashes);
+        hashes_to_remove.extend(acted_hashes.iter().cloned());
 
         let strike_records = strike_utils.get_strikes(&StrikeType::HandleUnregistered, None).context("Failed to get all strikes for HandleUnregistered")?;
         for strike_record in strike_records {
+            // Keep strikes of torrents whose tracker is down, as if the outage didn't happen
+            if paused_hashes.contains(strike_record.hash()) {
+                hashes_to_pause.push(strike_record.hash().to_string());
+                continue;
+            }
             match torrents_criteria.get(strike_record.hash()) {
                 // Check for stuff that doesn't meet criteria
                 Some((_, is_criteria_met)) => {
                     if !*is_criteria_met {
-                        hashes_to_remove.push(strike_record.hash().to_string());
+                        hashes_to_reset.push(strike_record.hash().to_string());
                     }
                 }
                 // Check for stuff that doesn't exist in torrents anymore
@@ -126,9 +393,17 @@ impl HandleUnregistered {
             }
         }
 
-        debug!(Category::HandleUnregistered, "Deleting {} hashes", hashes_to_remove.len());
+        debug!(
+            Category::HandleUnregistered,
+            "Deleting {} hashes, resetting {} hashes, pausing {} hashes",
+            hashes_to_remove.len(),
+            hashes_to_reset.len(),
+            hashes_to_pause.len()
+        );
 
-        strike_utils.delete(StrikeType::HandleUnregistered, hashes_to_remove).context("Failed to delete hashes")?;
+        strike_utils.delete(StrikeType::HandleUnregistered, &hashes_to_remove).context("Failed to delete hashes")?;
+        strike_utils.reset(StrikeType::HandleUnregistered, &hashes_to_reset).context("Failed to reset hashes")?;
+        strike_utils.pause(StrikeType::HandleUnregistered, &hashes_to_pause).context("Failed to pause hashes")?;
 
         Ok(())
     }
@@ -6,7 +6,11 @@ use reqwest::Url;
 use crate::{
     config::Config,
     debug, info,
-    jobs::enums::action_type::ActionType,
+    jobs::{
+        enums::action_type::ActionType,
+        models::{job_run_stats::JobRunStats, pipeline_context::PipelineContext},
+        utils::state_filter_utils::StateFilterUtils,
+    },
     logger::enums::category::Category,
     torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
     utils::discord_webhook_utils::DiscordWebhookUtils,
@@ -23,7 +27,7 @@ impl HealthCheckFiles {
         HealthCheckFiles { torrent_manager, config }
     }
 
-    pub async fn run(&self) -> Result<(), anyhow::Error> {
+    pub async fn run(&self, pipeline_context: &mut PipelineContext) -> Result<JobRunStats, anyhow::Error> {
         let discord_webhook_url: Option<Url> = match self.config.notification().discord_webhook_url().len() > 1 {
             true => Some(Url::parse(self.config.notification().discord_webhook_url()).context("Failed to parse discord_webhook_url")?),
             false => None,
@@ -35,12 +39,13 @@ impl HealthCheckFiles {
 
         // Get torrents from torrent client
         debug!(Category::HealthCheckFiles, "Getting torrents...");
-        let torrents = self.torrent_manager.get_all_torrents().await.context("Failed to get all torrents")?;
+        let torrents = pipeline_context.get_torrents(&self.torrent_manager).await?;
         debug!(Category::HealthCheckFiles, "Received {} torrents", torrents.len());
 
         debug!(Category::HealthCheckFiles, "Running file check...");
         let file_issues = self.check_files(self.torrent_manager.clone(), &torrents).await.context("Error while checking files for health check")?;
         debug!(Category::HealthCheckFiles, "File check reported {} issues", file_issues.len());
+        let job_run_stats = JobRunStats::new(torrents.len(), file_issues.len(), 0);
 
         // Handle file issues
         let action_type = ActionType::from_str(self.config.jobs().health_check_files().action())?;
@@ -53,6 +58,7 @@ impl HealthCheckFiles {
 
             match action_type {
                 ActionType::Test => info!(Category::HealthCheckFiles, "Action: test"),
+                ActionType::Recheck => warn!(Category::HealthCheckFiles, "Recheck action not supported on health_check_files"),
                 ActionType::Stop => warn!(Category::HealthCheckFiles, "Stop action not supported on health_check_files"),
                 ActionType::Delete => warn!(Category::HealthCheckFiles, "Delete action not supported on health_check_files"),
             }
@@ -61,7 +67,7 @@ impl HealthCheckFiles {
         // Logout
         self.torrent_manager.logout().await.context("Failed to logout to torrent client")?;
 
-        Ok(())
+        Ok(job_run_stats)
     }
 
     pub async fn check_files(&self, torrent_manager: Arc<TorrentManager>, torrents: &Vec<Torrent>) -> Result<Vec<String>, anyhow::Error> {
@@ -72,6 +78,10 @@ impl HealthCheckFiles {
                 debug!(Category::HealthCheckFiles, "Torrent not completed: ({}) {}", torrent.hash(), torrent.name());
                 continue;
             }
+            if !StateFilterUtils::is_allowed(torrent.state(), self.config.jobs().health_check_files().state_filter()) {
+                debug!(Category::HealthCheckFiles, "Torrent state {} is filtered: ({}) {}", torrent.state(), torrent.hash(), torrent.name());
+                continue;
+            }
             let torrent_files = torrent_manager.get_torrent_files(torrent.hash()).await.context("Getting torrent file failed")?;
             for torrent_file in torrent_files {
                 let path_str = format!("{}/{}", torrent.save_path(), torrent_file.name());
@@ -1,6 +1,10 @@
-mod enums;
+pub mod deferred_actions;
+pub mod enums;
+pub mod handle_errored;
 pub mod handle_orphaned;
+pub mod handle_tracker_errors;
 pub mod handle_unlinked;
 pub mod handle_unregistered;
 pub mod health_check_files;
-mod utils;
+pub mod models;
+pub mod utils;
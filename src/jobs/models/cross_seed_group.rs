@@ -0,0 +1,30 @@
+use std::collections::HashSet;
+
+#[derive(Clone)]
+pub struct CrossSeedGroup {
+    hashes: Vec<String>,
+}
+
+impl CrossSeedGroup {
+    pub fn new(hashes: Vec<String>) -> Self {
+        Self { hashes }
+    }
+
+    /**
+     * Hashes of all torrents that share at least 1 file (inode or content path) with another member
+     */
+    pub fn hashes(&self) -> &Vec<String> {
+        &self.hashes
+    }
+
+    pub fn is_cross_seeded(&self) -> bool {
+        self.hashes.len() > 1
+    }
+
+    /**
+     * True if every member of the group is in eligible_hashes
+     */
+    pub fn is_all_eligible(&self, eligible_hashes: &HashSet<String>) -> bool {
+        self.hashes.iter().all(|hash| eligible_hashes.contains(hash))
+    }
+}
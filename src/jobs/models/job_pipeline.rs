@@ -0,0 +1,124 @@
+use crate::{config::Pipeline, jobs::enums::failure_policy::FailurePolicy};
+
+/**
+ * Jobs that run after each other under one job lock, a job that is not part of a pipeline runs as a pipeline of itself
+ */
+#[derive(Clone)]
+pub struct JobPipeline {
+    name: String,
+    job_names: Vec<String>,
+    failure_policy: FailurePolicy,
+}
+
+impl JobPipeline {
+    pub fn from_job_name(job_name: &str) -> Self {
+        Self {
+            name: job_name.to_string(),
+            job_names: vec![job_name.to_string()],
+            failure_policy: FailurePolicy::Abort,
+        }
+    }
+
+    /**
+     * known_job_names are the jobs that can be part of a pipeline, the pipeline name can't be one of them since both are recorded in the job history
+     */
+    pub fn from_config(pipeline: &Pipeline, known_job_names: &[&str]) -> Result<Self, anyhow::Error> {
+        if pipeline.name().is_empty() {
+            anyhow::bail!("Pipeline name can't be empty");
+        }
+        if known_job_names.contains(&pipeline.name()) {
+            anyhow::bail!("Pipeline name '{}' is already the name of a job", pipeline.name());
+        }
+        if pipeline.jobs().is_empty() {
+            anyhow::bail!("Pipeline '{}' has no jobs", pipeline.name());
+        }
+        for job_name in pipeline.jobs() {
+            if !known_job_names.contains(&job_name.as_str()) {
+                anyhow::bail!("Unknown job '{}' in pipeline '{}'", job_name, pipeline.name());
+            }
+        }
+
+        Ok(Self {
+            name: pipeline.name().to_string(),
+            job_names: pipeline.jobs().clone(),
+            failure_policy: FailurePolicy::from_str(pipeline.on_failure())?,
+        })
+    }
+
+    /**
+     * True if this is a single job and not a configured pipeline
+     */
+    pub fn is_single_job(&self) -> bool {
+        self.job_names.len() == 1 && self.job_names[0] == self.name
+    }
+
+    /* Getter */
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn job_names(&self) -> &Vec<String> {
+        &self.job_names
+    }
+    pub fn failure_policy(&self) -> &FailurePolicy {
+        &self.failure_policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const KNOWN_JOB_NAMES: [&str; 3] = ["handle_unlinked", "handle_unregistered", "handle_orphaned"];
+
+    fn get_job_pipeline(pipeline: serde_json::Value) -> Result<JobPipeline, anyhow::Error> {
+        JobPipeline::from_config(&serde_json::from_value(pipeline).unwrap(), &KNOWN_JOB_NAMES)
+    }
+
+    #[test]
+    fn valid_pipeline() {
+        let job_pipeline = get_job_pipeline(json!({
+            "name": "cleanup",
+            "jobs": ["handle_unregistered", "handle_unlinked"],
+            "on_failure": "Continue"
+        }))
+        .unwrap();
+        assert_eq!(job_pipeline.name(), "cleanup");
+        assert_eq!(job_pipeline.job_names(), &vec!["handle_unregistered".to_string(), "handle_unlinked".to_string()]);
+        assert!(*job_pipeline.failure_policy() == FailurePolicy::Continue);
+        assert!(!job_pipeline.is_single_job());
+    }
+
+    #[test]
+    fn single_job_pipeline() {
+        let job_pipeline = JobPipeline::from_job_name("handle_orphaned");
+        assert!(job_pipeline.is_single_job());
+        assert!(*job_pipeline.failure_policy() == FailurePolicy::Abort);
+    }
+
+    #[test]
+    fn empty_name_fails() {
+        assert!(get_job_pipeline(json!({ "name": "", "jobs": ["handle_unlinked"] })).is_err());
+    }
+
+    #[test]
+    fn name_of_a_job_fails() {
+        assert!(get_job_pipeline(json!({ "name": "handle_unlinked", "jobs": ["handle_orphaned"] })).is_err());
+    }
+
+    #[test]
+    fn no_jobs_fails() {
+        assert!(get_job_pipeline(json!({ "name": "cleanup", "jobs": [] })).is_err());
+    }
+
+    #[test]
+    fn unknown_job_fails() {
+        assert!(get_job_pipeline(json!({ "name": "cleanup", "jobs": ["handle_unlinked", "handle_everything"] })).is_err());
+    }
+
+    #[test]
+    fn unknown_on_failure_fails() {
+        assert!(get_job_pipeline(json!({ "name": "cleanup", "jobs": ["handle_unlinked"], "on_failure": "retry" })).is_err());
+    }
+}
@@ -0,0 +1,28 @@
+/**
+ * Counts of a job run that are stored in its history, actions and freed bytes are taken from the recorded actions
+ * strikes counts the torrents or paths that reached their strike limit, jobs without strikes record 0
+ * handle_orphaned counts paths of torrents as scanned and orphaned paths as candidates
+ */
+#[derive(Default)]
+pub struct JobRunStats {
+    torrents_scanned: usize,
+    candidates: usize,
+    strikes: usize,
+}
+
+impl JobRunStats {
+    pub fn new(torrents_scanned: usize, candidates: usize, strikes: usize) -> Self {
+        Self { torrents_scanned, candidates, strikes }
+    }
+
+    /* Getter */
+    pub fn torrents_scanned(&self) -> usize {
+        self.torrents_scanned
+    }
+    pub fn candidates(&self) -> usize {
+        self.candidates
+    }
+    pub fn strikes(&self) -> usize {
+        self.strikes
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use croner::Cron;
+
+use crate::{
+    config::Schedule,
+    jobs::{
+        enums::{quiet_hours_mode::QuietHoursMode, schedule_type::ScheduleType},
+        models::quiet_windows::QuietWindows,
+    },
+    utils::date_utils::DateUtils,
+};
+
+#[derive(Clone)]
+pub struct JobSchedule {
+    schedule_type: ScheduleType,
+    jitter_minutes: u32,
+    run_on_startup: bool,
+    quiet_windows: QuietWindows,
+}
+
+impl JobSchedule {
+    /**
+     * A cron expression replaces interval_hours, returns None if the job is disabled (no cron and interval_hours -1)
+     * interval_hours 0 runs on startup and then uses the default interval
+     * Runs that fall into quiet_windows with skip_run are postponed until they end
+     */
+    pub fn from_config(interval_hours: i32, default_interval_hours: i32, schedule: &Schedule, quiet_windows: QuietWindows) -> Result<Option<Self>, anyhow::Error> {
+        let (schedule_type, run_on_startup) = if !schedule.cron().is_empty() {
+            let cron = Cron::new(schedule.cron()).parse().context(format!("Failed to parse cron expression '{}'", schedule.cron()))?;
+            (ScheduleType::Cron(Box::new(cron)), schedule.run_on_startup())
+        } else {
+            match interval_hours {
+                -1 => return Ok(None),
+                0 => (ScheduleType::Interval { hours: default_interval_hours as i64 }, true),
+                hours if hours > 0 => (ScheduleType::Interval { hours: hours as i64 }, schedule.run_on_startup()),
+                _ => anyhow::bail!("interval_hours has to be -1, 0 or positive, got {}", interval_hours),
+            }
+        };
+
+        Ok(Some(Self {
+            schedule_type,
+            jitter_minutes: schedule.jitter_minutes(),
+            run_on_startup,
+            quiet_windows,
+        }))
+    }
+
+    /**
+     * Latest time the next run after the given time starts, with the full jitter and postponed by quiet hours that skip runs
+     */
+    pub fn get_latest_next_run(&self, after: &DateTime<Utc>) -> Result<DateTime<Utc>, anyhow::Error> {
+        let next_run = match &self.schedule_type {
+            ScheduleType::Interval { hours } => *after + Duration::hours(*hours),
+            ScheduleType::Cron(cron) => DateUtils::get_next_cron_datetime(cron, after).context(format!("No next run found for cron {}", cron.as_str()))?,
+        };
+        let latest_next_run = next_run + Duration::minutes(self.jitter_minutes as i64);
+        Ok(self.get_skip_run_end(&latest_next_run).unwrap_or(latest_next_run))
+    }
+
+    /**
+     * End of the quiet hours that skip a run at the given time, None if none is active
+     * Windows that follow each other postpone the run until the last one ends
+     */
+    pub fn get_skip_run_end(&self, datetime: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut skip_run_end_option: Option<DateTime<Utc>> = None;
+        // Windows that last 24h can follow each other, at most for a week
+        for _ in 0..7 {
+            let Some(quiet_end) = self.quiet_windows.get_end(&QuietHoursMode::SkipRun, &skip_run_end_option.unwrap_or(*datetime)) else {
+                break;
+            };
+            skip_run_end_option = Some(quiet_end);
+        }
+        skip_run_end_option
+    }
+
+    /* Getter */
+    pub fn schedule_type(&self) -> &ScheduleType {
+        &self.schedule_type
+    }
+    pub fn jitter_minutes(&self) -> u32 {
+        self.jitter_minutes
+    }
+    pub fn run_on_startup(&self) -> bool {
+        self.run_on_startup
+    }
+    pub fn quiet_windows(&self) -> &QuietWindows {
+        &self.quiet_windows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::Europe::Berlin;
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::QuietHours;
+
+    fn get_job_schedule(interval_hours: i32, schedule: serde_json::Value) -> Result<Option<JobSchedule>, anyhow::Error> {
+        JobSchedule::from_config(interval_hours, 13, &serde_json::from_value(schedule).unwrap(), QuietWindows::default())
+    }
+
+    #[test]
+    fn interval_of_minus_1_disables_the_job() {
+        assert!(get_job_schedule(-1, json!({})).unwrap().is_none());
+    }
+
+    #[test]
+    fn interval_of_0_runs_on_startup_with_the_default_interval() {
+        let job_schedule = get_job_schedule(0, json!({ "run_on_startup": false })).unwrap().unwrap();
+        assert_eq!(job_schedule.schedule_type().to_string(), "interval of 13h");
+        assert!(job_schedule.run_on_startup());
+    }
+
+    #[test]
+    fn positive_interval_keeps_run_on_startup() {
+        let job_schedule = get_job_schedule(5, json!({ "jitter_minutes": 10 })).unwrap().unwrap();
+        assert_eq!(job_schedule.schedule_type().to_string(), "interval of 5h");
+        assert!(!job_schedule.run_on_startup());
+        assert_eq!(job_schedule.jitter_minutes(), 10);
+    }
+
+    #[test]
+    fn negative_interval_below_minus_1_fails() {
+        assert!(get_job_schedule(-2, json!({})).is_err());
+    }
+
+    #[test]
+    fn cron_replaces_the_interval() {
+        let job_schedule = get_job_schedule(-1, json!({ "cron": "30 4 * * *", "run_on_startup": true })).unwrap().unwrap();
+        assert_eq!(job_schedule.schedule_type().to_string(), "cron 30 4 * * *");
+        assert!(job_schedule.run_on_startup());
+    }
+
+    #[test]
+    fn latest_next_run_adds_jitter_and_skipped_quiet_hours() {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        let quiet_hours: Vec<QuietHours> = serde_json::from_value(json!([{ "start": "22:00", "end": "06:00", "mode": "skip_run" }])).unwrap();
+        let quiet_windows = QuietWindows::from_config(&[], &quiet_hours).unwrap();
+        let job_schedule = JobSchedule::from_config(5, 13, &serde_json::from_value(json!({ "jitter_minutes": 30 })).unwrap(), quiet_windows).unwrap().unwrap();
+        let after = Berlin.with_ymd_and_hms(2026, 10, 12, 12, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(job_schedule.get_latest_next_run(&after).unwrap(), Berlin.with_ymd_and_hms(2026, 10, 12, 17, 30, 0).unwrap().with_timezone(&Utc));
+        let after = Berlin.with_ymd_and_hms(2026, 10, 12, 18, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(job_schedule.get_latest_next_run(&after).unwrap(), Berlin.with_ymd_and_hms(2026, 10, 13, 6, 0, 0).unwrap().with_timezone(&Utc));
+    }
+
+    #[test]
+    fn cron_keeps_the_local_time_across_dst_changes() {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        let job_schedule = get_job_schedule(-1, json!({ "cron": "30 4 * * *" })).unwrap().unwrap();
+        // Clocks go forward on 2026-03-29, so 04:30 is 02:30 UTC instead of 03:30 UTC
+        let after = Berlin.with_ymd_and_hms(2026, 3, 28, 12, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(job_schedule.get_latest_next_run(&after).unwrap(), Utc.with_ymd_and_hms(2026, 3, 29, 2, 30, 0).unwrap());
+        // Clocks go back on 2026-10-25, so 04:30 is 03:30 UTC again
+        let after = Berlin.with_ymd_and_hms(2026, 10, 24, 12, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(job_schedule.get_latest_next_run(&after).unwrap(), Utc.with_ymd_and_hms(2026, 10, 25, 3, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn invalid_cron_fails() {
+        assert!(get_job_schedule(5, json!({ "cron": "every day" })).is_err());
+    }
+}
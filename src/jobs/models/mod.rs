@@ -0,0 +1,9 @@
+pub mod cross_seed_group;
+pub mod job_pipeline;
+pub mod job_run_stats;
+pub mod job_schedule;
+pub mod pipeline_context;
+pub mod quiet_windows;
+pub mod rule;
+pub mod torrent_links;
+pub mod unregistered_matcher;
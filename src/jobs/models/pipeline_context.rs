@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+
+use crate::{
+    jobs::{
+        models::{job_schedule::JobSchedule, quiet_windows::QuietWindows},
+        utils::{
+            cross_seed_utils::{CrossSeedGroups, CrossSeedUtils},
+            file_utils::FileUtils,
+        },
+    },
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+};
+
+/**
+ * Results a job leaves for the next jobs of its pipeline, a job that runs on its own gets an empty one
+ * Cleared by the pipeline after a job took actions, since the torrents and files might have changed
+ * The schedule the jobs run on is kept
+ */
+#[derive(Default)]
+pub struct PipelineContext {
+    job_schedule_option: Option<JobSchedule>,
+    torrents_option: Option<Vec<Torrent>>,
+    known_hardlinks_option: Option<HashMap<(u64, u64), u64>>,
+    cross_seed_groups_option: Option<CrossSeedGroups>,
+}
+
+impl PipelineContext {
+    pub fn new(job_schedule: JobSchedule) -> Self {
+        Self {
+            job_schedule_option: Some(job_schedule),
+            ..Default::default()
+        }
+    }
+
+    /**
+     * Schedule of the pipeline or of the job that runs on its own, None if the jobs don't run on a schedule
+     */
+    pub fn job_schedule(&self) -> Option<&JobSchedule> {
+        self.job_schedule_option.as_ref()
+    }
+
+    /**
+     * Quiet windows of the job together with the ones of the schedule it runs on, so windows of a pipeline that defer actions apply to its jobs
+     */
+    pub fn get_quiet_windows(&self, job_quiet_windows: QuietWindows) -> QuietWindows {
+        match &self.job_schedule_option {
+            Some(job_schedule) => job_quiet_windows.merge(job_schedule.quiet_windows()),
+            None => job_quiet_windows,
+        }
+    }
+
+    /**
+     * All torrents of the torrent client, only requested if no earlier job did
+     */
+    pub async fn get_torrents(&mut self, torrent_manager: &TorrentManager) -> Result<Vec<Torrent>, anyhow::Error> {
+        if let Some(torrents) = &self.torrents_option {
+            return Ok(torrents.clone());
+        }
+        let torrents = torrent_manager.get_all_torrents().await.context("Failed to get all torrents")?;
+        self.torrents_option = Some(torrents.clone());
+        Ok(torrents)
+    }
+
+    /**
+     * Known hardlinks of the torrent folder and link paths (see FileUtils::get_known_hardlinks), only walked if no earlier job did
+     */
+    pub fn get_known_hardlinks(&mut self, torrents_path: &str, link_paths: &[String]) -> Result<HashMap<(u64, u64), u64>, anyhow::Error> {
+        if let Some(known_hardlinks) = &self.known_hardlinks_option {
+            return Ok(known_hardlinks.clone());
+        }
+        let known_hardlinks = FileUtils::get_known_hardlinks(torrents_path, link_paths)?;
+        self.known_hardlinks_option = Some(known_hardlinks.clone());
+        Ok(known_hardlinks)
+    }
+
+    /**
+     * Cross-seed groups of all torrents (see get_torrents), only walked if no earlier job did
+     */
+    pub fn get_cross_seed_groups(&mut self, torrents: &[Torrent]) -> Result<CrossSeedGroups, anyhow::Error> {
+        if let Some(cross_seed_groups) = &self.cross_seed_groups_option {
+            return Ok(cross_seed_groups.clone());
+        }
+        let cross_seed_groups = CrossSeedUtils::get_cross_seed_groups(&torrents.iter().collect::<Vec<&Torrent>>()).context("Failed to get cross-seed groups")?;
+        self.cross_seed_groups_option = Some(cross_seed_groups.clone());
+        Ok(cross_seed_groups)
+    }
+
+    #[cfg(test)]
+    pub fn set_test_torrents(&mut self, torrents: Vec<Torrent>) {
+        self.torrents_option = Some(torrents);
+    }
+    #[cfg(test)]
+    pub fn has_torrents(&self) -> bool {
+        self.torrents_option.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.torrents_option = None;
+        self.known_hardlinks_option = None;
+        self.cross_seed_groups_option = None;
+    }
+}
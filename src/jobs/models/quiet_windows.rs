@@ -0,0 +1,215 @@
+use anyhow::Context;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+
+use crate::{config::QuietHours, jobs::enums::quiet_hours_mode::QuietHoursMode, utils::date_utils::DateUtils};
+
+#[derive(Clone, PartialEq)]
+struct QuietWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    days: Vec<Weekday>,
+    mode: QuietHoursMode,
+}
+
+impl QuietWindow {
+    fn from_config(quiet_hours: &QuietHours) -> Result<Self, anyhow::Error> {
+        let start = NaiveTime::parse_from_str(quiet_hours.start(), "%H:%M").context(format!("Failed to parse quiet hours start '{}'", quiet_hours.start()))?;
+        let end = NaiveTime::parse_from_str(quiet_hours.end(), "%H:%M").context(format!("Failed to parse quiet hours end '{}'", quiet_hours.end()))?;
+        let mut days: Vec<Weekday> = Vec::new();
+        for day in quiet_hours.days() {
+            days.push(day.parse::<Weekday>().map_err(|_| anyhow::anyhow!("Unknown quiet hours day '{}'", day))?);
+        }
+        Ok(Self {
+            start,
+            end,
+            days,
+            mode: QuietHoursMode::from_str(quiet_hours.mode())?,
+        })
+    }
+
+    /**
+     * End of the window if it is active at the local time, a window with the same start and end lasts 24h
+     */
+    fn get_active_end(&self, local_datetime: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let today = local_datetime.date();
+        let yesterday = today - Duration::days(1);
+        let time = local_datetime.time();
+        let is_day = |date: NaiveDate| self.days.is_empty() || self.days.contains(&date.weekday());
+
+        if self.start < self.end {
+            return (is_day(today) && time >= self.start && time < self.end).then(|| today.and_time(self.end));
+        }
+        // Window goes past midnight, it either started today or yesterday
+        if is_day(today) && time >= self.start {
+            return Some((today + Duration::days(1)).and_time(self.end));
+        }
+        if is_day(yesterday) && time < self.end {
+            return Some(today.and_time(self.end));
+        }
+        None
+    }
+}
+
+/**
+ * Global and job quiet hours together
+ */
+#[derive(Clone, Default)]
+pub struct QuietWindows {
+    windows: Vec<QuietWindow>,
+}
+
+impl QuietWindows {
+    pub fn from_config(global_quiet_hours: &[QuietHours], job_quiet_hours: &[QuietHours]) -> Result<Self, anyhow::Error> {
+        let mut windows: Vec<QuietWindow> = Vec::new();
+        for quiet_hours in global_quiet_hours.iter().chain(job_quiet_hours) {
+            windows.push(QuietWindow::from_config(quiet_hours)?);
+        }
+        Ok(Self { windows })
+    }
+
+    /**
+     * Add the windows of e.g. the pipeline a job runs in, windows that are already there (e.g. global ones) are skipped
+     */
+    pub fn merge(mut self, quiet_windows: &QuietWindows) -> Self {
+        for window in &quiet_windows.windows {
+            if !self.windows.contains(window) {
+                self.windows.push(window.clone());
+            }
+        }
+        self
+    }
+
+    /**
+     * Latest end of the active windows of the mode, None if none is active
+     */
+    pub fn get_end(&self, mode: &QuietHoursMode, datetime: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local_datetime = DateUtils::convert_utc_datetime_to_local_naive_datetime(datetime);
+        self.windows
+            .iter()
+            .filter(|window| window.mode == *mode)
+            .filter_map(|window| window.get_active_end(&local_datetime))
+            .max()
+            .map(|local_end| DateUtils::convert_local_naive_datetime_to_utc(&local_end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+
+    fn get_window(start: &str, end: &str, days: &[Weekday]) -> QuietWindow {
+        QuietWindow {
+            start: NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+            end: NaiveTime::parse_from_str(end, "%H:%M").unwrap(),
+            days: days.to_vec(),
+            mode: QuietHoursMode::DeferActions,
+        }
+    }
+
+    /**
+     * 2026-10-12 is a monday
+     */
+    fn get_local_datetime(day: u32, time: &str) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap().and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    fn get_end(window: QuietWindow, utc_datetime: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        QuietWindows { windows: vec![window] }.get_end(&QuietHoursMode::DeferActions, &utc_datetime)
+    }
+
+    #[test]
+    fn window_within_a_day() {
+        let window = get_window("09:00", "17:00", &[]);
+        assert_eq!(window.get_active_end(&get_local_datetime(12, "08:59")), None);
+        assert_eq!(window.get_active_end(&get_local_datetime(12, "09:00")), Some(get_local_datetime(12, "17:00")));
+        assert_eq!(window.get_active_end(&get_local_datetime(12, "17:00")), None);
+    }
+
+    #[test]
+    fn window_past_midnight_ends_the_next_day() {
+        let window = get_window("22:00", "06:00", &[]);
+        assert_eq!(window.get_active_end(&get_local_datetime(12, "23:00")), Some(get_local_datetime(13, "06:00")));
+        assert_eq!(window.get_active_end(&get_local_datetime(13, "03:00")), Some(get_local_datetime(13, "06:00")));
+        assert_eq!(window.get_active_end(&get_local_datetime(13, "06:00")), None);
+        assert_eq!(window.get_active_end(&get_local_datetime(13, "12:00")), None);
+    }
+
+    #[test]
+    fn window_with_same_start_and_end_lasts_a_day() {
+        let window = get_window("04:00", "04:00", &[]);
+        assert_eq!(window.get_active_end(&get_local_datetime(12, "04:00")), Some(get_local_datetime(13, "04:00")));
+        assert_eq!(window.get_active_end(&get_local_datetime(13, "03:59")), Some(get_local_datetime(13, "04:00")));
+    }
+
+    #[test]
+    fn window_only_on_its_days() {
+        let window = get_window("09:00", "17:00", &[Weekday::Mon]);
+        assert_eq!(window.get_active_end(&get_local_datetime(12, "10:00")), Some(get_local_datetime(12, "17:00")));
+        assert_eq!(window.get_active_end(&get_local_datetime(13, "10:00")), None);
+    }
+
+    #[test]
+    fn window_past_midnight_belongs_to_its_start_day() {
+        let window = get_window("22:00", "06:00", &[Weekday::Fri]);
+        // Starts on friday and lasts into saturday
+        assert_eq!(window.get_active_end(&get_local_datetime(16, "23:00")), Some(get_local_datetime(17, "06:00")));
+        assert_eq!(window.get_active_end(&get_local_datetime(17, "03:00")), Some(get_local_datetime(17, "06:00")));
+        // Doesn't start on saturday nor on thursday
+        assert_eq!(window.get_active_end(&get_local_datetime(17, "23:00")), None);
+        assert_eq!(window.get_active_end(&get_local_datetime(18, "03:00")), None);
+        assert_eq!(window.get_active_end(&get_local_datetime(16, "03:00")), None);
+    }
+
+    #[test]
+    fn get_end_takes_the_latest_active_window_of_the_mode() {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        let skip_run_window = QuietWindow {
+            mode: QuietHoursMode::SkipRun,
+            ..get_window("00:00", "12:00", &[])
+        };
+        let quiet_windows = QuietWindows {
+            windows: vec![get_window("01:00", "05:00", &[]), get_window("02:00", "04:00", &[]), skip_run_window],
+        };
+        // 03:00 in Berlin (CEST)
+        let utc_datetime = Utc.with_ymd_and_hms(2026, 10, 12, 1, 0, 0).unwrap();
+        assert_eq!(quiet_windows.get_end(&QuietHoursMode::DeferActions, &utc_datetime), Some(Utc.with_ymd_and_hms(2026, 10, 12, 3, 0, 0).unwrap()));
+        assert_eq!(quiet_windows.get_end(&QuietHoursMode::SkipRun, &utc_datetime), Some(Utc.with_ymd_and_hms(2026, 10, 12, 10, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn get_end_converts_across_dst_start() {
+        // 01:30 CET, the window ends at 04:00 CEST after the clocks went forward
+        let end = get_end(get_window("01:00", "04:00", &[]), Utc.with_ymd_and_hms(2026, 3, 29, 0, 30, 0).unwrap());
+        assert_eq!(end, Some(Utc.with_ymd_and_hms(2026, 3, 29, 2, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn get_end_converts_across_dst_end() {
+        // 02:30 CEST, the window ends at 04:00 CET after the clocks went back
+        let end = get_end(get_window("01:00", "04:00", &[]), Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap());
+        assert_eq!(end, Some(Utc.with_ymd_and_hms(2026, 10, 25, 3, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn get_end_takes_the_earlier_time_of_an_ambiguous_end() {
+        // 02:30 happens twice when the clocks go back, the window ends at the first one (CEST)
+        let end = get_end(get_window("01:00", "02:30", &[]), Utc.with_ymd_and_hms(2026, 10, 24, 23, 30, 0).unwrap());
+        assert_eq!(end, Some(Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap()));
+    }
+
+    #[test]
+    fn merge_skips_windows_that_are_already_there() {
+        let job_quiet_windows = QuietWindows {
+            windows: vec![get_window("22:00", "06:00", &[])],
+        };
+        let pipeline_quiet_windows = QuietWindows {
+            windows: vec![get_window("22:00", "06:00", &[]), get_window("12:00", "13:00", &[])],
+        };
+        let quiet_windows = job_quiet_windows.merge(&pipeline_quiet_windows);
+        assert!(quiet_windows.windows == pipeline_quiet_windows.windows);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::config::{HandleErrored, HandleOrphaned, HandleUnlinked, HandleUnregistered, RuleOverride};
+
+/**
+ * Job settings that apply to a single torrent or path after overrides have been resolved
+ */
+#[derive(Clone)]
+pub struct Rule {
+    name: String,
+    min_seeding_days: i32,
+    required_strikes: i32,
+    min_strike_days: i32,
+    action: String,
+    protection_tag: String,
+    protected: bool,
+}
+
+impl Rule {
+    pub fn from_handle_unlinked(handle_unlinked: &HandleUnlinked) -> Self {
+        Self {
+            name: String::from("default"),
+            min_seeding_days: handle_unlinked.min_seeding_days(),
+            required_strikes: handle_unlinked.required_strikes(),
+            min_strike_days: handle_unlinked.min_strike_days(),
+            action: handle_unlinked.action().to_string(),
+            protection_tag: handle_unlinked.protection_tag().to_string(),
+            protected: false,
+        }
+    }
+
+    pub fn from_handle_unregistered(handle_unregistered: &HandleUnregistered) -> Self {
+        Self {
+            name: String::from("default"),
+            min_seeding_days: 0,
+            required_strikes: handle_unregistered.required_strikes(),
+            min_strike_days: handle_unregistered.min_strike_days(),
+            action: handle_unregistered.action().to_string(),
+            protection_tag: handle_unregistered.protection_tag().to_string(),
+            protected: false,
+        }
+    }
+
+    pub fn from_handle_orphaned(handle_orphaned: &HandleOrphaned) -> Self {
+        Self {
+            name: String::from("default"),
+            min_seeding_days: 0,
+            required_strikes: handle_orphaned.required_strikes(),
+            min_strike_days: handle_orphaned.min_strike_days(),
+            action: handle_orphaned.action().to_string(),
+            protection_tag: String::new(),
+            protected: false,
+        }
+    }
+
+    pub fn from_handle_errored(handle_errored: &HandleErrored) -> Self {
+        Self {
+            name: String::from("default"),
+            min_seeding_days: 0,
+            required_strikes: handle_errored.required_strikes(),
+            min_strike_days: handle_errored.min_strike_days(),
+            action: handle_errored.action().to_string(),
+            protection_tag: handle_errored.protection_tag().to_string(),
+            protected: false,
+        }
+    }
+
+    /**
+     * Copy of this rule with every value that is set in the override replaced
+     */
+    pub fn with_override(&self, rule_override: &RuleOverride) -> Self {
+        Self {
+            name: rule_override.name().to_string(),
+            min_seeding_days: rule_override.min_seeding_days().unwrap_or(self.min_seeding_days),
+            required_strikes: rule_override.required_strikes().unwrap_or(self.required_strikes),
+            min_strike_days: rule_override.min_strike_days().unwrap_or(self.min_strike_days),
+            action: rule_override.action().unwrap_or(&self.action).to_string(),
+            protection_tag: rule_override.protection_tag().unwrap_or(&self.protection_tag).to_string(),
+            protected: rule_override.protected().unwrap_or(self.protected),
+        }
+    }
+
+    /**
+     * Protected by the rule itself or by having the protection tag (tags are comma separated, an empty protection tag never matches)
+     */
+    pub fn is_protected(&self, tags: &str) -> bool {
+        self.protected || (!self.protection_tag.is_empty() && tags.split(',').any(|tag| tag.trim() == self.protection_tag))
+    }
+
+    /* Getter */
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn min_seeding_days(&self) -> i32 {
+        self.min_seeding_days
+    }
+    pub fn required_strikes(&self) -> i32 {
+        self.required_strikes
+    }
+    pub fn min_strike_days(&self) -> i32 {
+        self.min_strike_days
+    }
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+    pub fn protected(&self) -> bool {
+        self.protected
+    }
+}
@@ -0,0 +1,135 @@
+#[derive(Clone)]
+pub struct FileLinks {
+    path: String,
+    size: u64,
+    is_linked: bool,
+    library_paths: Vec<String>,
+}
+
+impl FileLinks {
+    pub fn new(path: String, size: u64, is_linked: bool, library_paths: Vec<String>) -> Self {
+        Self { path, size, is_linked, library_paths }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /**
+     * Size in bytes
+     */
+    pub fn size(&self) -> &u64 {
+        &self.size
+    }
+
+    /**
+     * Linked into a library path if library paths are configured, else has any external hardlink
+     */
+    pub fn is_linked(&self) -> &bool {
+        &self.is_linked
+    }
+
+    /**
+     * Paths inside the library roots that share the inode of this file
+     */
+    pub fn library_paths(&self) -> &Vec<String> {
+        &self.library_paths
+    }
+}
+
+#[derive(Clone)]
+pub struct TorrentLinks {
+    files: Vec<FileLinks>,
+}
+
+impl TorrentLinks {
+    pub fn new(files: Vec<FileLinks>) -> Self {
+        Self { files }
+    }
+
+    pub fn files(&self) -> &Vec<FileLinks> {
+        &self.files
+    }
+
+    pub fn files_total(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn files_linked(&self) -> usize {
+        self.files.iter().filter(|file_links| *file_links.is_linked()).count()
+    }
+
+    pub fn bytes_total(&self) -> u64 {
+        self.files.iter().map(|file_links| file_links.size()).sum()
+    }
+
+    pub fn bytes_linked(&self) -> u64 {
+        self.files.iter().filter(|file_links| *file_links.is_linked()).map(|file_links| file_links.size()).sum()
+    }
+
+    /**
+     * Percentage of bytes that are linked, falls back to the file ratio if all files are empty
+     */
+    pub fn linked_percent(&self) -> f64 {
+        let bytes_total = self.bytes_total();
+        if bytes_total > 0 {
+            return self.bytes_linked() as f64 / bytes_total as f64 * 100.0;
+        }
+        match self.files_total() {
+            0 => 0.0,
+            files_total => self.files_linked() as f64 / files_total as f64 * 100.0,
+        }
+    }
+
+    /**
+     * Linked if at least 1 file is linked and at least min_linked_percent of the bytes are linked
+     */
+    pub fn is_linked(&self, min_linked_percent: f64) -> bool {
+        self.files_linked() > 0 && self.linked_percent() >= min_linked_percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_file_links(size: u64, is_linked: bool) -> FileLinks {
+        FileLinks::new(String::from("/data/torrents/file"), size, is_linked, Vec::new())
+    }
+
+    #[test]
+    fn no_files_are_not_linked() {
+        let torrent_links = TorrentLinks::new(Vec::new());
+        assert_eq!(torrent_links.linked_percent(), 0.0);
+        assert!(!torrent_links.is_linked(0.0));
+    }
+
+    #[test]
+    fn any_linked_file_is_linked_without_min_linked_percent() {
+        let torrent_links = TorrentLinks::new(vec![get_file_links(900, false), get_file_links(100, true)]);
+        assert_eq!(torrent_links.linked_percent(), 10.0);
+        assert!(torrent_links.is_linked(0.0));
+    }
+
+    #[test]
+    fn linked_bytes_have_to_reach_min_linked_percent() {
+        let torrent_links = TorrentLinks::new(vec![get_file_links(900, true), get_file_links(100, false)]);
+        assert_eq!(torrent_links.linked_percent(), 90.0);
+        assert!(torrent_links.is_linked(90.0));
+        assert!(!torrent_links.is_linked(95.0));
+    }
+
+    #[test]
+    fn unlinked_files_are_not_linked_with_0_min_linked_percent() {
+        let torrent_links = TorrentLinks::new(vec![get_file_links(100, false)]);
+        assert!(!torrent_links.is_linked(0.0));
+    }
+
+    #[test]
+    fn empty_files_fall_back_to_the_file_ratio() {
+        let torrent_links = TorrentLinks::new(vec![get_file_links(0, true), get_file_links(0, false)]);
+        assert_eq!(torrent_links.linked_percent(), 50.0);
+        assert!(torrent_links.is_linked(50.0));
+        assert!(!torrent_links.is_linked(60.0));
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::Context;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::{MessageRule, UnregisteredMessages},
+    torrent_clients::models::tracker::Tracker,
+    utils::domain_utils::DomainUtils,
+};
+
+enum MessagePattern {
+    Substring(String),
+    Regex(Regex),
+}
+
+/**
+ * A configured unregistered message rule, compiled once per run
+ */
+struct CompiledRule {
+    pattern: MessagePattern,
+    description: String,
+}
+
+impl CompiledRule {
+    fn new(message_rule: &MessageRule, tracker_domain: Option<&str>) -> Result<Self, anyhow::Error> {
+        let pattern = match message_rule.regex() {
+            true => MessagePattern::Regex(
+                RegexBuilder::new(message_rule.pattern())
+                    .case_insensitive(true)
+                    .build()
+                    .context(format!("Invalid unregistered message regex: {}", message_rule.pattern()))?,
+            ),
+            false => MessagePattern::Substring(message_rule.pattern().to_lowercase()),
+        };
+        let kind = if message_rule.regex() { "regex" } else { "substring" };
+        let description = match tracker_domain {
+            Some(tracker_domain) => format!("{} \"{}\" ({})", kind, message_rule.pattern(), tracker_domain),
+            None => format!("{} \"{}\"", kind, message_rule.pattern()),
+        };
+        Ok(Self { pattern, description })
+    }
+
+    fn is_match(&self, msg_lowercase: &str) -> bool {
+        match &self.pattern {
+            MessagePattern::Substring(substring) => msg_lowercase.contains(substring.as_str()),
+            MessagePattern::Regex(regex) => regex.is_match(msg_lowercase),
+        }
+    }
+}
+
+/**
+ * Rules added or removed for trackers of a domain
+ */
+struct CompiledTrackerRules {
+    domain: String,
+    add: Vec<CompiledRule>,
+    remove: Vec<String>,
+}
+
+pub struct UnregisteredMatcher {
+    rules: Vec<(String, CompiledRule)>,
+    tracker_rules: Vec<CompiledTrackerRules>,
+}
+
+impl UnregisteredMatcher {
+    pub fn new(unregistered_messages: &UnregisteredMessages) -> Result<Self, anyhow::Error> {
+        let mut rules: Vec<(String, CompiledRule)> = Vec::new();
+        for message_rule in unregistered_messages.rules() {
+            rules.push((message_rule.pattern().to_string(), CompiledRule::new(message_rule, None)?));
+        }
+
+        let mut tracker_rules: Vec<CompiledTrackerRules> = Vec::new();
+        for (domain, tracker_message_rules) in unregistered_messages.trackers() {
+            let mut add: Vec<CompiledRule> = Vec::new();
+            for message_rule in tracker_message_rules.add() {
+                add.push(CompiledRule::new(message_rule, Some(domain))?);
+            }
+            tracker_rules.push(CompiledTrackerRules {
+                domain: domain.to_string(),
+                add,
+                remove: tracker_message_rules.remove().clone(),
+            });
+        }
+
+        Ok(Self { rules, tracker_rules })
+    }
+
+    /**
+     * Description of the first rule matching the message of a tracker that is not working, None if the tracker is not unregistered
+     * Rules added for the tracker domain are checked before the global rules, global rules removed for the tracker domain are skipped
+     */
+    pub fn get_matched_rule(&self, tracker: &Tracker) -> Option<String> {
+        if tracker.is_working() {
+            return None;
+        }
+
+        self.get_matched_message_rule(&tracker.domain(), tracker.msg())
+    }
+
+    /**
+     * Description of the first rule matching the message of a tracker domain, regardless of the tracker status
+     */
+    pub fn get_matched_message_rule(&self, domain: &str, msg: &str) -> Option<String> {
+        let msg_lowercase = msg.to_lowercase();
+        let matching_tracker_rules: Vec<&CompiledTrackerRules> = self.tracker_rules.iter().filter(|tracker_rules| DomainUtils::matches(domain, &tracker_rules.domain)).collect();
+
+        for tracker_rules in &matching_tracker_rules {
+            if let Some(rule) = tracker_rules.add.iter().find(|rule| rule.is_match(&msg_lowercase)) {
+                return Some(rule.description.clone());
+            }
+        }
+
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| !matching_tracker_rules.iter().any(|tracker_rules| tracker_rules.remove.contains(pattern)))
+            .find(|(_, rule)| rule.is_match(&msg_lowercase))
+            .map(|(_, rule)| rule.description.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn get_matcher(unregistered_messages: serde_json::Value) -> UnregisteredMatcher {
+        UnregisteredMatcher::new(&serde_json::from_value(unregistered_messages).unwrap()).unwrap()
+    }
+
+    fn get_tracker(url: &str, status: i8, msg: &str) -> Tracker {
+        serde_json::from_value(json!({ "url": url, "status": status, "msg": msg })).unwrap()
+    }
+
+    #[test]
+    fn global_rules_match_case_insensitive_substrings() {
+        let unregistered_matcher = get_matcher(json!({ "rules": [{ "pattern": "Unregistered" }] }));
+        assert_eq!(
+            unregistered_matcher.get_matched_message_rule("tracker.example.org", "Torrent UNREGISTERED"),
+            Some(String::from("substring \"Unregistered\""))
+        );
+        assert_eq!(unregistered_matcher.get_matched_message_rule("tracker.example.org", "timed out"), None);
+    }
+
+    #[test]
+    fn regex_rules_match() {
+        let unregistered_matcher = get_matcher(json!({ "rules": [{ "pattern": "^torrent (deleted|trumped)$", "regex": true }] }));
+        assert!(unregistered_matcher.get_matched_message_rule("tracker.example.org", "Torrent Trumped").is_some());
+        assert!(unregistered_matcher.get_matched_message_rule("tracker.example.org", "torrent trumped by another").is_none());
+    }
+
+    #[test]
+    fn invalid_regex_fails() {
+        let unregistered_messages: UnregisteredMessages = serde_json::from_value(json!({ "rules": [{ "pattern": "(", "regex": true }] })).unwrap();
+        assert!(UnregisteredMatcher::new(&unregistered_messages).is_err());
+    }
+
+    #[test]
+    fn added_rules_only_match_their_domain_and_subdomains() {
+        let unregistered_matcher = get_matcher(json!({ "rules": [], "trackers": { "example.org": { "add": [{ "pattern": "gone" }] } } }));
+        assert_eq!(unregistered_matcher.get_matched_message_rule("tracker.example.org", "gone"), Some(String::from("substring \"gone\" (example.org)")));
+        assert_eq!(unregistered_matcher.get_matched_message_rule("example.org", "gone"), Some(String::from("substring \"gone\" (example.org)")));
+        assert_eq!(unregistered_matcher.get_matched_message_rule("other.org", "gone"), None);
+    }
+
+    #[test]
+    fn removed_rules_only_skip_their_domain() {
+        let unregistered_matcher = get_matcher(json!({ "rules": [{ "pattern": "unknown" }], "trackers": { "example.org": { "remove": ["unknown"] } } }));
+        assert_eq!(unregistered_matcher.get_matched_message_rule("tracker.example.org", "unknown error"), None);
+        assert!(unregistered_matcher.get_matched_message_rule("other.org", "unknown error").is_some());
+    }
+
+    #[test]
+    fn working_trackers_are_never_unregistered() {
+        let unregistered_matcher = get_matcher(json!({ "rules": [{ "pattern": "unregistered" }] }));
+        assert!(unregistered_matcher.get_matched_rule(&get_tracker("https://tracker.example.org/announce", 2, "unregistered")).is_none());
+        assert!(unregistered_matcher.get_matched_rule(&get_tracker("https://tracker.example.org/announce", 4, "unregistered")).is_some());
+    }
+}
@@ -0,0 +1,638 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rusqlite::{ToSql, params};
+
+use crate::{
+    info,
+    jobs::{
+        enums::{action_outcome::ActionOutcome, action_type::ActionType, quiet_hours_mode::QuietHoursMode},
+        models::quiet_windows::QuietWindows,
+        utils::tracker_domain_utils::TrackerDomainUtils,
+    },
+    logger::enums::category::Category,
+    torrent_clients::{models::torrent::Torrent, torrent_manager::TorrentManager},
+    trace,
+    utils::{date_utils::DateUtils, db_manager::DbManager},
+    warn,
+};
+
+/**
+ * Action that is about to be recorded or deferred, bytes_reclaimed, kept_for_cross_seeds and delete_files default to 0 and false
+ */
+pub struct ActionEntry {
+    action: String,
+    hash: String,
+    name: String,
+    tracker: String,
+    paths: Vec<String>,
+    bytes_reclaimed: i64,
+    kept_for_cross_seeds: bool,
+    delete_files: bool,
+    rule: String,
+}
+
+impl ActionEntry {
+    pub fn new(action: &str, hash: &str, name: &str, tracker: &str, paths: Vec<String>, rule: &str) -> Self {
+        Self {
+            action: action.to_string(),
+            hash: hash.to_string(),
+            name: name.to_string(),
+            tracker: tracker.to_string(),
+            paths,
+            bytes_reclaimed: 0,
+            kept_for_cross_seeds: false,
+            delete_files: false,
+            rule: rule.to_string(),
+        }
+    }
+
+    /**
+     * Action on a torrent, its content path is recorded as the path
+     * tracker is passed since the tracker of a torrent is empty without a working tracker (e.g. stopped or unregistered)
+     */
+    pub fn from_torrent(action: &str, torrent: &Torrent, tracker: &str, rule: &str) -> Self {
+        ActionEntry::new(action, torrent.hash(), torrent.name(), tracker, vec![torrent.content_path().to_string()], rule)
+    }
+
+    pub fn with_bytes_reclaimed(mut self, bytes_reclaimed: i64) -> Self {
+        self.bytes_reclaimed = bytes_reclaimed;
+        self
+    }
+
+    pub fn with_kept_for_cross_seeds(mut self, kept_for_cross_seeds: bool) -> Self {
+        self.kept_for_cross_seeds = kept_for_cross_seeds;
+        self
+    }
+
+    /**
+     * Only needed for deferred deletes of torrents, so they delete the files like the action would have
+     */
+    pub fn with_delete_files(mut self, delete_files: bool) -> Self {
+        self.delete_files = delete_files;
+        self
+    }
+
+    /* Getter */
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn paths(&self) -> &Vec<String> {
+        &self.paths
+    }
+    pub fn delete_files(&self) -> bool {
+        self.delete_files
+    }
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+}
+
+pub struct DeferredActionRecord {
+    id: i64,
+    job: String,
+    action_entry: ActionEntry,
+}
+
+impl DeferredActionRecord {
+    /* Getter */
+    pub fn job(&self) -> &str {
+        &self.job
+    }
+    pub fn action_entry(&self) -> &ActionEntry {
+        &self.action_entry
+    }
+}
+
+pub struct ActionRecord {
+    created_at: DateTime<Utc>,
+    job: String,
+    action: String,
+    hash: String,
+    name: String,
+    tracker: String,
+    paths: Vec<String>,
+    bytes_reclaimed: i64,
+    kept_for_cross_seeds: bool,
+    rule: String,
+    success: bool,
+    error: String,
+}
+
+impl ActionRecord {
+    /* Getter */
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+    pub fn job(&self) -> &str {
+        &self.job
+    }
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn tracker(&self) -> &str {
+        &self.tracker
+    }
+    pub fn paths(&self) -> &Vec<String> {
+        &self.paths
+    }
+    pub fn bytes_reclaimed(&self) -> i64 {
+        self.bytes_reclaimed
+    }
+    pub fn kept_for_cross_seeds(&self) -> bool {
+        self.kept_for_cross_seeds
+    }
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+    pub fn success(&self) -> bool {
+        self.success
+    }
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+}
+
+/**
+ * Filter for recorded actions, None matches everything
+ * since is inclusive, until is exclusive
+ */
+#[derive(Default)]
+pub struct ActionFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub job: Option<String>,
+    pub tracker: Option<String>,
+}
+
+pub struct ActionUtils {
+    db_manager: DbManager,
+    category: Category,
+    job: String,
+    quiet_windows: QuietWindows,
+    deferred_count: usize,
+    deferral_end: Option<DateTime<Utc>>,
+}
+
+impl ActionUtils {
+    /**
+     * Actions are recorded for the job of the category and deferred during its quiet hours
+     */
+    pub fn new(db_manager: DbManager, category: Category, quiet_windows: QuietWindows) -> Self {
+        ActionUtils::from_job_name(db_manager, category, category.to_string(), quiet_windows)
+    }
+
+    /**
+     * Actions of another job (e.g. taken from the deferred queue) are recorded for that job, category is the one of the calling job
+     */
+    pub fn from_job_name(db_manager: DbManager, category: Category, job: String, quiet_windows: QuietWindows) -> Self {
+        Self {
+            db_manager,
+            category,
+            job,
+            quiet_windows,
+            deferred_count: 0,
+            deferral_end: None,
+        }
+    }
+
+    /**
+     * Number of actions deferred by this instance and the end of the latest quiet hours they wait for
+     */
+    pub fn get_deferred(&self) -> Option<(usize, DateTime<Utc>)> {
+        self.deferral_end.map(|deferral_end| (self.deferred_count, deferral_end))
+    }
+
+    /**
+     * Queue the actions instead of taking them if quiet hours that defer actions are active
+     * Returns the end of the quiet hours if they were deferred
+     */
+    pub fn defer_if_quiet(&mut self, action_entries: &[ActionEntry]) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        let now = DateUtils::get_current_utc_datetime();
+        let Some(deferral_end) = self.quiet_windows.get_end(&QuietHoursMode::DeferActions, &now) else {
+            return Ok(None);
+        };
+        let now_str = DateUtils::convert_utc_datetime_to_string(&now);
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        // The same action is only queued once, even if later runs defer it again
+        for action_entry in action_entries {
+            tx.prepare_cached(
+                "INSERT INTO deferred_actions (created_at, job, action, hash, name, tracker, paths, delete_files, bytes_reclaimed, kept_for_cross_seeds, rule)
+                    SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11
+                    WHERE NOT EXISTS (SELECT 1 FROM deferred_actions WHERE job = ?2 AND action = ?3 AND hash = ?4 AND paths = ?7)",
+            )?
+            .execute(params![
+                now_str,
+                self.job,
+                action_entry.action,
+                action_entry.hash,
+                action_entry.name,
+                action_entry.tracker,
+                action_entry.paths.join("\n"),
+                action_entry.delete_files,
+                action_entry.bytes_reclaimed,
+                action_entry.kept_for_cross_seeds,
+                action_entry.rule,
+            ])
+            .context("Failed to insert deferred action")?;
+            trace!(self.category, "Deferred {} action of {} for {}", action_entry.action, self.job, action_entry.hash);
+        }
+        tx.commit().context("Failed to commit deferred actions")?;
+
+        self.deferred_count += action_entries.len();
+        self.deferral_end = self.deferral_end.max(Some(deferral_end));
+        Ok(Some(deferral_end))
+    }
+
+    /**
+     * Check if any job has deferred actions, without reading them
+     */
+    pub fn has_deferred_actions(&self) -> Result<bool, anyhow::Error> {
+        let conn = self.db_manager.conn()?;
+        let has_deferred_actions: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM deferred_actions)", [], |row| row.get(0)).context("Failed to check for deferred actions")?;
+        Ok(has_deferred_actions)
+    }
+
+    /**
+     * Get the deferred actions of every job, oldest first
+     */
+    pub fn get_deferred_actions(&mut self) -> Result<Vec<DeferredActionRecord>, anyhow::Error> {
+        let conn = self.db_manager.conn()?;
+        let mut stmt = conn
+            .prepare("SELECT id, job, action, hash, name, tracker, paths, delete_files, bytes_reclaimed, kept_for_cross_seeds, rule FROM deferred_actions ORDER BY id")
+            .context("Failed to prepare get_deferred_actions select")?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                let paths_str: String = row.get(6)?;
+                Ok(DeferredActionRecord {
+                    id: row.get(0)?,
+                    job: row.get(1)?,
+                    action_entry: ActionEntry {
+                        action: row.get(2)?,
+                        hash: row.get(3)?,
+                        name: row.get(4)?,
+                        tracker: row.get(5)?,
+                        paths: paths_str.lines().map(String::from).collect(),
+                        delete_files: row.get(7)?,
+                        bytes_reclaimed: row.get(8)?,
+                        kept_for_cross_seeds: row.get(9)?,
+                        rule: row.get(10)?,
+                    },
+                })
+            })
+            .context("Failed to execute query to get deferred actions")?;
+
+        let mut deferred_action_records: Vec<DeferredActionRecord> = Vec::new();
+        for row in rows {
+            deferred_action_records.push(row.context("Failed to map deferred action")?);
+        }
+
+        Ok(deferred_action_records)
+    }
+
+    pub fn delete_deferred_action(&mut self, deferred_action_record: &DeferredActionRecord) -> Result<(), anyhow::Error> {
+        let conn = self.db_manager.conn()?;
+        conn.execute("DELETE FROM deferred_actions WHERE id = ?1", params![deferred_action_record.id])
+            .context("Failed to delete deferred action")?;
+        Ok(())
+    }
+
+    /**
+     * Record actions with the result they had, the error is stored with its whole context chain
+     */
+    pub fn record(&mut self, action_entries: &[ActionEntry], result: &Result<(), anyhow::Error>) -> Result<(), anyhow::Error> {
+        let now_str = DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime());
+        let error_str = match result {
+            Ok(()) => String::new(),
+            Err(e) => format!("{:#}", e),
+        };
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for action_entry in action_entries {
+            tx.prepare_cached(
+                "INSERT INTO actions (created_at, job, action, hash, name, tracker, paths, bytes_reclaimed, kept_for_cross_seeds, rule, success, error)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?
+            .execute(params![
+                now_str,
+                self.job,
+                action_entry.action,
+                action_entry.hash,
+                action_entry.name,
+                action_entry.tracker,
+                action_entry.paths.join("\n"),
+                action_entry.bytes_reclaimed,
+                action_entry.kept_for_cross_seeds,
+                action_entry.rule,
+                result.is_ok(),
+                error_str,
+            ])
+            .context("Failed to insert action")?;
+            trace!(self.category, "Recorded {} action of {} for {}", action_entry.action, self.job, action_entry.hash);
+        }
+        tx.commit().context("Failed to commit actions")?;
+
+        Ok(())
+    }
+
+    /**
+     * Record actions that are not taken since a hit-and-run requirement is not met, they are recorded as failed with the reason
+     */
+    pub fn record_skipped(&mut self, action_entries: &[ActionEntry], reason: &str) -> Result<(), anyhow::Error> {
+        self.record(action_entries, &Err(anyhow::anyhow!("Skipped, {}", reason)))
+    }
+
+    /**
+     * Take the action on torrents that share their files (e.g. a cross-seed group), each of them is recorded
+     * Files are deleted with the torrents, the reclaimed bytes are recorded on the first one
+     */
+    pub async fn take_group_action(&mut self, torrent_manager: &TorrentManager, action_type: &ActionType, torrents: &[Torrent], rule_name: &str, tracker_domain_utils: &TrackerDomainUtils) -> Result<ActionOutcome, anyhow::Error> {
+        let reclaimed_size = torrents.iter().map(|torrent| *torrent.total_size()).max().unwrap_or(0);
+        let action_entries: Vec<ActionEntry> = torrents
+            .iter()
+            .enumerate()
+            .map(|(i, torrent)| {
+                let action_entry = ActionEntry::from_torrent(&action_type.to_string(), torrent, &tracker_domain_utils.get_tracker_domain(torrent), rule_name);
+                match action_type {
+                    ActionType::Delete => action_entry.with_bytes_reclaimed(if i == 0 { reclaimed_size } else { 0 }).with_delete_files(true),
+                    _ => action_entry,
+                }
+            })
+            .collect();
+
+        match action_type {
+            ActionType::Test => {
+                self.record(&action_entries, &Ok(()))?;
+                return Ok(ActionOutcome::Taken);
+            }
+            ActionType::Recheck => {
+                warn!(self.category, "Recheck action not supported on {}", self.job);
+                return Ok(ActionOutcome::Skipped);
+            }
+            ActionType::Stop | ActionType::Delete => {}
+        }
+
+        if self.is_deferred(&action_entries)? {
+            return Ok(ActionOutcome::Deferred);
+        }
+        for (torrent, action_entry) in torrents.iter().zip(action_entries) {
+            let result = match action_type {
+                ActionType::Delete => torrent_manager.delete_torrent(torrent.hash(), true).await.context(format!("Failed to delete torrent {}", torrent.hash())),
+                _ => torrent_manager.stop_torrent(torrent.hash()).await.context(format!("Failed to stop torrent {}", torrent.hash())),
+            };
+            self.record(&[action_entry], &result)?;
+            result?;
+        }
+        match action_type {
+            ActionType::Delete => Ok(ActionOutcome::Deleted),
+            _ => Ok(ActionOutcome::Taken),
+        }
+    }
+
+    /**
+     * Queue the actions instead if quiet hours are active, returns true if they were deferred
+     */
+    pub fn is_deferred(&mut self, action_entries: &[ActionEntry]) -> Result<bool, anyhow::Error> {
+        match self.defer_if_quiet(action_entries).context("Failed to defer actions")? {
+            Some(deferral_end) => {
+                info!(self.category, "Action: Deferred until {} (quiet hours)", DateUtils::convert_utc_datetime_to_local_string(&deferral_end));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /**
+     * Get recorded actions that match the filter, oldest first
+     */
+    pub fn get_actions(&mut self, action_filter: &ActionFilter) -> Result<Vec<ActionRecord>, anyhow::Error> {
+        let since_str = action_filter.since.as_ref().map(DateUtils::convert_utc_datetime_to_string);
+        let until_str = action_filter.until.as_ref().map(DateUtils::convert_utc_datetime_to_string);
+
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<&dyn ToSql> = Vec::new();
+        if let Some(since_str) = &since_str {
+            conditions.push("created_at >= ?");
+            values.push(since_str);
+        }
+        if let Some(until_str) = &until_str {
+            conditions.push("created_at < ?");
+            values.push(until_str);
+        }
+        if let Some(job) = &action_filter.job {
+            conditions.push("job = ?");
+            values.push(job);
+        }
+        if let Some(tracker) = &action_filter.tracker {
+            conditions.push("tracker = ?");
+            values.push(tracker);
+        }
+        let where_str = match conditions.is_empty() {
+            true => String::new(),
+            false => format!("WHERE {}", conditions.join(" AND ")),
+        };
+
+        let conn = self.db_manager.conn()?;
+        let mut stmt = conn
+            .prepare(
+                format!(
+                    "SELECT created_at, job, action, hash, name, tracker, paths, bytes_reclaimed, kept_for_cross_seeds, rule, success, error FROM actions {} ORDER BY created_at, id",
+                    where_str
+                )
+                .as_str(),
+            )
+            .context("Failed to prepare get_actions select")?;
+
+        let rows = stmt
+            .query_map(values.as_slice(), |row| {
+                let created_at_str: String = row.get(0)?;
+                let created_at = DateUtils::parse_utc_datetime_from_str(&created_at_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))?;
+                let paths_str: String = row.get(6)?;
+
+                Ok(ActionRecord {
+                    created_at,
+                    job: row.get(1)?,
+                    action: row.get(2)?,
+                    hash: row.get(3)?,
+                    name: row.get(4)?,
+                    tracker: row.get(5)?,
+                    paths: paths_str.lines().map(String::from).collect(),
+                    bytes_reclaimed: row.get(7)?,
+                    kept_for_cross_seeds: row.get(8)?,
+                    rule: row.get(9)?,
+                    success: row.get(10)?,
+                    error: row.get(11)?,
+                })
+            })
+            .context("Failed to execute query to get actions")?;
+
+        let mut action_records: Vec<ActionRecord> = Vec::new();
+        for row in rows {
+            action_records.push(row.context("Failed to map action")?);
+        }
+
+        Ok(action_records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono_tz::Europe::Berlin;
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::QuietHours;
+
+    fn record(db_manager: &DbManager, category: Category, hash: &str, tracker: &str) {
+        let mut action_utils = ActionUtils::new(db_manager.clone(), category, QuietWindows::default());
+        action_utils.record(&[ActionEntry::new("delete", hash, hash, tracker, Vec::new(), "default")], &Ok(())).unwrap();
+    }
+
+    fn set_created_at(db_manager: &DbManager, hash: &str, created_at: &DateTime<Utc>) {
+        db_manager
+            .conn()
+            .unwrap()
+            .execute("UPDATE actions SET created_at = ?1 WHERE hash = ?2", params![DateUtils::convert_utc_datetime_to_string(created_at), hash])
+            .unwrap();
+    }
+
+    fn get_hashes(db_manager: &DbManager, action_filter: &ActionFilter) -> Vec<String> {
+        let mut action_utils = ActionUtils::new(db_manager.clone(), Category::Setup, QuietWindows::default());
+        action_utils.get_actions(action_filter).unwrap().iter().map(|action_record| action_record.hash().to_string()).collect()
+    }
+
+    /**
+     * a is 3 days old, b 2 days and c 1 day
+     */
+    fn get_db_manager() -> (DbManager, DateTime<Utc>) {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        let db_manager = DbManager::open_in_memory().unwrap();
+        record(&db_manager, Category::HandleUnlinked, "a", "tracker.example.org");
+        record(&db_manager, Category::HandleUnregistered, "b", "tracker.example.org");
+        record(&db_manager, Category::HandleUnlinked, "c", "other.org");
+        let now = DateUtils::get_current_utc_datetime();
+        set_created_at(&db_manager, "a", &(now - Duration::days(3)));
+        set_created_at(&db_manager, "b", &(now - Duration::days(2)));
+        set_created_at(&db_manager, "c", &(now - Duration::days(1)));
+        (db_manager, now)
+    }
+
+    #[test]
+    fn empty_filter_gets_every_action_oldest_first() {
+        let (db_manager, _) = get_db_manager();
+        assert_eq!(get_hashes(&db_manager, &ActionFilter::default()), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn since_is_inclusive_and_until_exclusive() {
+        let (db_manager, now) = get_db_manager();
+        let action_filter = ActionFilter {
+            since: Some(now - Duration::days(3)),
+            until: Some(now - Duration::days(1)),
+            ..Default::default()
+        };
+        assert_eq!(get_hashes(&db_manager, &action_filter), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn job_and_tracker_filters_are_combined() {
+        let (db_manager, _) = get_db_manager();
+        let action_filter = ActionFilter {
+            job: Some(String::from("handle_unlinked")),
+            ..Default::default()
+        };
+        assert_eq!(get_hashes(&db_manager, &action_filter), vec!["a", "c"]);
+        let action_filter = ActionFilter {
+            job: Some(String::from("handle_unlinked")),
+            tracker: Some(String::from("tracker.example.org")),
+            ..Default::default()
+        };
+        assert_eq!(get_hashes(&db_manager, &action_filter), vec!["a"]);
+    }
+
+    /**
+     * Quiet hours with the same start and end last the whole day
+     */
+    fn get_deferring_action_utils() -> ActionUtils {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        let quiet_hours: Vec<QuietHours> = serde_json::from_value(json!([{ "start": "00:00", "end": "00:00", "mode": "defer_actions" }])).unwrap();
+        ActionUtils::new(DbManager::open_in_memory().unwrap(), Category::HandleUnlinked, QuietWindows::from_config(&[], &quiet_hours).unwrap())
+    }
+
+    #[test]
+    fn actions_are_only_deferred_during_quiet_hours() {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        let mut action_utils = ActionUtils::new(DbManager::open_in_memory().unwrap(), Category::HandleUnlinked, QuietWindows::default());
+        let action_entry = ActionEntry::new("delete", "a", "a", "tracker.example.org", Vec::new(), "default");
+        assert!(action_utils.defer_if_quiet(&[action_entry]).unwrap().is_none());
+        assert!(action_utils.get_deferred_actions().unwrap().is_empty());
+        assert!(!action_utils.has_deferred_actions().unwrap());
+        assert!(action_utils.get_deferred().is_none());
+    }
+
+    #[test]
+    fn deferred_action_is_queued_once() {
+        let mut action_utils = get_deferring_action_utils();
+        let action_entry = ActionEntry::new("delete", "a", "a", "tracker.example.org", Vec::new(), "default");
+        assert!(action_utils.defer_if_quiet(std::slice::from_ref(&action_entry)).unwrap().is_some());
+        assert!(action_utils.defer_if_quiet(std::slice::from_ref(&action_entry)).unwrap().is_some());
+        // Another action of the same torrent is queued as well
+        let action_entry = ActionEntry::new("stop", "a", "a", "tracker.example.org", Vec::new(), "default");
+        assert!(action_utils.defer_if_quiet(&[action_entry]).unwrap().is_some());
+
+        let deferred_action_records = action_utils.get_deferred_actions().unwrap();
+        assert_eq!(
+            deferred_action_records.iter().map(|deferred_action_record| deferred_action_record.action_entry().action()).collect::<Vec<&str>>(),
+            vec!["delete", "stop"]
+        );
+        assert!(deferred_action_records.iter().all(|deferred_action_record| deferred_action_record.job() == "handle_unlinked"));
+    }
+
+    #[test]
+    fn deferred_paths_keep_their_order() {
+        let mut action_utils = get_deferring_action_utils();
+        let paths = vec![String::from("/data/orphaned/a.mkv"), String::from("/data/orphaned/b.nfo"), String::from("/data/orphaned")];
+        let action_entry = ActionEntry::new("delete", "", "orphaned", "", paths.clone(), "default").with_delete_files(true);
+        action_utils.defer_if_quiet(&[action_entry]).unwrap();
+
+        let deferred_action_records = action_utils.get_deferred_actions().unwrap();
+        assert_eq!(deferred_action_records[0].action_entry().paths(), &paths);
+        assert!(deferred_action_records[0].action_entry().delete_files());
+    }
+
+    #[test]
+    fn taken_deferred_action_is_dropped_from_the_queue() {
+        let mut action_utils = get_deferring_action_utils();
+        let action_entries = vec![
+            ActionEntry::new("delete", "a", "a", "tracker.example.org", Vec::new(), "default"),
+            ActionEntry::new("delete", "b", "b", "tracker.example.org", Vec::new(), "default"),
+        ];
+        action_utils.defer_if_quiet(&action_entries).unwrap();
+        assert_eq!(action_utils.get_deferred().map(|(deferred_count, _)| deferred_count), Some(2));
+        assert!(action_utils.has_deferred_actions().unwrap());
+
+        let deferred_action_records = action_utils.get_deferred_actions().unwrap();
+        action_utils.delete_deferred_action(&deferred_action_records[0]).unwrap();
+        let deferred_action_records = action_utils.get_deferred_actions().unwrap();
+        assert_eq!(
+            deferred_action_records.iter().map(|deferred_action_record| deferred_action_record.action_entry().hash()).collect::<Vec<&str>>(),
+            vec!["b"]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::{HashMap, HashSet},
+    os::unix::fs::MetadataExt,
+    path::Path,
+};
+
+use anyhow::Context;
+use walkdir::WalkDir;
+
+use crate::{jobs::models::cross_seed_group::CrossSeedGroup, logger::enums::category::Category, torrent_clients::models::torrent::Torrent, trace};
+
+#[derive(Clone, Default)]
+pub struct CrossSeedGroups {
+    groups: Vec<CrossSeedGroup>,
+    group_indexes: HashMap<String, usize>,
+}
+
+impl CrossSeedGroups {
+    pub fn groups(&self) -> &Vec<CrossSeedGroup> {
+        &self.groups
+    }
+
+    /**
+     * Get the unique groups that contain at least 1 of the given hashes, in order of the hashes
+     */
+    pub fn get_groups_of_hashes(&self, hashes: &[String]) -> Vec<&CrossSeedGroup> {
+        let mut seen_group_indexes: HashSet<usize> = HashSet::new();
+        let mut groups: Vec<&CrossSeedGroup> = Vec::new();
+        for hash in hashes {
+            if let Some(group_index) = self.group_indexes.get(hash)
+                && seen_group_indexes.insert(*group_index)
+                && let Some(group) = self.groups.get(*group_index)
+            {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+}
+
+pub struct CrossSeedUtils;
+
+impl CrossSeedUtils {
+    /**
+     * Group torrents that share files, either by the same content path or by at least 1 shared inode (device and inode)
+     * Every torrent is in exactly 1 group, torrents that don't share anything are in a group of their own
+     */
+    pub fn get_cross_seed_groups(torrents: &[&Torrent]) -> Result<CrossSeedGroups, anyhow::Error> {
+        let mut parents: Vec<usize> = (0..torrents.len()).collect();
+        let mut content_path_owners: HashMap<&str, usize> = HashMap::new();
+        let mut inode_owners: HashMap<(u64, u64), usize> = HashMap::new();
+
+        for (i, torrent) in torrents.iter().enumerate() {
+            if torrent.content_path().is_empty() {
+                continue;
+            }
+
+            // Same content path
+            match content_path_owners.get(torrent.content_path()) {
+                Some(owner) => CrossSeedUtils::union(&mut parents, *owner, i),
+                None => {
+                    content_path_owners.insert(torrent.content_path(), i);
+                }
+            }
+
+            // Shared inodes
+            if !Path::new(torrent.content_path()).exists() {
+                trace!(Category::CrossSeed, "Content path doesn't exist, only grouping by content path: ({}) {}", torrent.hash(), torrent.name());
+                continue;
+            }
+            for entry in WalkDir::new(torrent.content_path()) {
+                let entry_result = entry.context(format!("Failed to get entry_result for {}", torrent.content_path()))?;
+                if !entry_result.file_type().is_file() {
+                    continue;
+                }
+                let metadata = entry_result.metadata().context(format!("Failed to get file metadata for {:?}", entry_result.path()))?;
+                match inode_owners.get(&(metadata.dev(), metadata.ino())) {
+                    Some(owner) => CrossSeedUtils::union(&mut parents, *owner, i),
+                    None => {
+                        inode_owners.insert((metadata.dev(), metadata.ino()), i);
+                    }
+                }
+            }
+        }
+
+        // Collect members by root
+        let mut groups: Vec<CrossSeedGroup> = Vec::new();
+        let mut group_indexes: HashMap<String, usize> = HashMap::new();
+        let mut root_group_indexes: HashMap<usize, usize> = HashMap::new();
+        let mut group_hashes: Vec<Vec<String>> = Vec::new();
+        for (i, torrent) in torrents.iter().enumerate() {
+            let root = CrossSeedUtils::find(&mut parents, i);
+            let group_index = *root_group_indexes.entry(root).or_insert_with(|| {
+                group_hashes.push(Vec::new());
+                group_hashes.len() - 1
+            });
+            group_hashes[group_index].push(torrent.hash().to_string());
+            group_indexes.insert(torrent.hash().to_string(), group_index);
+        }
+        for hashes in group_hashes {
+            if hashes.len() > 1 {
+                trace!(Category::CrossSeed, "Found cross-seed group with {} torrents: {:?}", hashes.len(), hashes);
+            }
+            groups.push(CrossSeedGroup::new(hashes));
+        }
+
+        Ok(CrossSeedGroups { groups, group_indexes })
+    }
+
+    fn find(parents: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parents[root] != root {
+            root = parents[root];
+        }
+        // Path compression
+        let mut current = i;
+        while parents[current] != root {
+            let next = parents[current];
+            parents[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    fn union(parents: &mut [usize], a: usize, b: usize) {
+        let root_a = CrossSeedUtils::find(parents, a);
+        let root_b = CrossSeedUtils::find(parents, b);
+        if root_a != root_b {
+            parents[root_b] = root_a;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        process,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use serde_json::json;
+
+    use super::*;
+
+    static TEST_DIR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /**
+     * Empty dir for files of a test, unique per test so they can run in parallel
+     */
+    fn get_test_dir() -> PathBuf {
+        let test_dir = std::env::temp_dir().join(format!("torrent-cleaner-cross-seed-{}-{}", process::id(), TEST_DIR_COUNT.fetch_add(1, Ordering::SeqCst)));
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&test_dir).unwrap();
+        test_dir
+    }
+
+    fn get_torrent(hash: &str, content_path: &str) -> Torrent {
+        Torrent::from_test_fields(json!({ "hash": hash, "name": hash, "content_path": content_path }))
+    }
+
+    fn get_group_hashes(torrents: &[Torrent]) -> Vec<Vec<String>> {
+        let cross_seed_groups = CrossSeedUtils::get_cross_seed_groups(&torrents.iter().collect::<Vec<&Torrent>>()).unwrap();
+        let hashes: Vec<String> = torrents.iter().map(|torrent| torrent.hash().to_string()).collect();
+        cross_seed_groups.get_groups_of_hashes(&hashes).iter().map(|group| group.hashes().clone()).collect()
+    }
+
+    #[test]
+    fn groups_torrents_with_the_same_content_path() {
+        let torrents = vec![get_torrent("a", "/missing/movie"), get_torrent("b", "/missing/other"), get_torrent("c", "/missing/movie")];
+        assert_eq!(get_group_hashes(&torrents), vec![vec!["a", "c"], vec!["b"]]);
+    }
+
+    #[test]
+    fn keeps_torrents_without_content_path_alone() {
+        let torrents = vec![get_torrent("a", ""), get_torrent("b", "")];
+        assert_eq!(get_group_hashes(&torrents), vec![vec!["a"], vec!["b"]]);
+    }
+
+    #[test]
+    fn groups_torrents_with_a_shared_inode() {
+        let test_dir = get_test_dir();
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::write(test_dir.join("a/movie.mkv"), "movie").unwrap();
+        fs::hard_link(test_dir.join("a/movie.mkv"), test_dir.join("b/movie.mkv")).unwrap();
+        fs::write(test_dir.join("c/movie.mkv"), "movie").unwrap();
+
+        let torrents: Vec<Torrent> = ["a", "b", "c"].iter().map(|hash| get_torrent(hash, test_dir.join(hash).to_str().unwrap())).collect();
+        assert_eq!(get_group_hashes(&torrents), vec![vec!["a", "b"], vec!["c"]]);
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn groups_torrents_transitively() {
+        let test_dir = get_test_dir();
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::write(test_dir.join("a/movie.mkv"), "movie").unwrap();
+        fs::hard_link(test_dir.join("a/movie.mkv"), test_dir.join("c/movie.mkv")).unwrap();
+
+        // a and b share the content path, a and c an inode
+        let a_path = test_dir.join("a");
+        let torrents = vec![
+            get_torrent("b", a_path.to_str().unwrap()),
+            get_torrent("c", test_dir.join("c").to_str().unwrap()),
+            get_torrent("a", a_path.to_str().unwrap()),
+        ];
+        assert_eq!(get_group_hashes(&torrents), vec![vec!["b", "c", "a"]]);
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn get_groups_of_hashes_returns_every_group_once() {
+        let torrents = [get_torrent("a", "/missing/movie"), get_torrent("b", "/missing/other"), get_torrent("c", "/missing/movie")];
+        let cross_seed_groups = CrossSeedUtils::get_cross_seed_groups(&torrents.iter().collect::<Vec<&Torrent>>()).unwrap();
+        let hashes: Vec<String> = ["c", "b", "a", "unknown"].iter().map(|hash| hash.to_string()).collect();
+        let groups: Vec<&Vec<String>> = cross_seed_groups.get_groups_of_hashes(&hashes).iter().map(|group| group.hashes()).collect();
+        assert_eq!(groups, vec![&vec!["a", "c"], &vec!["b"]]);
+    }
+}
@@ -1,25 +1,48 @@
 use std::{collections::HashMap, os::unix::fs::MetadataExt, path::Path};
 
 use anyhow::Context;
-use walkdir::{DirEntryExt, WalkDir};
+use walkdir::WalkDir;
 
-use crate::{logger::enums::category::Category, trace};
+use crate::{jobs::models::torrent_links::FileLinks, logger::enums::category::Category, trace, warn};
 
 pub struct FileUtils {}
 
 impl FileUtils {
     /**
-     * Returns a HashMap of the inode and the count of known links
+     * Returns a HashMap of the file id (device, inode) and the count of known links
      * Walk through dir instead of using torrent content files because an orphaned file might still be externally linked
+     * Links inside link_paths (e.g. cross-seed linkDir) are counted as known as well, so only links into media libraries are external
      */
-    pub fn get_known_hardlinks(dir_path_str: &str) -> Result<HashMap<u64, u64>, anyhow::Error> {
-        let mut files_hardlink_count: HashMap<u64, u64> = HashMap::new();
+    pub fn get_known_hardlinks(dir_path_str: &str, link_path_strs: &[String]) -> Result<HashMap<(u64, u64), u64>, anyhow::Error> {
+        let mut files_hardlink_count: HashMap<(u64, u64), u64> = HashMap::new();
         for entry in WalkDir::new(dir_path_str) {
             let entry_result = entry.context("Failed to get entry_result")?;
             if entry_result.file_type().is_file() {
-                *files_hardlink_count.entry(entry_result.ino()).or_insert(0) += 1;
+                let metadata = entry_result.metadata().context(format!("Failed to get file metadata for {:?}", entry_result.path()))?;
+                *files_hardlink_count.entry((metadata.dev(), metadata.ino())).or_insert(0) += 1;
             }
         }
+
+        for link_path_str in link_path_strs {
+            let link_path = Path::new(link_path_str);
+            // Links would be counted twice if the link path and the torrent folder overlap
+            if link_path.starts_with(dir_path_str) || Path::new(dir_path_str).starts_with(link_path) {
+                warn!(Category::FileUtils, "Ignoring link path {} because it overlaps with the torrent folder {}", link_path_str, dir_path_str);
+                continue;
+            }
+            if !link_path.is_dir() {
+                warn!(Category::FileUtils, "Ignoring link path {} because it doesn't exist or isn't a dir", link_path_str);
+                continue;
+            }
+            for entry in WalkDir::new(link_path) {
+                let entry_result = entry.context(format!("Failed to get entry_result in link path {}", link_path_str))?;
+                if entry_result.file_type().is_file() {
+                    let metadata = entry_result.metadata().context(format!("Failed to get file metadata for {:?}", entry_result.path()))?;
+                    *files_hardlink_count.entry((metadata.dev(), metadata.ino())).or_insert(0) += 1;
+                }
+            }
+        }
+
         Ok(files_hardlink_count)
     }
 
@@ -27,7 +50,7 @@ impl FileUtils {
      * Check if the given path has more hardlinks than the known amount of hardlinks in the HashMap
      * If the path is a file, check it directly, if the path is a dir walk through dir and all subdirs recursively and check each file
      */
-    pub fn has_external_hardlinks(known_hardlinks: &HashMap<u64, u64>, path_str: &str) -> Result<bool, anyhow::Error> {
+    pub fn has_external_hardlinks(known_hardlinks: &HashMap<(u64, u64), u64>, path_str: &str) -> Result<bool, anyhow::Error> {
         let path_metadata = Path::new(path_str).metadata().context(format!("Failed to get file metadata for {}", path_str))?;
         let path_file_type = path_metadata.file_type();
 
@@ -36,7 +59,7 @@ impl FileUtils {
             trace!(Category::FileUtils, "has_external_hardlinks: Path is file: {}", path_str);
             let ino = path_metadata.ino();
             let nlink = path_metadata.nlink();
-            if let Some(known_links_count) = known_hardlinks.get(&ino) {
+            if let Some(known_links_count) = known_hardlinks.get(&(path_metadata.dev(), ino)) {
                 trace!(Category::FileUtils, "  -> File path {} (ino {}) has known_links_count {} nlink {}", path_str, ino, known_links_count, nlink);
                 if *known_links_count > nlink {
                     anyhow::bail!("{} | known_hardlinks_count ({}) is bigger than nlink ({}) which is impossible", path_str, known_links_count, nlink);
@@ -56,7 +79,7 @@ impl FileUtils {
                 if metadata.is_file() {
                     let ino = metadata.ino();
                     let nlink = metadata.nlink();
-                    match known_hardlinks.get(&ino) {
+                    match known_hardlinks.get(&(metadata.dev(), ino)) {
                         Some(known_links_count) => {
                             trace!(Category::FileUtils, "  -> File path {:?} (ino {}) has known_links_count {} nlink {}", entry_result_path, ino, known_links_count, nlink);
                             if *known_links_count > nlink {
@@ -79,4 +102,81 @@ impl FileUtils {
 
         Ok(false)
     }
+
+    /**
+     * Returns a HashMap of the file id (device, inode) and all paths inside the library paths with that file id
+     * Library paths can be on other filesystems, so the inode alone doesn't identify a file
+     * Only files with more than 1 link are indexed since anything else can't be linked to a torrent
+     * Bails if a library path is missing or empty so an unmounted library doesn't make every torrent look unlinked
+     */
+    pub fn get_library_index(library_path_strs: &[String]) -> Result<HashMap<(u64, u64), Vec<String>>, anyhow::Error> {
+        let mut library_index: HashMap<(u64, u64), Vec<String>> = HashMap::new();
+        for library_path_str in library_path_strs {
+            if !Path::new(library_path_str).is_dir() {
+                anyhow::bail!("Library path {} doesn't exist or isn't a dir", library_path_str);
+            }
+            let mut file_count: u64 = 0;
+            for entry in WalkDir::new(library_path_str) {
+                let entry_result = entry.context(format!("Failed to get entry_result in library path {}", library_path_str))?;
+                if !entry_result.file_type().is_file() {
+                    continue;
+                }
+                file_count += 1;
+                let metadata = entry_result.metadata().context(format!("Failed to get file metadata for {:?}", entry_result.path()))?;
+                if metadata.nlink() > 1 {
+                    let path_str = entry_result
+                        .path()
+                        .to_str()
+                        .ok_or(anyhow::anyhow!("Failed to get string from path (may due to non-UTF8 path: {:?}", entry_result.path()))?;
+                    library_index.entry((metadata.dev(), metadata.ino())).or_default().push(path_str.to_string());
+                }
+            }
+            if file_count == 0 {
+                anyhow::bail!("Library path {} doesn't contain any files, is it mounted?", library_path_str);
+            }
+        }
+        Ok(library_index)
+    }
+
+    /**
+     * Get the link state of every file in the given path
+     * If library_index is Some, a file is only linked if its file id is found in a library path, else any external hardlink counts
+     */
+    pub fn get_file_links(known_hardlinks: &HashMap<(u64, u64), u64>, library_index: Option<&HashMap<(u64, u64), Vec<String>>>, path_str: &str) -> Result<Vec<FileLinks>, anyhow::Error> {
+        let mut files_links: Vec<FileLinks> = Vec::new();
+        for entry in WalkDir::new(path_str) {
+            let entry_result = entry.context("Failed to get entry result")?;
+            let entry_result_path = entry_result.path();
+            let metadata = entry_result.metadata().context(format!("Failed to get file metadata for {:?}", entry_result_path))?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let file_id = (metadata.dev(), metadata.ino());
+            let nlink = metadata.nlink();
+            let has_external_hardlinks = match known_hardlinks.get(&file_id) {
+                Some(known_links_count) => {
+                    if *known_links_count > nlink {
+                        anyhow::bail!("{:?} | known_hardlinks_count ({}) is bigger than nlink ({}) which is impossible", entry_result_path, known_links_count, nlink);
+                    }
+                    *known_links_count < nlink
+                }
+                None => anyhow::bail!("Didn't find file in known_hardlinks for {:?}", entry_result_path),
+            };
+            let library_paths: Vec<String> = match library_index {
+                Some(library_index) if has_external_hardlinks => library_index.get(&file_id).cloned().unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            let is_linked = match library_index {
+                Some(_) => !library_paths.is_empty(),
+                None => has_external_hardlinks,
+            };
+            trace!(
+                Category::FileUtils,
+                "  -> File path {:?} (file id {:?}) has_external_hardlinks {} library_paths {:?}", entry_result_path, file_id, has_external_hardlinks, library_paths
+            );
+            let entry_path_str = entry_result_path.to_str().ok_or(anyhow::anyhow!("Failed to get string from path (may due to non-UTF8 path: {:?}", entry_result_path))?;
+            files_links.push(FileLinks::new(entry_path_str.to_string(), metadata.len(), is_linked, library_paths));
+        }
+        Ok(files_links)
+    }
 }
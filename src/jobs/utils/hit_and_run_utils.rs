@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use crate::{
+    config::{Config, HitAndRunPolicy},
+    debug,
+    jobs::utils::tracker_domain_utils::TrackerDomainUtils,
+    logger::enums::category::Category,
+    torrent_clients::models::torrent::Torrent,
+    utils::domain_utils::DomainUtils,
+};
+
+pub struct HitAndRunUtils {
+    policies: HashMap<String, HitAndRunPolicy>,
+}
+
+impl HitAndRunUtils {
+    pub fn new(config: &Config) -> Self {
+        Self { policies: config.hit_and_run().clone() }
+    }
+
+    /**
+     * True if the tracker of the torrent has a hit-and-run policy that is not met yet
+     */
+    pub fn is_at_risk(&self, torrent: &Torrent, tracker_domain_utils: &TrackerDomainUtils) -> bool {
+        self.get_risk_str(torrent, tracker_domain_utils).is_some()
+    }
+
+    /**
+     * True if any of the torrents is at risk of a hit-and-run, each of them is logged
+     */
+    pub fn is_any_at_risk(&self, torrents: &[Torrent], tracker_domain_utils: &TrackerDomainUtils, category: Category) -> bool {
+        let mut is_any_at_risk = false;
+        for torrent in torrents {
+            if let Some(risk_str) = self.get_risk_str(torrent, tracker_domain_utils) {
+                debug!(category, "  -> Hit-and-run requirement not met ({}): {}", risk_str, torrent.name());
+                is_any_at_risk = true;
+            }
+        }
+        is_any_at_risk
+    }
+
+    /**
+     * Describes the unmet hit-and-run requirement, None if the torrent is not at risk
+     * A requirement is met as soon as the torrent reached either the seeding days or the ratio of the policy
+     * A torrent whose tracker domain can't be resolved is always at risk once any policy is configured
+     */
+    pub fn get_risk_str(&self, torrent: &Torrent, tracker_domain_utils: &TrackerDomainUtils) -> Option<String> {
+        if self.policies.is_empty() {
+            return None;
+        }
+        let tracker_domains = tracker_domain_utils.get_tracker_domains(torrent);
+        if tracker_domains.is_empty() {
+            return Some(String::from("tracker domain unknown"));
+        }
+        let (configured_domain, policy) = self.get_policy(&tracker_domains)?;
+
+        let seeding_days = *torrent.seeding_time() as f64 / 60.0 / 60.0 / 24.0;
+        let ratio = *torrent.ratio() as f64;
+
+        let mut requirement_strs: Vec<String> = Vec::new();
+        if policy.min_seeding_days() > 0.0 {
+            if seeding_days >= policy.min_seeding_days() {
+                return None;
+            }
+            requirement_strs.push(format!("seeded {:.2}/{:.2} days", seeding_days, policy.min_seeding_days()));
+        }
+        if policy.min_ratio() > 0.0 {
+            if ratio >= policy.min_ratio() {
+                return None;
+            }
+            requirement_strs.push(format!("ratio {:.2}/{:.2}", ratio, policy.min_ratio()));
+        }
+        if requirement_strs.is_empty() {
+            return None;
+        }
+
+        Some(format!("{}: {}", configured_domain, requirement_strs.join(", ")))
+    }
+
+    /**
+     * The most specific configured domain wins if several match (tracker.example.org over example.org)
+     */
+    fn get_policy(&self, tracker_domains: &[String]) -> Option<(&String, &HitAndRunPolicy)> {
+        self.policies
+            .iter()
+            .filter(|(configured_domain, _)| tracker_domains.iter().any(|tracker_domain| DomainUtils::matches(tracker_domain, configured_domain)))
+            .max_by_key(|(configured_domain, _)| configured_domain.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::torrent_clients::models::tracker::Tracker;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn get_hit_and_run_utils(hit_and_run: serde_json::Value) -> HitAndRunUtils {
+        HitAndRunUtils::new(&Config::from_test_fields(json!({ "hit_and_run": hit_and_run })))
+    }
+
+    fn get_torrent(tracker: &str, seeding_days: i64, ratio: f64) -> Torrent {
+        Torrent::from_test_fields(json!({ "hash": "a", "tracker": tracker, "seeding_time": seeding_days * DAY, "ratio": ratio }))
+    }
+
+    #[test]
+    fn no_policies_are_never_at_risk() {
+        let hit_and_run_utils = get_hit_and_run_utils(json!({}));
+        assert_eq!(hit_and_run_utils.get_risk_str(&get_torrent("", 0, 0.0), &TrackerDomainUtils::default()), None);
+    }
+
+    #[test]
+    fn most_specific_policy_wins() {
+        let hit_and_run_utils = get_hit_and_run_utils(json!({
+            "example.org": { "min_seeding_days": 10.0 },
+            "tracker.example.org": { "min_seeding_days": 3.0 },
+        }));
+        let tracker_domains = [String::from("tracker.example.org")];
+        assert_eq!(hit_and_run_utils.get_policy(&tracker_domains).map(|(configured_domain, _)| configured_domain.as_str()), Some("tracker.example.org"));
+        let tracker_domains = [String::from("other.example.org")];
+        assert_eq!(hit_and_run_utils.get_policy(&tracker_domains).map(|(configured_domain, _)| configured_domain.as_str()), Some("example.org"));
+        let tracker_domains = [String::from("notexample.org")];
+        assert!(hit_and_run_utils.get_policy(&tracker_domains).is_none());
+    }
+
+    #[test]
+    fn requirement_is_met_by_seeding_days_or_ratio() {
+        let hit_and_run_utils = get_hit_and_run_utils(json!({ "example.org": { "min_seeding_days": 7.0, "min_ratio": 1.0 } }));
+        let tracker = "https://tracker.example.org/announce";
+        assert_eq!(
+            hit_and_run_utils.get_risk_str(&get_torrent(tracker, 3, 0.5), &TrackerDomainUtils::default()),
+            Some(String::from("example.org: seeded 3.00/7.00 days, ratio 0.50/1.00"))
+        );
+        assert_eq!(hit_and_run_utils.get_risk_str(&get_torrent(tracker, 7, 0.5), &TrackerDomainUtils::default()), None);
+        assert_eq!(hit_and_run_utils.get_risk_str(&get_torrent(tracker, 3, 1.0), &TrackerDomainUtils::default()), None);
+    }
+
+    #[test]
+    fn torrents_of_other_trackers_are_not_at_risk() {
+        let hit_and_run_utils = get_hit_and_run_utils(json!({ "example.org": { "min_seeding_days": 7.0 } }));
+        assert_eq!(hit_and_run_utils.get_risk_str(&get_torrent("https://other.org/announce", 0, 0.0), &TrackerDomainUtils::default()), None);
+    }
+
+    #[test]
+    fn unknown_tracker_domain_is_at_risk() {
+        let hit_and_run_utils = get_hit_and_run_utils(json!({ "example.org": { "min_seeding_days": 7.0 } }));
+        assert_eq!(hit_and_run_utils.get_risk_str(&get_torrent("", 30, 5.0), &TrackerDomainUtils::default()), Some(String::from("tracker domain unknown")));
+    }
+
+    #[test]
+    fn trackers_list_is_used_without_working_tracker() {
+        let hit_and_run_utils = get_hit_and_run_utils(json!({ "example.org": { "min_seeding_days": 7.0 } }));
+        let mut tracker_domain_utils = TrackerDomainUtils::default();
+        let trackers: Vec<Tracker> = serde_json::from_value(json!([
+            { "url": "** [DHT] **", "status": 0, "msg": "" },
+            { "url": "https://tracker.example.org/announce", "status": 4, "msg": "unregistered" },
+        ]))
+        .unwrap();
+        tracker_domain_utils.add_tracker_domains("a", &trackers);
+        let torrent = get_torrent("", 3, 0.0);
+        assert_eq!(hit_and_run_utils.get_risk_str(&torrent, &tracker_domain_utils), Some(String::from("example.org: seeded 3.00/7.00 days")));
+    }
+}
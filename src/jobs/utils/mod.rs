@@ -1,2 +1,12 @@
+pub mod action_utils;
+pub mod cross_seed_utils;
 pub mod file_utils;
+pub mod hit_and_run_utils;
+pub mod rule_utils;
+pub mod state_filter_utils;
 pub mod strike_utils;
+pub mod tracker_domain_utils;
+pub mod tracker_error_utils;
+pub mod tracker_message_utils;
+pub mod tracker_outage_utils;
+pub mod upload_history_utils;
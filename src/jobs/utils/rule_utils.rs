@@ -0,0 +1,187 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    config::RuleOverride,
+    debug,
+    jobs::{enums::action_type::ActionType, models::rule::Rule, utils::tracker_domain_utils::TrackerDomainUtils},
+    logger::enums::category::Category,
+    torrent_clients::models::torrent::Torrent,
+    utils::domain_utils::DomainUtils,
+};
+
+pub struct RuleUtils;
+
+impl RuleUtils {
+    /**
+     * Resolve the rule of every torrent, the first matching override is applied on top of the base rule
+     * Tracker matchers use the resolved tracker domains (see has_tracker_overrides), a torrent without working tracker has an empty tracker
+     * Returns: HashMap<String, Rule> | HashMap<torrent_hash, Rule>
+     */
+    pub fn get_torrent_rules(torrents: &[Torrent], base_rule: &Rule, overrides: &[RuleOverride], tracker_domain_utils: &TrackerDomainUtils, category: Category) -> HashMap<String, Rule> {
+        let mut torrent_rules: HashMap<String, Rule> = HashMap::new();
+        for torrent in torrents {
+            let tracker_domains = tracker_domain_utils.get_tracker_domains(torrent);
+            let rule = match overrides.iter().find(|rule_override| RuleUtils::is_torrent_match(torrent, &tracker_domains, rule_override)) {
+                Some(rule_override) => {
+                    let rule = base_rule.with_override(rule_override);
+                    debug!(category, "Using rule {}: ({}) {}", rule.name(), torrent.hash(), torrent.name());
+                    rule
+                }
+                None => base_rule.clone(),
+            };
+            torrent_rules.insert(torrent.hash().to_string(), rule);
+        }
+        torrent_rules
+    }
+
+    /**
+     * True if an override matches trackers, the tracker domains of the torrents have to be resolved before their rules then
+     */
+    pub fn has_tracker_overrides(overrides: &[RuleOverride]) -> bool {
+        overrides.iter().any(|rule_override| !rule_override.tracker().is_empty())
+    }
+
+    /**
+     * Resolve the rule of a path, only the path matcher of overrides is checked since there is no torrent
+     */
+    pub fn get_path_rule(path_str: &str, base_rule: &Rule, overrides: &[RuleOverride], category: Category) -> Rule {
+        match overrides.iter().find(|rule_override| !rule_override.path().is_empty() && Path::new(path_str).starts_with(rule_override.path())) {
+            Some(rule_override) => {
+                let rule = base_rule.with_override(rule_override);
+                debug!(category, "Using rule {}: {}", rule.name(), path_str);
+                rule
+            }
+            None => base_rule.clone(),
+        }
+    }
+
+    /**
+     * Rule of a cross-seed group that is handled together, the member rule with the least destructive action wins
+     */
+    pub fn get_group_rule(hashes: &[String], torrent_rules: &HashMap<String, Rule>) -> Result<Rule, anyhow::Error> {
+        let mut group_rule: Option<(ActionType, &Rule)> = None;
+        for hash in hashes {
+            let rule = torrent_rules.get(hash).ok_or(anyhow::anyhow!("Didn't find rule for torrent {}", hash))?;
+            let action_type = ActionType::from_str(rule.action())?;
+            if group_rule.as_ref().is_none_or(|(group_action_type, _)| action_type < *group_action_type) {
+                group_rule = Some((action_type, rule));
+            }
+        }
+        group_rule.map(|(_, rule)| rule.clone()).ok_or(anyhow::anyhow!("Cross-seed group has no torrents"))
+    }
+
+    /**
+     * An override matches if it has at least one matcher set and all set matchers match
+     */
+    fn is_torrent_match(torrent: &Torrent, tracker_domains: &[String], rule_override: &RuleOverride) -> bool {
+        if rule_override.tracker().is_empty() && rule_override.category().is_empty() && rule_override.tag().is_empty() && rule_override.path().is_empty() {
+            return false;
+        }
+        if !rule_override.tracker().is_empty() && !tracker_domains.iter().any(|tracker_domain| DomainUtils::matches(tracker_domain, rule_override.tracker())) {
+            return false;
+        }
+        if !rule_override.category().is_empty() && torrent.category() != rule_override.category() {
+            return false;
+        }
+        if !rule_override.tag().is_empty() && !torrent.tags().split(',').any(|tag| tag.trim() == rule_override.tag()) {
+            return false;
+        }
+        if !rule_override.path().is_empty() && !Path::new(torrent.content_path()).starts_with(rule_override.path()) {
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{config::Config, torrent_clients::models::tracker::Tracker};
+
+    fn get_overrides(overrides: serde_json::Value) -> Vec<RuleOverride> {
+        serde_json::from_value(overrides).unwrap()
+    }
+
+    fn get_rule_names(torrents: &[Torrent], overrides: &[RuleOverride], tracker_domain_utils: &TrackerDomainUtils) -> Vec<String> {
+        let base_rule = Rule::from_handle_unlinked(Config::default().jobs().handle_unlinked());
+        let torrent_rules = RuleUtils::get_torrent_rules(torrents, &base_rule, overrides, tracker_domain_utils, Category::HandleUnlinked);
+        torrents.iter().map(|torrent| torrent_rules[torrent.hash()].name().to_string()).collect()
+    }
+
+    #[test]
+    fn first_matching_override_wins() {
+        let overrides = get_overrides(json!([
+            { "name": "movies", "category": "movies", "action": "stop" },
+            { "name": "tagged", "tag": "keep", "protected": true },
+        ]));
+        let torrents = [
+            Torrent::from_test_fields(json!({ "hash": "a", "category": "movies", "tags": "keep" })),
+            Torrent::from_test_fields(json!({ "hash": "b", "category": "tv", "tags": "seed, keep" })),
+            Torrent::from_test_fields(json!({ "hash": "c", "category": "tv" })),
+        ];
+        assert_eq!(get_rule_names(&torrents, &overrides, &TrackerDomainUtils::default()), vec!["movies", "tagged", "default"]);
+    }
+
+    #[test]
+    fn override_without_matchers_never_matches() {
+        let overrides = get_overrides(json!([{ "name": "empty", "action": "delete" }]));
+        let torrents = [Torrent::from_test_fields(json!({ "hash": "a" }))];
+        assert_eq!(get_rule_names(&torrents, &overrides, &TrackerDomainUtils::default()), vec!["default"]);
+    }
+
+    #[test]
+    fn all_set_matchers_have_to_match() {
+        let overrides = get_overrides(json!([{ "name": "movies", "category": "movies", "path": "/data/torrents/movies" }]));
+        let torrents = [
+            Torrent::from_test_fields(json!({ "hash": "a", "category": "movies", "content_path": "/data/torrents/movies/movie" })),
+            Torrent::from_test_fields(json!({ "hash": "b", "category": "movies", "content_path": "/data/torrents/other/movie" })),
+        ];
+        assert_eq!(get_rule_names(&torrents, &overrides, &TrackerDomainUtils::default()), vec!["movies", "default"]);
+    }
+
+    #[test]
+    fn tracker_override_matches_subdomains_and_the_trackers_list() {
+        let overrides = get_overrides(json!([{ "name": "example", "tracker": "example.org" }]));
+        let torrents = [
+            Torrent::from_test_fields(json!({ "hash": "a", "tracker": "https://tracker.example.org/announce" })),
+            Torrent::from_test_fields(json!({ "hash": "b", "tracker": "" })),
+            Torrent::from_test_fields(json!({ "hash": "c", "tracker": "https://notexample.org/announce" })),
+        ];
+        // b has no working tracker, so its domain comes from its trackers list
+        let mut tracker_domain_utils = TrackerDomainUtils::default();
+        let trackers: Vec<Tracker> = serde_json::from_value(json!([{ "url": "https://tracker.example.org/announce", "status": 4, "msg": "unregistered" }])).unwrap();
+        tracker_domain_utils.add_tracker_domains("b", &trackers);
+        assert_eq!(get_rule_names(&torrents, &overrides, &tracker_domain_utils), vec!["example", "example", "default"]);
+    }
+
+    #[test]
+    fn protection_tag_matches_whole_tags() {
+        let base_rule = Rule::from_handle_unlinked(Config::default().jobs().handle_unlinked());
+        assert!(base_rule.is_protected("seed, protected-unlinked"));
+        assert!(base_rule.is_protected("protected-unlinked"));
+        assert!(!base_rule.is_protected("protected-unlinked-old, seed"));
+        assert!(!base_rule.is_protected(""));
+
+        let overrides = get_overrides(json!([{ "name": "untagged", "protection_tag": "" }]));
+        let untagged_rule = base_rule.with_override(&overrides[0]);
+        assert!(!untagged_rule.is_protected("seed, protected-unlinked"));
+        assert!(!untagged_rule.is_protected(""));
+    }
+
+    #[test]
+    fn group_rule_is_the_least_destructive() {
+        let base_rule = Rule::from_handle_unlinked(Config::default().jobs().handle_unlinked());
+        let overrides = get_overrides(json!([{ "name": "stop", "action": "stop" }, { "name": "delete", "action": "delete" }]));
+        let torrent_rules = HashMap::from([(String::from("a"), base_rule.with_override(&overrides[1])), (String::from("b"), base_rule.with_override(&overrides[0]))]);
+        let group_rule = RuleUtils::get_group_rule(&[String::from("a"), String::from("b")], &torrent_rules).unwrap();
+        assert_eq!(group_rule.name(), "stop");
+    }
+
+    #[test]
+    fn group_rule_fails_without_a_rule_of_a_member() {
+        assert!(RuleUtils::get_group_rule(&[String::from("a")], &HashMap::new()).is_err());
+        assert!(RuleUtils::get_group_rule(&[], &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{config::StateFilter, torrent_clients::enums::torrent_state::TorrentState};
+
+pub struct StateFilterUtils;
+
+impl StateFilterUtils {
+    /**
+     * True if the state is included (or include is empty) and not excluded
+     */
+    pub fn is_allowed(state: &TorrentState, state_filter: &StateFilter) -> bool {
+        (state_filter.include().is_empty() || state_filter.include().contains(state)) && !state_filter.exclude().contains(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn get_state_filter(state_filter: serde_json::Value) -> StateFilter {
+        serde_json::from_value(state_filter).unwrap()
+    }
+
+    #[test]
+    fn empty_filter_allows_every_state() {
+        let state_filter = get_state_filter(json!({}));
+        assert!(StateFilterUtils::is_allowed(&TorrentState::StalledUP, &state_filter));
+        assert!(StateFilterUtils::is_allowed(&TorrentState::Unknown, &state_filter));
+    }
+
+    #[test]
+    fn include_only_allows_its_states() {
+        let state_filter = get_state_filter(json!({ "include": ["stalledUP", "stoppedUP"] }));
+        assert!(StateFilterUtils::is_allowed(&TorrentState::StoppedUP, &state_filter));
+        assert!(!StateFilterUtils::is_allowed(&TorrentState::Uploading, &state_filter));
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let state_filter = get_state_filter(json!({ "include": ["stalledUP", "forcedUP"], "exclude": ["forcedUP"] }));
+        assert!(StateFilterUtils::is_allowed(&TorrentState::StalledUP, &state_filter));
+        assert!(!StateFilterUtils::is_allowed(&TorrentState::ForcedUP, &state_filter));
+    }
+
+    #[test]
+    fn exclude_without_include_allows_the_rest() {
+        let state_filter = get_state_filter(json!({ "exclude": ["checkingUP"] }));
+        assert!(!StateFilterUtils::is_allowed(&TorrentState::CheckingUP, &state_filter));
+        assert!(StateFilterUtils::is_allowed(&TorrentState::Uploading, &state_filter));
+    }
+
+    #[test]
+    fn unknown_state_fails_to_load() {
+        let state_filter: Result<StateFilter, serde_json::Error> = serde_json::from_value(json!({ "exclude": ["stalledUp"] }));
+        assert!(state_filter.is_err_and(|e| e.to_string().contains("Unknown torrent state 'stalledUp'")));
+    }
+}
@@ -1,33 +1,43 @@
+use std::collections::HashMap;
+
 use anyhow::Context;
-use chrono::{Duration, NaiveDate};
-use rusqlite::{Connection, params};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, Row, ToSql, params};
 
 use crate::{
-    jobs::enums::strike_type::StrikeType,
+    jobs::{
+        enums::{strike_policy_type::StrikePolicyType, strike_type::StrikeType},
+        models::job_schedule::JobSchedule,
+    },
     logger::enums::category::Category,
     trace,
-    utils::{date_utils::DateUtils, db_manager::Session},
-    warn,
+    utils::{date_utils::DateUtils, db_manager::DbManager},
 };
 
 #[derive(Clone)]
 pub struct StrikeRecord {
     id: i32,
-    strike_type: String,
     hash: String,
     strikes: i32,
     strike_days: i32,
-    last_strike_date: NaiveDate,
+    first_strike_at: DateTime<Utc>,
+    last_strike_at: DateTime<Utc>,
+    strike_times: Vec<DateTime<Utc>>,
+    missed_runs: i32,
 }
 
 impl StrikeRecord {
-    pub fn is_limit_reached(&self, required_strikes: i32, min_strike_days: i32) -> bool {
-        let today_local = DateUtils::get_current_local_naive_date();
-        let yesterday_local = today_local - Duration::days(1);
-        if self.last_strike_date == today_local || self.last_strike_date == yesterday_local {
-            self.strikes >= required_strikes && self.strike_days >= min_strike_days
-        } else {
-            false
+    pub fn is_limit_reached(&self, required_strikes: i32, min_strike_days: i32, strike_policy_type: &StrikePolicyType) -> bool {
+        let now = DateUtils::get_current_utc_datetime();
+        let days_since_last_strike = (DateUtils::get_strike_day(&now) - DateUtils::get_strike_day(&self.last_strike_at)).num_days();
+        match strike_policy_type {
+            StrikePolicyType::ConsecutiveDays => days_since_last_strike <= 1 && self.strikes >= required_strikes && self.strike_days >= min_strike_days,
+            StrikePolicyType::Tolerant => days_since_last_strike <= 2 && self.strikes >= required_strikes && self.strike_days >= min_strike_days,
+            StrikePolicyType::SlidingWindow { window_hours } => {
+                let window_start = now - Duration::hours(*window_hours);
+                self.strike_times.iter().filter(|strike_time| **strike_time >= window_start).count() as i32 >= required_strikes
+            }
+            StrikePolicyType::Continuous { min_hours } => self.last_strike_at - self.first_strike_at >= Duration::hours(*min_hours),
         }
     }
 
@@ -35,9 +45,6 @@ impl StrikeRecord {
     pub fn id(&self) -> &i32 {
         &self.id
     }
-    pub fn strike_type(&self) -> &str {
-        &self.strike_type
-    }
     pub fn hash(&self) -> &str {
         &self.hash
     }
@@ -47,73 +54,60 @@ impl StrikeRecord {
     pub fn strike_days(&self) -> &i32 {
         &self.strike_days
     }
-    pub fn last_strike_date(&self) -> &NaiveDate {
-        &self.last_strike_date
-    }
 }
 
+/**
+ * Runs start late by their own duration and failed runs are retried after an hour, so the continuous policy allows that much more than the schedule
+ */
+const CONTINUOUS_GAP_SLACK_MINUTES: i64 = 90;
+
 pub struct StrikeUtils {
-    conn: Connection,
+    db_manager: DbManager,
+    strike_policy_type: StrikePolicyType,
+    job_schedule_option: Option<JobSchedule>,
 }
 
 impl StrikeUtils {
-    pub fn new() -> Result<Self, anyhow::Error> {
-        let session = Session::new()?;
-        let conn = session.into_conn().ok_or(anyhow::anyhow!("Failed to get conn from session"))?;
-        Ok(Self { conn })
+    pub fn new(db_manager: DbManager, strike_policy_type: StrikePolicyType) -> Self {
+        Self {
+            db_manager,
+            strike_policy_type,
+            job_schedule_option: None,
+        }
     }
 
     /**
-     * Get strikes
+     * Only used by continuous, a strike later than the next run of the schedule after the last strike breaks the continuity
      */
-    pub fn get_strikes(&mut self, strike_type: &StrikeType, hashes: Option<Vec<String>>) -> Result<Vec<StrikeRecord>, anyhow::Error> {
-        // Build statement from sql query
-        let mut stmt = match &hashes {
-            Some(hashes) => {
-                let placeholders = std::iter::repeat_n("?", hashes.len()).collect::<Vec<&str>>().join(",");
-                let sql = format!("SELECT id, strike_type, hash, strikes, strike_days, last_strike_date FROM strikes WHERE strike_type = ?1 AND hash IN ({})", placeholders);
-                self.conn.prepare(sql.as_str()).context("Failed to prepare get_strikes select")?
-            }
-            None => {
-                let sql = "SELECT id, strike_type, hash, strikes, strike_days, last_strike_date FROM strikes WHERE strike_type = ?1";
-                self.conn.prepare(sql).context("Failed to prepare get_strikes select")?
-            }
-        };
+    pub fn with_job_schedule(mut self, job_schedule_option: Option<&JobSchedule>) -> Self {
+        self.job_schedule_option = job_schedule_option.cloned();
+        self
+    }
 
-        let rows = match hashes {
-            // Execute query based on if hashes are passed or not
-            Some(hashes) => {
-                let mut params: Vec<String> = vec![strike_type.to_string()];
-                params.extend(hashes);
-                let params: Vec<&dyn rusqlite::ToSql> = params.iter().map(|hash| hash as &dyn rusqlite::ToSql).collect();
-                stmt.query(params.as_slice()).context("Failed to execute query to get strikes")?
-            }
-            None => stmt.query(params![strike_type.to_string()]).context("Failed to execute query to get strikes")?,
-        }
-        // Map results
-        .mapped(|row| {
-            let last_strike_date_str: String = row.get(5)?;
-            let last_strike_date = DateUtils::parse_naive_date_from_str(&last_strike_date_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
-
-            Ok(StrikeRecord {
-                id: row.get(0)?,
-                strike_type: row.get(1)?,
-                hash: row.get(2)?,
-                strikes: row.get(3)?,
-                strike_days: row.get(4)?,
-                last_strike_date,
-            })
-        });
-
-        let mut strike_records: Vec<StrikeRecord> = Vec::new();
-        for row in rows {
-            match row {
-                Ok(strike_record) => strike_records.push(strike_record),
-                Err(e) => {
-                    anyhow::bail!(e);
-                }
-            }
-        }
+    pub fn strike_policy_type(&self) -> &StrikePolicyType {
+        &self.strike_policy_type
+    }
+
+    /**
+     * Get strikes, only of the passed hashes if set
+     * The hashes are passed as 1 json array, so there is no variable limit of sqlite and nothing is written
+     */
+    pub fn get_strikes(&mut self, strike_type: &StrikeType, hashes: Option<&[String]>) -> Result<Vec<StrikeRecord>, anyhow::Error> {
+        let conn = self.db_manager.conn()?;
+
+        let strike_records = match hashes {
+            Some(hashes) => StrikeUtils::query_strikes(
+                &conn,
+                "SELECT id, hash, strikes, strike_days, first_strike_at, last_strike_at, strike_times, missed_runs
+                    FROM strikes WHERE strike_type = ?1 AND hash IN (SELECT value FROM json_each(?2))",
+                params![strike_type.to_string(), serde_json::to_string(hashes).context("Failed to convert hashes to json")?],
+            )?,
+            None => StrikeUtils::query_strikes(
+                &conn,
+                "SELECT id, hash, strikes, strike_days, first_strike_at, last_strike_at, strike_times, missed_runs FROM strikes WHERE strike_type = ?1",
+                params![strike_type.to_string()],
+            )?,
+        };
 
         Ok(strike_records)
     }
@@ -121,69 +115,80 @@ impl StrikeUtils {
     /**
      * Strike multiple
      */
-    pub fn strike(&mut self, strike_type: &StrikeType, hashes: Vec<String>) -> Result<(), anyhow::Error> {
+    pub fn strike(&mut self, strike_type: &StrikeType, hashes: &[String]) -> Result<(), anyhow::Error> {
         // Get current strike records
-        let strike_records = self.get_strikes(strike_type, Some(hashes.clone())).context("Failed to get strike types")?;
+        let strike_records: HashMap<String, StrikeRecord> = self
+            .get_strikes(strike_type, Some(hashes))
+            .context("Failed to get strike types")?
+            .into_iter()
+            .map(|strike_record| (strike_record.hash.clone(), strike_record))
+            .collect();
 
         // Open transaction
-        let tx = self.conn.transaction().context("Failed to get transaction")?;
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
 
         // Handle hashes
+        let now = DateUtils::get_current_utc_datetime();
+        let strike_day = DateUtils::get_strike_day(&now);
         for hash in hashes {
-            // Try to get the strike record of the hash
-            let strike_records_for_hash: Vec<StrikeRecord> = strike_records
-                .clone()
-                .into_iter()
-                .filter(|strike_record| strike_record.strike_type == strike_type.to_string() && strike_record.hash == hash)
-                .collect();
-            // This should never be the case due to the unique contraint but you never know
-            if strike_records_for_hash.len() > 1 {
-                warn!(
-                    Category::Striker,
-                    "Hash {} for strike type {} is {} times in the db. This should be impossible",
-                    hash,
-                    strike_type.to_string(),
-                    strike_records_for_hash.len(),
-                );
-            }
             // Check for strike record of the hash
-            match strike_records_for_hash.first() {
-                // If the strike record of the hash exists, handle multiple scenarios
+            match strike_records.get(hash) {
+                // If the strike record of the hash exists, continue or reset it depending on the strike policy
                 Some(strike_record) => {
-                    let today_local = DateUtils::get_current_local_naive_date();
-                    let yesterday_local = today_local - Duration::days(1);
-                    // If the strike record was last striked yesterday, increase everything
-                    if strike_record.last_strike_date == yesterday_local {
-                        tx.execute(
-                            "UPDATE strikes SET strikes = strikes + 1, strike_days = strike_days + 1, last_strike_date = ?1 WHERE strike_type = ?2 AND hash = ?3",
-                            params![DateUtils::convert_naive_date_to_string(today_local), strike_type.to_string(), hash],
-                        )
-                        .context("Failed to insert new strike")?;
-                        trace!(Category::Striker, "Hash {} ({}) was last striked yesterday, strikes and strike days have been increased", hash, strike_type.to_string(),);
-                    }
-                    // If the strike record was last striked today, just increase strikes
-                    else if strike_record.last_strike_date == today_local {
-                        tx.execute("UPDATE strikes SET strikes = strikes + 1 WHERE strike_type = ?1 AND hash = ?2", params![strike_type.to_string(), hash])
+                    let days_since_last_strike = (strike_day - DateUtils::get_strike_day(&strike_record.last_strike_at)).num_days();
+                    let is_continued = match (&self.strike_policy_type, &self.job_schedule_option) {
+                        (StrikePolicyType::ConsecutiveDays, _) => days_since_last_strike <= 1,
+                        (StrikePolicyType::Tolerant, _) => days_since_last_strike <= 1 || (days_since_last_strike == 2 && strike_record.missed_runs == 0),
+                        (StrikePolicyType::Continuous { .. }, Some(job_schedule)) => now <= job_schedule.get_latest_next_run(&strike_record.last_strike_at)? + Duration::minutes(CONTINUOUS_GAP_SLACK_MINUTES),
+                        (StrikePolicyType::SlidingWindow { .. }, _) | (StrikePolicyType::Continuous { .. }, None) => true,
+                    };
+                    if is_continued {
+                        let strike_days = match days_since_last_strike {
+                            0 => strike_record.strike_days,
+                            _ => strike_record.strike_days + 1,
+                        };
+                        let mut strike_times: Vec<DateTime<Utc>> = match self.strike_policy_type {
+                            StrikePolicyType::SlidingWindow { window_hours } => strike_record.strike_times.iter().filter(|strike_time| **strike_time >= now - Duration::hours(window_hours)).cloned().collect(),
+                            _ => Vec::new(),
+                        };
+                        strike_times.push(now);
+                        tx.prepare_cached("UPDATE strikes SET strikes = strikes + 1, strike_days = ?1, last_strike_at = ?2, strike_times = ?3, missed_runs = 0 WHERE strike_type = ?4 AND hash = ?5")?
+                            .execute(params![
+                                strike_days,
+                                DateUtils::convert_utc_datetime_to_string(&now),
+                                StrikeUtils::convert_strike_times_to_string(&strike_times),
+                                strike_type.to_string(),
+                                hash
+                            ])
                             .context("Failed to insert new strike")?;
-                        trace!(Category::Striker, "Hash {} ({}) was last striked today, strikes have been increased", hash, strike_type.to_string());
+                        trace!(
+                            Category::Striker,
+                            "Hash {} ({}) was last striked {} days ago, strikes have been increased",
+                            hash,
+                            strike_type.to_string(),
+                            days_since_last_strike
+                        );
                     }
-                    // If the strike record was not striked today or yesterday, reset it
+                    // The strike record was not striked recently enough, reset it
                     else {
-                        tx.execute(
-                            "UPDATE strikes SET strikes = 1, strike_days = 1, last_strike_date = ?1 WHERE strike_type = ?2 AND hash = ?3",
-                            params![DateUtils::convert_naive_date_to_string(today_local), strike_type.to_string(), hash],
-                        )
-                        .context("Failed to insert new strike")?;
-                        trace!(Category::Striker, "Hash {} ({}) was not striked today or yesterday, everything has been reset", hash, strike_type.to_string(),);
+                        tx.prepare_cached("UPDATE strikes SET strikes = 1, strike_days = 1, first_strike_at = ?1, last_strike_at = ?1, strike_times = ?1, missed_runs = 0 WHERE strike_type = ?2 AND hash = ?3")?
+                            .execute(params![DateUtils::convert_utc_datetime_to_string(&now), strike_type.to_string(), hash])
+                            .context("Failed to insert new strike")?;
+                        trace!(
+                            Category::Striker,
+                            "Hash {} ({}) was last striked {} days ago, everything has been reset",
+                            hash,
+                            strike_type.to_string(),
+                            days_since_last_strike
+                        );
                     }
                 }
                 // If the strike record of the hash doesn't exist, strike for the first time
                 None => {
-                    tx.execute(
-                        "INSERT INTO strikes (strike_type, hash, strikes, strike_days, last_strike_date) VALUES (?1, ?2, ?3, ?4, ?5)",
-                        params![strike_type.to_string(), hash, 1, 1, DateUtils::convert_naive_date_to_string(DateUtils::get_current_local_naive_date())],
-                    )
-                    .context("Failed to insert new strike")?;
+                    tx.prepare_cached("INSERT INTO strikes (strike_type, hash, strikes, strike_days, first_strike_at, last_strike_at, strike_times, missed_runs) VALUES (?1, ?2, 1, 1, ?3, ?3, ?3, 0)")?
+                        .execute(params![strike_type.to_string(), hash, DateUtils::convert_utc_datetime_to_string(&now)])
+                        .context("Failed to insert new strike")?;
                     trace!(Category::Striker, "Hash {} ({}) has been striked for the first time", hash, strike_type.to_string());
                 }
             }
@@ -195,18 +200,387 @@ impl StrikeUtils {
     }
 
     /**
-     * Delete strikes
+     * Reset strikes of hashes that don't meet criteria anymore
+     * The tolerant strike policy only counts the first missed run in a row, the strikes are deleted on the second one
+     * The sliding window keeps its strike times until they aged out of the window, the strikes are deleted once none is left
      */
-    pub fn delete(&mut self, strike_type: StrikeType, hashes: Vec<String>) -> Result<(), anyhow::Error> {
-        let placeholders = std::iter::repeat_n("?", hashes.len()).collect::<Vec<&str>>().join(",");
-        let sql = format!("DELETE FROM strikes WHERE strike_type = ?1 AND hash IN ({})", placeholders);
+    pub fn reset(&mut self, strike_type: StrikeType, hashes: &[String]) -> Result<(), anyhow::Error> {
+        if matches!(self.strike_policy_type, StrikePolicyType::ConsecutiveDays | StrikePolicyType::Continuous { .. }) {
+            return self.delete(strike_type, hashes);
+        }
 
-        let mut params: Vec<String> = vec![strike_type.to_string()];
-        params.extend(hashes);
-        let params: Vec<&dyn rusqlite::ToSql> = params.iter().map(|hash| hash as &dyn rusqlite::ToSql).collect();
+        let strike_records = self.get_strikes(&strike_type, Some(hashes)).context("Failed to get strikes to reset")?;
+        let now = DateUtils::get_current_utc_datetime();
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for strike_record in strike_records {
+            if let StrikePolicyType::SlidingWindow { window_hours } = self.strike_policy_type {
+                let strike_times: Vec<DateTime<Utc>> = strike_record.strike_times.iter().filter(|strike_time| **strike_time >= now - Duration::hours(window_hours)).cloned().collect();
+                if strike_times.is_empty() {
+                    tx.prepare_cached("DELETE FROM strikes WHERE id = ?1")?.execute(params![strike_record.id]).context("Failed to delete strikes")?;
+                } else {
+                    tx.prepare_cached("UPDATE strikes SET strike_times = ?1 WHERE id = ?2")?
+                        .execute(params![StrikeUtils::convert_strike_times_to_string(&strike_times), strike_record.id])
+                        .context("Failed to age out strike times")?;
+                    trace!(
+                        Category::Striker,
+                        "Hash {} ({}) missed a run, {} strikes are left in the window",
+                        strike_record.hash,
+                        strike_type.to_string(),
+                        strike_times.len()
+                    );
+                }
+            } else if strike_record.missed_runs == 0 {
+                tx.prepare_cached("UPDATE strikes SET missed_runs = 1 WHERE id = ?1")?
+                    .execute(params![strike_record.id])
+                    .context("Failed to count missed run")?;
+                trace!(Category::Striker, "Hash {} ({}) missed a run, strikes are kept once", strike_record.hash, strike_type.to_string());
+            } else {
+                tx.prepare_cached("DELETE FROM strikes WHERE id = ?1")?.execute(params![strike_record.id]).context("Failed to delete strikes")?;
+            }
+        }
+        tx.commit().context("Failed to commit missed runs")?;
 
-        self.conn.execute(&sql, params.as_slice()).context("Failed to delete strikes")?;
+        Ok(())
+    }
+
+    /**
+     * Carry strikes of hashes that can't be checked in this run (e.g. their tracker is down) over to now without striking them
+     * The paused time neither breaks the continuity of the strike days nor counts as continuous time, the strike times of the sliding window age out as usual
+     */
+    pub fn pause(&mut self, strike_type: StrikeType, hashes: &[String]) -> Result<(), anyhow::Error> {
+        if matches!(self.strike_policy_type, StrikePolicyType::SlidingWindow { .. }) {
+            return Ok(());
+        }
+
+        let strike_records = self.get_strikes(&strike_type, Some(hashes)).context("Failed to get strikes to pause")?;
+        let now = DateUtils::get_current_utc_datetime();
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for strike_record in strike_records {
+            let first_strike_at = match self.strike_policy_type {
+                StrikePolicyType::Continuous { .. } => strike_record.first_strike_at + (now - strike_record.last_strike_at),
+                _ => strike_record.first_strike_at,
+            };
+            tx.prepare_cached("UPDATE strikes SET first_strike_at = ?1, last_strike_at = ?2 WHERE id = ?3")?
+                .execute(params![DateUtils::convert_utc_datetime_to_string(&first_strike_at), DateUtils::convert_utc_datetime_to_string(&now), strike_record.id])
+                .context("Failed to pause strikes")?;
+            trace!(Category::Striker, "Hash {} ({}) couldn't be checked, strikes have been paused", strike_record.hash, strike_type.to_string());
+        }
+        tx.commit().context("Failed to commit paused strikes")?;
 
         Ok(())
     }
+
+    /**
+     * Delete strikes
+     * The hashes are passed as 1 json array like in get_strikes
+     */
+    pub fn delete(&mut self, strike_type: StrikeType, hashes: &[String]) -> Result<(), anyhow::Error> {
+        let conn = self.db_manager.conn()?;
+        conn.execute(
+            "DELETE FROM strikes WHERE strike_type = ?1 AND hash IN (SELECT value FROM json_each(?2))",
+            params![strike_type.to_string(), serde_json::to_string(hashes).context("Failed to convert hashes to json")?],
+        )
+        .context("Failed to delete strikes")?;
+
+        Ok(())
+    }
+
+    fn query_strikes(conn: &Connection, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<StrikeRecord>, anyhow::Error> {
+        let mut stmt = conn.prepare(sql).context("Failed to prepare get_strikes select")?;
+        let strike_records = stmt
+            .query_map(params, StrikeUtils::map_strike_record)
+            .context("Failed to execute query to get strikes")?
+            .collect::<Result<Vec<StrikeRecord>, rusqlite::Error>>()
+            .context("Failed to map strikes")?;
+        Ok(strike_records)
+    }
+
+    fn map_strike_record(row: &Row) -> Result<StrikeRecord, rusqlite::Error> {
+        let first_strike_at_str: String = row.get(4)?;
+        let first_strike_at = DateUtils::parse_utc_datetime_from_str(&first_strike_at_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+        let last_strike_at_str: String = row.get(5)?;
+        let last_strike_at = DateUtils::parse_utc_datetime_from_str(&last_strike_at_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+        let strike_times_str: String = row.get(6)?;
+        let mut strike_times: Vec<DateTime<Utc>> = Vec::new();
+        for strike_time_str in strike_times_str.lines() {
+            strike_times.push(DateUtils::parse_utc_datetime_from_str(strike_time_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))?);
+        }
+
+        Ok(StrikeRecord {
+            id: row.get(0)?,
+            hash: row.get(1)?,
+            strikes: row.get(2)?,
+            strike_days: row.get(3)?,
+            first_strike_at,
+            last_strike_at,
+            strike_times,
+            missed_runs: row.get(7)?,
+        })
+    }
+
+    fn convert_strike_times_to_string(strike_times: &[DateTime<Utc>]) -> String {
+        strike_times.iter().map(DateUtils::convert_utc_datetime_to_string).collect::<Vec<String>>().join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeZone};
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+    use crate::{config::Schedule, jobs::models::quiet_windows::QuietWindows};
+
+    const HASH: &str = "0123456789abcdef";
+
+    fn get_strike_utils(strike_policy_type: StrikePolicyType) -> StrikeUtils {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        StrikeUtils::new(DbManager::open_in_memory().unwrap(), strike_policy_type)
+    }
+
+    /**
+     * Noon of the strike day that is days_ago before today, so DST and the time of the test run don't matter
+     */
+    fn get_days_ago(days_ago: i64) -> DateTime<Utc> {
+        let strike_day = DateUtils::get_strike_day(&DateUtils::get_current_utc_datetime()) - Duration::days(days_ago);
+        Berlin.from_local_datetime(&strike_day.and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap())).single().unwrap().with_timezone(&Utc)
+    }
+
+    fn strike(strike_utils: &mut StrikeUtils) {
+        strike_utils.strike(&StrikeType::HandleUnlinked, &[HASH.to_string()]).unwrap();
+    }
+
+    fn reset(strike_utils: &mut StrikeUtils) {
+        strike_utils.reset(StrikeType::HandleUnlinked, &[HASH.to_string()]).unwrap();
+    }
+
+    fn get_strike_record(strike_utils: &mut StrikeUtils) -> Option<StrikeRecord> {
+        strike_utils.get_strikes(&StrikeType::HandleUnlinked, None).unwrap().into_iter().next()
+    }
+
+    fn set_strike_record(strike_utils: &StrikeUtils, first_strike_at: &DateTime<Utc>, last_strike_at: &DateTime<Utc>, strike_times: &[DateTime<Utc>], missed_runs: i32) {
+        strike_utils
+            .db_manager
+            .conn()
+            .unwrap()
+            .execute(
+                "UPDATE strikes SET first_strike_at = ?1, last_strike_at = ?2, strike_times = ?3, missed_runs = ?4 WHERE hash = ?5",
+                params![
+                    DateUtils::convert_utc_datetime_to_string(first_strike_at),
+                    DateUtils::convert_utc_datetime_to_string(last_strike_at),
+                    StrikeUtils::convert_strike_times_to_string(strike_times),
+                    missed_runs,
+                    HASH
+                ],
+            )
+            .unwrap();
+    }
+
+    fn set_last_strike(strike_utils: &StrikeUtils, last_strike_at: &DateTime<Utc>, missed_runs: i32) {
+        set_strike_record(strike_utils, last_strike_at, last_strike_at, &[*last_strike_at], missed_runs);
+    }
+
+    #[test]
+    fn get_and_delete_strikes_of_more_hashes_than_sqlite_variables() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::ConsecutiveDays);
+        let hashes: Vec<String> = (0..40_000).map(|i| format!("{:040x}", i)).collect();
+        strike_utils.strike(&StrikeType::HandleUnlinked, &hashes[..20_000]).unwrap();
+
+        let strike_records = strike_utils.get_strikes(&StrikeType::HandleUnlinked, Some(&hashes)).unwrap();
+        assert_eq!(strike_records.len(), 20_000);
+        let strike_records = strike_utils.get_strikes(&StrikeType::HandleUnlinked, Some(&hashes[19_999..])).unwrap();
+        assert_eq!(strike_records.len(), 1);
+        assert!(strike_utils.get_strikes(&StrikeType::HandleUnregistered, Some(&hashes)).unwrap().is_empty());
+
+        strike_utils.delete(StrikeType::HandleUnlinked, &hashes[10_000..]).unwrap();
+        let strike_records = strike_utils.get_strikes(&StrikeType::HandleUnlinked, Some(&hashes)).unwrap();
+        assert_eq!(strike_records.len(), 10_000);
+        assert!(strike_records.iter().all(|strike_record| strike_record.hash < hashes[10_000]));
+    }
+
+    #[test]
+    fn consecutive_days_continues_on_the_next_day() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::ConsecutiveDays);
+        strike(&mut strike_utils);
+        set_last_strike(&strike_utils, &get_days_ago(1), 0);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 2);
+        assert_eq!(strike_record.strike_days, 2);
+        assert!(strike_record.is_limit_reached(2, 2, strike_utils.strike_policy_type()));
+    }
+
+    #[test]
+    fn consecutive_days_counts_a_strike_day_once() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::ConsecutiveDays);
+        strike(&mut strike_utils);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 2);
+        assert_eq!(strike_record.strike_days, 1);
+        assert!(!strike_record.is_limit_reached(2, 2, strike_utils.strike_policy_type()));
+    }
+
+    #[test]
+    fn consecutive_days_resets_after_a_missed_day() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::ConsecutiveDays);
+        strike(&mut strike_utils);
+        set_last_strike(&strike_utils, &get_days_ago(2), 0);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 1);
+        assert_eq!(strike_record.strike_days, 1);
+    }
+
+    #[test]
+    fn consecutive_days_reset_deletes_strikes() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::ConsecutiveDays);
+        strike(&mut strike_utils);
+        reset(&mut strike_utils);
+
+        assert!(get_strike_record(&mut strike_utils).is_none());
+    }
+
+    #[test]
+    fn tolerant_continues_after_1_missed_day() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Tolerant);
+        strike(&mut strike_utils);
+        set_last_strike(&strike_utils, &get_days_ago(2), 0);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 2);
+        assert_eq!(strike_record.strike_days, 2);
+    }
+
+    #[test]
+    fn tolerant_resets_after_a_missed_day_and_a_missed_run() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Tolerant);
+        strike(&mut strike_utils);
+        set_last_strike(&strike_utils, &get_days_ago(2), 1);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 1);
+        assert_eq!(strike_record.missed_runs, 0);
+    }
+
+    #[test]
+    fn tolerant_resets_after_2_missed_days() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Tolerant);
+        strike(&mut strike_utils);
+        set_last_strike(&strike_utils, &get_days_ago(3), 0);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 1);
+        assert_eq!(strike_record.strike_days, 1);
+    }
+
+    #[test]
+    fn tolerant_reset_keeps_strikes_for_1_missed_run() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Tolerant);
+        strike(&mut strike_utils);
+        reset(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 1);
+        assert_eq!(strike_record.missed_runs, 1);
+
+        // Striking again forgives the missed run
+        strike(&mut strike_utils);
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 2);
+        assert_eq!(strike_record.missed_runs, 0);
+    }
+
+    #[test]
+    fn tolerant_reset_deletes_strikes_on_the_second_missed_run() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Tolerant);
+        strike(&mut strike_utils);
+        reset(&mut strike_utils);
+        reset(&mut strike_utils);
+
+        assert!(get_strike_record(&mut strike_utils).is_none());
+    }
+
+    #[test]
+    fn sliding_window_drops_strike_times_outside_the_window() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::SlidingWindow { window_hours: 24 });
+        let now = DateUtils::get_current_utc_datetime();
+        strike(&mut strike_utils);
+        set_strike_record(&strike_utils, &(now - Duration::hours(48)), &(now - Duration::hours(1)), &[now - Duration::hours(48), now - Duration::hours(1)], 0);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strike_times.len(), 2);
+        assert!(strike_record.is_limit_reached(2, 0, strike_utils.strike_policy_type()));
+        assert!(!strike_record.is_limit_reached(3, 0, strike_utils.strike_policy_type()));
+    }
+
+    #[test]
+    fn sliding_window_reset_keeps_strikes_in_the_window() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::SlidingWindow { window_hours: 24 });
+        let now = DateUtils::get_current_utc_datetime();
+        strike(&mut strike_utils);
+        set_strike_record(&strike_utils, &(now - Duration::hours(48)), &(now - Duration::hours(1)), &[now - Duration::hours(48), now - Duration::hours(1)], 0);
+        reset(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strike_times.len(), 1);
+    }
+
+    #[test]
+    fn sliding_window_reset_deletes_strikes_once_the_window_is_empty() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::SlidingWindow { window_hours: 24 });
+        let now = DateUtils::get_current_utc_datetime();
+        strike(&mut strike_utils);
+        set_last_strike(&strike_utils, &(now - Duration::hours(25)), 0);
+        reset(&mut strike_utils);
+
+        assert!(get_strike_record(&mut strike_utils).is_none());
+    }
+
+    #[test]
+    fn continuous_continues_after_missed_days() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Continuous { min_hours: 24 });
+        strike(&mut strike_utils);
+        set_strike_record(&strike_utils, &get_days_ago(3), &get_days_ago(2), &[], 0);
+        strike(&mut strike_utils);
+
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 2);
+        assert!(strike_record.is_limit_reached(0, 0, strike_utils.strike_policy_type()));
+    }
+
+    #[test]
+    fn continuous_breaks_after_a_gap_longer_than_the_schedule() {
+        let job_schedule = JobSchedule::from_config(6, 6, &Schedule::default(), QuietWindows::default()).unwrap();
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Continuous { min_hours: 24 }).with_job_schedule(job_schedule.as_ref());
+        let now = DateUtils::get_current_utc_datetime();
+        strike(&mut strike_utils);
+        set_strike_record(&strike_utils, &(now - Duration::hours(30)), &(now - Duration::hours(6)), &[], 0);
+        strike(&mut strike_utils);
+        assert_eq!(get_strike_record(&mut strike_utils).unwrap().strikes, 2);
+
+        set_strike_record(&strike_utils, &(now - Duration::hours(30)), &(now - Duration::hours(12)), &[], 0);
+        strike(&mut strike_utils);
+        let strike_record = get_strike_record(&mut strike_utils).unwrap();
+        assert_eq!(strike_record.strikes, 1);
+        assert!(!strike_record.is_limit_reached(0, 0, strike_utils.strike_policy_type()));
+    }
+
+    #[test]
+    fn continuous_reset_deletes_strikes() {
+        let mut strike_utils = get_strike_utils(StrikePolicyType::Continuous { min_hours: 24 });
+        strike(&mut strike_utils);
+        reset(&mut strike_utils);
+
+        assert!(get_strike_record(&mut strike_utils).is_none());
+    }
 }
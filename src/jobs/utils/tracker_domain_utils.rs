@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+
+use crate::torrent_clients::{
+    models::{torrent::Torrent, tracker::Tracker},
+    torrent_manager::TorrentManager,
+};
+
+#[derive(Default)]
+pub struct TrackerDomainUtils {
+    tracker_domains: HashMap<String, Vec<String>>,
+}
+
+impl TrackerDomainUtils {
+    /**
+     * Resolve the tracker domains of the torrents from their trackers lists, they are used by rules, storage quotas, hit-and-run policies and recorded with the actions
+     * The tracker of a torrent is empty without a working tracker (e.g. stopped or unregistered), so only those are requested
+     */
+    pub async fn resolve_tracker_domains(&mut self, torrent_manager: &TorrentManager, torrents: &[Torrent]) -> Result<(), anyhow::Error> {
+        for torrent in torrents {
+            if self.tracker_domains.contains_key(torrent.hash()) || !torrent.tracker_domain().is_empty() {
+                continue;
+            }
+            let trackers: Vec<Tracker> = torrent_manager
+                .get_torrent_trackers(torrent.hash())
+                .await
+                .context(format!("Failed to get trackers for torrent: ({}) {}", torrent.hash(), torrent.name()))?;
+            self.add_tracker_domains(torrent.hash(), &trackers);
+        }
+        Ok(())
+    }
+
+    /**
+     * Add the tracker domains of a torrent from its already known trackers list, DHT, PeX and LSD are ignored
+     */
+    pub fn add_tracker_domains(&mut self, hash: &str, trackers: &[Tracker]) {
+        let domains: Vec<String> = trackers.iter().map(|tracker| tracker.domain()).filter(|domain| !domain.is_empty()).collect();
+        self.tracker_domains.insert(hash.to_string(), domains);
+    }
+
+    /**
+     * Tracker domain of the torrent that is recorded with its actions, empty if it has none
+     */
+    pub fn get_tracker_domain(&self, torrent: &Torrent) -> String {
+        self.get_tracker_domains(torrent).into_iter().next().unwrap_or_default()
+    }
+
+    /**
+     * Resolved tracker domains of the torrent, falls back to the domain of its working tracker
+     */
+    pub fn get_tracker_domains(&self, torrent: &Torrent) -> Vec<String> {
+        match self.tracker_domains.get(torrent.hash()) {
+            Some(tracker_domains) => tracker_domains.clone(),
+            None => vec![torrent.tracker_domain()].into_iter().filter(|domain| !domain.is_empty()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn working_tracker_is_used_without_trackers_list() {
+        let tracker_domain_utils = TrackerDomainUtils::default();
+        let torrent = Torrent::from_test_fields(json!({ "hash": "a", "tracker": "https://tracker.example.org/announce" }));
+        assert_eq!(tracker_domain_utils.get_tracker_domain(&torrent), "tracker.example.org");
+        let torrent = Torrent::from_test_fields(json!({ "hash": "b", "tracker": "" }));
+        assert_eq!(tracker_domain_utils.get_tracker_domain(&torrent), "");
+    }
+
+    #[test]
+    fn trackers_list_is_used_without_working_tracker() {
+        let mut tracker_domain_utils = TrackerDomainUtils::default();
+        let trackers: Vec<Tracker> = serde_json::from_value(json!([
+            { "url": "** [DHT] **", "status": 0, "msg": "" },
+            { "url": "https://tracker.example.org/announce", "status": 4, "msg": "unregistered" },
+        ]))
+        .unwrap();
+        tracker_domain_utils.add_tracker_domains("a", &trackers);
+        let torrent = Torrent::from_test_fields(json!({ "hash": "a", "tracker": "" }));
+        assert_eq!(tracker_domain_utils.get_tracker_domains(&torrent), vec!["tracker.example.org"]);
+        assert_eq!(tracker_domain_utils.get_tracker_domain(&torrent), "tracker.example.org");
+    }
+}
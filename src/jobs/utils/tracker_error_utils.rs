@@ -0,0 +1,158 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+
+use crate::{
+    logger::enums::category::Category,
+    trace,
+    utils::{date_utils::DateUtils, db_manager::DbManager},
+};
+
+pub struct TrackerErrorRecord {
+    id: i64,
+    hash: String,
+    domain: String,
+    msg: String,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    notified_count: i64,
+}
+
+impl TrackerErrorRecord {
+    /* Getter */
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+    pub fn first_seen(&self) -> &DateTime<Utc> {
+        &self.first_seen
+    }
+    pub fn notified_count(&self) -> &i64 {
+        &self.notified_count
+    }
+
+    /**
+     * Days between the first and the last time the tracker was seen not working
+     */
+    pub fn not_working_days(&self) -> f64 {
+        (self.last_seen - self.first_seen).num_minutes() as f64 / 60.0 / 24.0
+    }
+}
+
+pub struct TrackerErrorUtils {
+    db_manager: DbManager,
+    category: Category,
+}
+
+impl TrackerErrorUtils {
+    pub fn new(db_manager: DbManager, category: Category) -> Self {
+        Self { db_manager, category }
+    }
+
+    /**
+     * Record trackers that are not working in this run, first_seen is kept for trackers that were already not working
+     * tracker_errors: HashMap<(torrent_hash, domain), msg>
+     */
+    pub fn record(&mut self, tracker_errors: &HashMap<(String, String), String>) -> Result<(), anyhow::Error> {
+        let now_str = DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime());
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for ((hash, domain), msg) in tracker_errors {
+            tx.prepare_cached(
+                "INSERT INTO tracker_errors (hash, domain, msg, first_seen, last_seen) VALUES (?1, ?2, ?3, ?4, ?4)
+                    ON CONFLICT (hash, domain) DO UPDATE SET msg = excluded.msg, last_seen = excluded.last_seen",
+            )?
+            .execute(params![hash, domain, msg, now_str])
+            .context("Failed to insert or update tracker error")?;
+            trace!(self.category, "Recorded tracker error of {} for {}: {}", domain, hash, msg);
+        }
+        tx.commit().context("Failed to commit tracker errors")?;
+
+        Ok(())
+    }
+
+    /**
+     * Delete the history of every tracker that isn't in keep, e.g. because it is working again or the torrent is gone
+     * keep: HashSet<(torrent_hash, domain)>
+     */
+    pub fn delete_resolved(&mut self, keep: &HashSet<(String, String)>) -> Result<usize, anyhow::Error> {
+        let tracker_error_records = self.get_tracker_errors()?;
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        let mut deleted_count: usize = 0;
+        for tracker_error_record in tracker_error_records {
+            if !keep.contains(&(tracker_error_record.hash.clone(), tracker_error_record.domain.clone())) {
+                tx.prepare_cached("DELETE FROM tracker_errors WHERE id = ?1")?
+                    .execute(params![tracker_error_record.id])
+                    .context("Failed to delete tracker error")?;
+                deleted_count += 1;
+            }
+        }
+        tx.commit().context("Failed to commit deleted tracker errors")?;
+
+        Ok(deleted_count)
+    }
+
+    /**
+     * Remember that the tracker errors of a domain were notified, together with the count of torrents they were notified for
+     */
+    pub fn set_notified(&mut self, tracker_error_records: &[&TrackerErrorRecord]) -> Result<(), anyhow::Error> {
+        let now_str = DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime());
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for tracker_error_record in tracker_error_records {
+            tx.prepare_cached("UPDATE tracker_errors SET notified_at = ?1, notified_count = ?2 WHERE id = ?3")?
+                .execute(params![now_str, tracker_error_records.len() as i64, tracker_error_record.id])
+                .context("Failed to set tracker error notified")?;
+        }
+        tx.commit().context("Failed to commit notified tracker errors")?;
+
+        Ok(())
+    }
+
+    /**
+     * Get all recorded tracker errors
+     */
+    pub fn get_tracker_errors(&mut self) -> Result<Vec<TrackerErrorRecord>, anyhow::Error> {
+        let conn = self.db_manager.conn()?;
+        let mut stmt = conn
+            .prepare("SELECT id, hash, domain, msg, first_seen, last_seen, notified_count FROM tracker_errors ORDER BY domain, first_seen")
+            .context("Failed to prepare get_tracker_errors select")?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                let first_seen_str: String = row.get(4)?;
+                let first_seen = DateUtils::parse_utc_datetime_from_str(&first_seen_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+                let last_seen_str: String = row.get(5)?;
+                let last_seen = DateUtils::parse_utc_datetime_from_str(&last_seen_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+
+                Ok(TrackerErrorRecord {
+                    id: row.get(0)?,
+                    hash: row.get(1)?,
+                    domain: row.get(2)?,
+                    msg: row.get(3)?,
+                    first_seen,
+                    last_seen,
+                    notified_count: row.get(6)?,
+                })
+            })
+            .context("Failed to execute query to get tracker errors")?;
+
+        let mut tracker_error_records: Vec<TrackerErrorRecord> = Vec::new();
+        for row in rows {
+            tracker_error_records.push(row.context("Failed to map tracker error")?);
+        }
+
+        Ok(tracker_error_records)
+    }
+}
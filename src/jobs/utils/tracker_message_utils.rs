@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{OptionalExtension, params};
+
+use crate::{
+    jobs::models::unregistered_matcher::UnregisteredMatcher,
+    logger::enums::category::Category,
+    trace,
+    utils::{date_utils::DateUtils, db_manager::DbManager},
+};
+
+/**
+ * Max amount of example torrent names stored per message
+ */
+const MAX_EXAMPLES: usize = 5;
+
+/**
+ * Amount of days a message is kept after it was last seen
+ */
+const RETENTION_DAYS: i64 = 30;
+
+pub struct TrackerMessageRecord {
+    id: i64,
+    domain: String,
+    msg: String,
+    count: i64,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    examples: Vec<String>,
+}
+
+impl TrackerMessageRecord {
+    /* Getter */
+    pub fn id(&self) -> &i64 {
+        &self.id
+    }
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+    pub fn count(&self) -> &i64 {
+        &self.count
+    }
+    pub fn first_seen(&self) -> &DateTime<Utc> {
+        &self.first_seen
+    }
+    pub fn last_seen(&self) -> &DateTime<Utc> {
+        &self.last_seen
+    }
+    pub fn examples(&self) -> &Vec<String> {
+        &self.examples
+    }
+}
+
+pub struct TrackerMessageUtils {
+    db_manager: DbManager,
+    category: Category,
+}
+
+impl TrackerMessageUtils {
+    pub fn new(db_manager: DbManager, category: Category) -> Self {
+        Self { db_manager, category }
+    }
+
+    /**
+     * Record the messages of non working trackers that matched no unregistered rule
+     * messages: HashMap<(domain, msg), Vec<torrent_name>>, every torrent counts as 1 sighting
+     */
+    pub fn record(&mut self, messages: &HashMap<(String, String), Vec<String>>) -> Result<(), anyhow::Error> {
+        let now_str = DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime());
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for ((domain, msg), torrent_names) in messages {
+            let stored_examples: Option<String> = tx
+                .query_row("SELECT examples FROM tracker_messages WHERE domain = ?1 AND msg = ?2", params![domain, msg], |row| row.get(0))
+                .optional()
+                .context("Failed to get tracker message")?;
+            match stored_examples {
+                Some(stored_examples) => {
+                    let mut examples: Vec<String> = stored_examples.lines().map(|line| line.to_string()).collect();
+                    for torrent_name in torrent_names {
+                        if examples.len() < MAX_EXAMPLES && !examples.contains(torrent_name) {
+                            examples.push(torrent_name.to_string());
+                        }
+                    }
+                    tx.prepare_cached("UPDATE tracker_messages SET count = count + ?1, last_seen = ?2, examples = ?3 WHERE domain = ?4 AND msg = ?5")?
+                        .execute(params![torrent_names.len() as i64, now_str, examples.join("\n"), domain, msg])
+                        .context("Failed to update tracker message")?;
+                    trace!(self.category, "Tracker message of {} has been seen again: {}", domain, msg);
+                }
+                None => {
+                    let examples: Vec<&str> = torrent_names.iter().take(MAX_EXAMPLES).map(|torrent_name| torrent_name.as_str()).collect();
+                    tx.prepare_cached("INSERT INTO tracker_messages (domain, msg, count, first_seen, last_seen, examples, reported) VALUES (?1, ?2, ?3, ?4, ?4, ?5, 0)")?
+                        .execute(params![domain, msg, torrent_names.len() as i64, now_str, examples.join("\n")])
+                        .context("Failed to insert tracker message")?;
+                    trace!(self.category, "Tracker message of {} has been seen for the first time: {}", domain, msg);
+                }
+            }
+        }
+        tx.commit().context("Failed to commit tracker messages")?;
+
+        Ok(())
+    }
+
+    /**
+     * Delete messages that are matched by an unregistered rule by now, e.g. after adding them to the config
+     */
+    pub fn delete_classified(&mut self, unregistered_matcher: &UnregisteredMatcher) -> Result<usize, anyhow::Error> {
+        let tracker_message_records = self.get_tracker_messages(false)?;
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        let mut deleted_count: usize = 0;
+        for tracker_message_record in tracker_message_records {
+            if unregistered_matcher.get_matched_message_rule(tracker_message_record.domain(), tracker_message_record.msg()).is_some() {
+                tx.prepare_cached("DELETE FROM tracker_messages WHERE id = ?1")?
+                    .execute(params![tracker_message_record.id()])
+                    .context("Failed to delete tracker message")?;
+                deleted_count += 1;
+            }
+        }
+        tx.commit().context("Failed to commit deleted tracker messages")?;
+
+        Ok(deleted_count)
+    }
+
+    /**
+     * Delete messages that haven't been seen for RETENTION_DAYS, they are reported again if they come back
+     */
+    pub fn delete_stale(&mut self) -> Result<usize, anyhow::Error> {
+        let prune_before_str = DateUtils::convert_utc_datetime_to_string(&(DateUtils::get_current_utc_datetime() - Duration::days(RETENTION_DAYS)));
+
+        let conn = self.db_manager.conn()?;
+        let deleted_count = conn
+            .execute("DELETE FROM tracker_messages WHERE last_seen < ?1", params![prune_before_str])
+            .context("Failed to delete stale tracker messages")?;
+
+        Ok(deleted_count)
+    }
+
+    /**
+     * Get all tracker messages, or only the ones that haven't been reported yet
+     */
+    pub fn get_tracker_messages(&mut self, only_unreported: bool) -> Result<Vec<TrackerMessageRecord>, anyhow::Error> {
+        let sql = match only_unreported {
+            true => "SELECT id, domain, msg, count, first_seen, last_seen, examples FROM tracker_messages WHERE reported = 0 ORDER BY domain, msg",
+            false => "SELECT id, domain, msg, count, first_seen, last_seen, examples FROM tracker_messages ORDER BY domain, msg",
+        };
+        let conn = self.db_manager.conn()?;
+        let mut stmt = conn.prepare(sql).context("Failed to prepare get_tracker_messages select")?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                let first_seen_str: String = row.get(4)?;
+                let first_seen = DateUtils::parse_utc_datetime_from_str(&first_seen_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+                let last_seen_str: String = row.get(5)?;
+                let last_seen = DateUtils::parse_utc_datetime_from_str(&last_seen_str).map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+                let examples_str: String = row.get(6)?;
+
+                Ok(TrackerMessageRecord {
+                    id: row.get(0)?,
+                    domain: row.get(1)?,
+                    msg: row.get(2)?,
+                    count: row.get(3)?,
+                    first_seen,
+                    last_seen,
+                    examples: examples_str.lines().map(|line| line.to_string()).collect(),
+                })
+            })
+            .context("Failed to execute query to get tracker messages")?;
+
+        let mut tracker_message_records: Vec<TrackerMessageRecord> = Vec::new();
+        for row in rows {
+            tracker_message_records.push(row.context("Failed to map tracker message")?);
+        }
+
+        Ok(tracker_message_records)
+    }
+
+    /**
+     * Mark tracker messages as reported so they are only reported once
+     */
+    pub fn mark_reported(&mut self, ids: &[i64]) -> Result<(), anyhow::Error> {
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for id in ids {
+            tx.prepare_cached("UPDATE tracker_messages SET reported = 1 WHERE id = ?1")?
+                .execute(params![id])
+                .context("Failed to mark tracker message as reported")?;
+        }
+        tx.commit().context("Failed to commit reported tracker messages")?;
+
+        Ok(())
+    }
+}
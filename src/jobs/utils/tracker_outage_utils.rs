@@ -0,0 +1,81 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+
+use crate::{
+    logger::enums::category::Category,
+    trace,
+    utils::{date_utils::DateUtils, db_manager::DbManager},
+};
+
+pub struct TrackerOutageRecord {
+    domain: String,
+    started_at: DateTime<Utc>,
+}
+
+impl TrackerOutageRecord {
+    /* Getter */
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+    pub fn started_at(&self) -> &DateTime<Utc> {
+        &self.started_at
+    }
+}
+
+pub struct TrackerOutageUtils {
+    db_manager: DbManager,
+    category: Category,
+}
+
+impl TrackerOutageUtils {
+    pub fn new(db_manager: DbManager, category: Category) -> Self {
+        Self { db_manager, category }
+    }
+
+    /**
+     * Store the tracker domains that look down in this run and compare them to the last run
+     * Returns: (Vec<String>, Vec<TrackerOutageRecord>) | (domains whose outage started, outages that ended)
+     */
+    pub fn update(&mut self, outage_domains: &HashMap<String, String>) -> Result<(Vec<String>, Vec<TrackerOutageRecord>), anyhow::Error> {
+        let now_str = DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime());
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        let stored_outages: Vec<(String, String)> = {
+            let mut stmt = tx.prepare("SELECT domain, started_at FROM tracker_outages").context("Failed to prepare select tracker outages")?;
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .context("Failed to query tracker outages")?
+                .collect::<Result<Vec<(String, String)>, rusqlite::Error>>()?
+        };
+        let stored_domains: HashSet<&str> = stored_outages.iter().map(|(domain, _)| domain.as_str()).collect();
+
+        let mut started_domains: Vec<String> = Vec::new();
+        for domain in outage_domains.keys() {
+            if stored_domains.contains(domain.as_str()) {
+                continue;
+            }
+            tx.prepare_cached("INSERT INTO tracker_outages (domain, started_at) VALUES (?1, ?2)")?
+                .execute(params![domain, now_str])
+                .context("Failed to insert tracker outage")?;
+            trace!(self.category, "Tracker outage of {} started", domain);
+            started_domains.push(domain.to_string());
+        }
+
+        let mut ended_outages: Vec<TrackerOutageRecord> = Vec::new();
+        for (domain, started_at_str) in stored_outages {
+            if outage_domains.contains_key(&domain) {
+                continue;
+            }
+            tx.prepare_cached("DELETE FROM tracker_outages WHERE domain = ?1")?.execute(params![domain]).context("Failed to delete tracker outage")?;
+            trace!(self.category, "Tracker outage of {} ended", domain);
+            let started_at = DateUtils::parse_utc_datetime_from_str(&started_at_str).context(format!("Failed to parse tracker outage start: {}", started_at_str))?;
+            ended_outages.push(TrackerOutageRecord { domain, started_at });
+        }
+        tx.commit().context("Failed to commit tracker outages")?;
+
+        Ok((started_domains, ended_outages))
+    }
+}
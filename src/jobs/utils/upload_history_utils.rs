@@ -0,0 +1,95 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use chrono::Duration;
+use rusqlite::params;
+
+use crate::{
+    logger::enums::category::Category,
+    torrent_clients::models::torrent::Torrent,
+    trace,
+    utils::{date_utils::DateUtils, db_manager::DbManager},
+};
+
+pub struct UploadHistoryUtils {
+    db_manager: DbManager,
+    category: Category,
+}
+
+impl UploadHistoryUtils {
+    pub fn new(db_manager: DbManager, category: Category) -> Self {
+        Self { db_manager, category }
+    }
+
+    /**
+     * Save the current uploaded bytes of every torrent and remove history that is not needed anymore
+     */
+    pub fn record(&mut self, torrents: &[Torrent], days: i64) -> Result<(), anyhow::Error> {
+        let now_str = DateUtils::convert_utc_datetime_to_string(&DateUtils::get_current_utc_datetime());
+        let prune_before_str = DateUtils::convert_utc_datetime_to_string(&(DateUtils::get_current_utc_datetime() - Duration::days(days * 2)));
+        let hashes: HashSet<&str> = torrents.iter().map(|torrent| torrent.hash()).collect();
+
+        let mut conn = self.db_manager.conn()?;
+        let tx = conn.transaction().context("Failed to get transaction")?;
+        for torrent in torrents {
+            tx.prepare_cached("INSERT INTO upload_history (hash, uploaded, recorded_at) VALUES (?1, ?2, ?3)")?
+                .execute(params![torrent.hash(), torrent.uploaded(), now_str])
+                .context("Failed to insert upload history")?;
+        }
+        // Keep twice the window so there is always a record older than the window
+        tx.prepare_cached("DELETE FROM upload_history WHERE recorded_at < ?1")?
+            .execute(params![prune_before_str])
+            .context("Failed to prune upload history")?;
+        // Torrents that don't exist anymore
+        let stored_hashes: Vec<String> = {
+            let mut stmt = tx.prepare("SELECT DISTINCT hash FROM upload_history").context("Failed to prepare select upload history hashes")?;
+            stmt.query_map([], |row| row.get(0)).context("Failed to query upload history hashes")?.collect::<Result<Vec<String>, rusqlite::Error>>()?
+        };
+        for stored_hash in stored_hashes {
+            if !hashes.contains(stored_hash.as_str()) {
+                tx.prepare_cached("DELETE FROM upload_history WHERE hash = ?1")?
+                    .execute(params![stored_hash])
+                    .context("Failed to delete upload history")?;
+            }
+        }
+        tx.commit().context("Failed to commit upload history")?;
+
+        Ok(())
+    }
+
+    /**
+     * Get the uploaded bytes of every torrent in the last days
+     * Uses the newest record older than the window, or the oldest record if the history isn't that long yet
+     * Returns: HashMap<String, i64> | HashMap<torrent_hash, uploaded_bytes>
+     */
+    pub fn get_recent_uploads(&mut self, torrents: &[Torrent], days: i64) -> Result<HashMap<String, i64>, anyhow::Error> {
+        let window_start_str = DateUtils::convert_utc_datetime_to_string(&(DateUtils::get_current_utc_datetime() - Duration::days(days)));
+
+        let conn = self.db_manager.conn()?;
+        let mut before_window_stmt = conn
+            .prepare("SELECT uploaded FROM upload_history WHERE hash = ?1 AND recorded_at <= ?2 ORDER BY recorded_at DESC LIMIT 1")
+            .context("Failed to prepare select upload history before window")?;
+        let mut oldest_stmt = conn
+            .prepare("SELECT uploaded FROM upload_history WHERE hash = ?1 ORDER BY recorded_at ASC LIMIT 1")
+            .context("Failed to prepare select oldest upload history")?;
+
+        let mut recent_uploads: HashMap<String, i64> = HashMap::new();
+        for torrent in torrents {
+            let mut uploaded_option: Option<i64> = before_window_stmt
+                .query_map(params![torrent.hash(), window_start_str], |row| row.get(0))
+                .context("Failed to query upload history before window")?
+                .next()
+                .transpose()?;
+            if uploaded_option.is_none() {
+                uploaded_option = oldest_stmt.query_map(params![torrent.hash()], |row| row.get(0)).context("Failed to query oldest upload history")?.next().transpose()?;
+            }
+            if let Some(uploaded) = uploaded_option {
+                let recent_upload = (torrent.uploaded() - uploaded).max(0);
+                trace!(self.category, "Torrent uploaded {} bytes in the last {} days: ({}) {}", recent_upload, days, torrent.hash(), torrent.name());
+                recent_uploads.insert(torrent.hash().to_string(), recent_upload);
+            }
+        }
+
+        Ok(recent_uploads)
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 
+#[derive(Clone, Copy)]
 pub enum Category {
     Qbittorrent,
     Setup,
@@ -7,11 +8,15 @@ pub enum Category {
     DiscordNotifier,
     Striker,
     FileUtils,
+    CrossSeed,
     DbManager,
     HandleUnlinked,
     HandleUnregistered,
     HandleOrphaned,
+    HandleTrackerErrors,
+    HandleErrored,
     HealthCheckFiles,
+    DeferredActions,
 }
 
 impl fmt::Display for Category {
@@ -23,11 +28,15 @@ impl fmt::Display for Category {
             Category::DiscordNotifier => String::from("discord_notifier"),
             Category::Striker => String::from("striker"),
             Category::FileUtils => String::from("file_utils"),
+            Category::CrossSeed => String::from("cross_seed"),
             Category::DbManager => String::from("db_manager"),
             Category::HandleUnlinked => String::from("handle_unlinked"),
             Category::HandleUnregistered => String::from("handle_unregistered"),
             Category::HandleOrphaned => String::from("handle_orphaned"),
+            Category::HandleTrackerErrors => String::from("handle_tracker_errors"),
+            Category::HandleErrored => String::from("handle_errored"),
             Category::HealthCheckFiles => String::from("health_check_files"),
+            Category::DeferredActions => String::from("deferred_actions"),
         };
         write!(f, "{}", category_str)
     }
@@ -1,7 +1,10 @@
+use std::env;
+
 use tokio::signal::unix::{SignalKind, signal};
 
-use crate::{logger::enums::category::Category, setup::Setup};
+use crate::{cli::Cli, logger::enums::category::Category, setup::Setup};
 
+mod cli;
 mod config;
 mod job_manager;
 mod jobs;
@@ -12,6 +15,17 @@ mod utils;
 
 #[tokio::main]
 async fn main() {
+    // Commands (e.g. torrent-cleaner actions --since 2024-01-01) run instead of the jobs
+    let args: Vec<String> = env::args().skip(1).collect();
+    match Cli::run(&args) {
+        Ok(true) => return,
+        Ok(false) => {}
+        Err(e) => {
+            error!(Category::Setup, "{:#}", e);
+            return;
+        }
+    }
+
     // Define signals
     let mut sigint = match signal(SignalKind::interrupt()) {
         Ok(sigint) => sigint,
@@ -1,15 +1,19 @@
 use std::{env, fs, path::Path, sync::Arc};
 
+use anyhow::Context;
+use chrono_tz::Tz;
+
 use crate::{
-    config::Config,
+    config::{Config, RuleOverride},
     debug, error, info,
     job_manager::JobManager,
+    jobs::enums::action_type::ActionType,
     logger::{
         enums::{category::Category, log_level::LogLevel},
         logger::Logger,
     },
     torrent_clients::{adapters::qbittorrent::Qbittorrent, enums::any_client::AnyClient, torrent_manager::TorrentManager},
-    utils::db_manager::DbManager,
+    utils::{date_utils::DateUtils, db_manager::DbManager},
 };
 
 pub struct Setup;
@@ -34,12 +38,14 @@ impl Setup {
 
         // Setup Config
         let config = Setup::get_config()?;
+        Setup::check_actions(&config)?;
         debug!(Category::Setup, "Config has been loaded");
 
-        // Create strike utils table
-        if let Err(e) = DbManager::check_create_tables() {
-            anyhow::bail!("Failed to check create db: {:#}", e);
-        }
+        // Setup timezone and day boundary
+        Setup::setup_time(&config)?;
+
+        // Open db and migrate its schema
+        let db_manager = Setup::setup_db(&config)?;
 
         // Setup torrent_manager
         let torrent_manager = match Setup::setup_torrent_manager(config.clone()) {
@@ -55,12 +61,25 @@ impl Setup {
         torrent_manager.logout().await?;
 
         // Setup jobs
-        let job_manager = JobManager::new(config.clone(), torrent_manager.clone(), torrents_path);
-        job_manager.setup();
+        let job_manager = JobManager::new(config.clone(), torrent_manager.clone(), db_manager, torrents_path);
+        job_manager.setup()?;
 
         Ok(job_manager)
     }
 
+    /**
+     * Setup for commands that only read the db (e.g. querying actions), no torrent client or jobs are started
+     */
+    pub fn setup_cli() -> Result<DbManager, anyhow::Error> {
+        Setup::setup_logging();
+        let config = Setup::get_config()?;
+        Setup::setup_time(&config)?;
+        // The daemon migrates the db, so the cli only reads a db of the same version
+        let db_manager = DbManager::open(config.database()).context("Failed to open db")?;
+        db_manager.check_version().context("Failed to check db")?;
+        Ok(db_manager)
+    }
+
     fn setup_logging() {
         let log_level = match env::var("LOG_LEVEL") {
             Ok(log_level) => log_level,
@@ -72,6 +91,68 @@ impl Setup {
         Logger::set_log_level(LogLevel::from_string(log_level.as_str()));
     }
 
+    fn setup_time(config: &Config) -> Result<(), anyhow::Error> {
+        let timezone: Option<Tz> = match config.time().timezone().is_empty() {
+            true => None,
+            false => match config.time().timezone().parse::<Tz>() {
+                Ok(timezone) => Some(timezone),
+                Err(e) => {
+                    anyhow::bail!("Failed to parse timezone {}: {:#}", config.time().timezone(), e);
+                }
+            },
+        };
+        if config.time().day_start_hour() > 23 {
+            anyhow::bail!("day_start_hour has to be between 0 and 23, got {}", config.time().day_start_hour());
+        }
+        DateUtils::set_time_settings(timezone, config.time().day_start_hour());
+        Ok(())
+    }
+
+    /**
+     * Reject actions a job can't take, otherwise they would only be skipped on every run
+     */
+    fn check_actions(config: &Config) -> Result<(), anyhow::Error> {
+        let jobs = config.jobs();
+        let torrent_actions = [ActionType::Test, ActionType::Stop, ActionType::Delete];
+        Setup::check_job_actions("handle_unlinked", jobs.handle_unlinked().action(), jobs.handle_unlinked().overrides(), &torrent_actions)?;
+        Setup::check_job_actions("handle_unlinked retention", jobs.handle_unlinked().retention().action(), &[], &torrent_actions)?;
+        Setup::check_job_actions("handle_unregistered", jobs.handle_unregistered().action(), jobs.handle_unregistered().overrides(), &torrent_actions)?;
+        Setup::check_job_actions("handle_orphaned", jobs.handle_orphaned().action(), jobs.handle_orphaned().overrides(), &[ActionType::Test, ActionType::Delete])?;
+        Setup::check_job_actions(
+            "handle_errored",
+            jobs.handle_errored().action(),
+            jobs.handle_errored().overrides(),
+            &[ActionType::Test, ActionType::Recheck, ActionType::Stop, ActionType::Delete],
+        )?;
+        Setup::check_job_actions("health_check_files", jobs.health_check_files().action(), &[], &[ActionType::Test])?;
+        Ok(())
+    }
+
+    fn check_job_actions(job_name: &str, action: &str, overrides: &[RuleOverride], supported_action_types: &[ActionType]) -> Result<(), anyhow::Error> {
+        let override_actions = overrides.iter().filter_map(|rule_override| rule_override.action());
+        for action in std::iter::once(action).chain(override_actions) {
+            let action_type = ActionType::from_str(action).context(format!("Invalid action of {}", job_name))?;
+            if !supported_action_types.contains(&action_type) {
+                let supported_str = supported_action_types.iter().map(|action_type| action_type.to_string()).collect::<Vec<String>>().join(", ");
+                anyhow::bail!("Action '{}' is not supported on {} (supported: {})", action, job_name, supported_str);
+            }
+        }
+        Ok(())
+    }
+
+    fn setup_db(config: &Config) -> Result<DbManager, anyhow::Error> {
+        let db_manager = match DbManager::open(config.database()) {
+            Ok(db_manager) => db_manager,
+            Err(e) => {
+                anyhow::bail!("Failed to open db: {:#}", e);
+            }
+        };
+        if let Err(e) = db_manager.migrate() {
+            anyhow::bail!("Failed to migrate db: {:#}", e);
+        }
+        Ok(db_manager)
+    }
+
     fn get_config() -> Result<Config, anyhow::Error> {
         let config_path = "/config/config.json";
         if !Path::new(config_path).exists() {
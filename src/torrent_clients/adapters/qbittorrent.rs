@@ -219,6 +219,50 @@ impl Qbittorrent {
         Ok(torrent_files)
     }
 
+    /**
+     * Reannounce torrents to all of their trackers
+     */
+    pub async fn reannounce(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error> {
+        let endpoint = self.base_url.join("api/v2/torrents/reannounce")?;
+        let hashes = torrent_hashes.join("|");
+        let params = [("hashes", hashes.as_str())];
+
+        let make_request_builder = || self.client.post(endpoint.clone()).form(&params);
+
+        self.make_request(make_request_builder).await.context("Qbittorrent reannounce torrents failed")?;
+
+        Ok(())
+    }
+
+    /**
+     * Recheck the data of torrents
+     */
+    pub async fn recheck(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error> {
+        let endpoint = self.base_url.join("api/v2/torrents/recheck")?;
+        let hashes = torrent_hashes.join("|");
+        let params = [("hashes", hashes.as_str())];
+
+        let make_request_builder = || self.client.post(endpoint.clone()).form(&params);
+
+        self.make_request(make_request_builder).await.context("Qbittorrent recheck torrents failed")?;
+
+        Ok(())
+    }
+
+    /**
+     * Replace an announce url of a torrent
+     */
+    pub async fn edit_tracker(&self, torrent_hash: &str, orig_url: &str, new_url: &str) -> Result<(), anyhow::Error> {
+        let endpoint = self.base_url.join("api/v2/torrents/editTracker")?;
+        let params = [("hash", torrent_hash), ("origUrl", orig_url), ("newUrl", new_url)];
+
+        let make_request_builder = || self.client.post(endpoint.clone()).form(&params);
+
+        self.make_request(make_request_builder).await.context("Qbittorrent edit tracker failed")?;
+
+        Ok(())
+    }
+
     /**
      * Stop torrent
      */
@@ -45,6 +45,24 @@ impl TorrentClient for AnyClient {
         }
     }
 
+    async fn reannounce(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error> {
+        match self {
+            AnyClient::Qbittorrent(c) => c.reannounce(torrent_hashes).await,
+        }
+    }
+
+    async fn recheck(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error> {
+        match self {
+            AnyClient::Qbittorrent(c) => c.recheck(torrent_hashes).await,
+        }
+    }
+
+    async fn edit_tracker(&self, torrent_hash: &str, orig_url: &str, new_url: &str) -> Result<(), anyhow::Error> {
+        match self {
+            AnyClient::Qbittorrent(c) => c.edit_tracker(torrent_hash, orig_url, new_url).await,
+        }
+    }
+
     async fn stop_torrent(&self, torrent_hash: &str) -> Result<(), anyhow::Error> {
         match self {
             AnyClient::Qbittorrent(c) => c.stop_torrent(torrent_hash).await,
@@ -1,20 +1,130 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize, de::IntoDeserializer};
+
+/**
+ * Every state a qBittorrent torrent can be in, states of the client this version doesn't know yet are Unknown
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub enum TorrentState {
+    Error,
+    MissingFiles,
+    Uploading,
     PausedUP,
     StoppedUP,
+    QueuedUP,
+    StalledUP,
+    CheckingUP,
+    ForcedUP,
+    Allocating,
+    Downloading,
+    MetaDL,
+    ForcedMetaDL,
     PausedDL,
     StoppedDL,
+    QueuedDL,
+    StalledDL,
+    CheckingDL,
+    ForcedDL,
+    CheckingResumeData,
+    Moving,
+    #[serde(other)]
+    Unknown,
 }
 
 impl fmt::Display for TorrentState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let state_str = match self {
+            TorrentState::Error => String::from("error"),
+            TorrentState::MissingFiles => String::from("missingFiles"),
+            TorrentState::Uploading => String::from("uploading"),
             TorrentState::PausedUP => String::from("pausedUP"),
             TorrentState::StoppedUP => String::from("stoppedUP"),
+            TorrentState::QueuedUP => String::from("queuedUP"),
+            TorrentState::StalledUP => String::from("stalledUP"),
+            TorrentState::CheckingUP => String::from("checkingUP"),
+            TorrentState::ForcedUP => String::from("forcedUP"),
+            TorrentState::Allocating => String::from("allocating"),
+            TorrentState::Downloading => String::from("downloading"),
+            TorrentState::MetaDL => String::from("metaDL"),
+            TorrentState::ForcedMetaDL => String::from("forcedMetaDL"),
             TorrentState::PausedDL => String::from("pausedDL"),
             TorrentState::StoppedDL => String::from("stoppedDL"),
+            TorrentState::QueuedDL => String::from("queuedDL"),
+            TorrentState::StalledDL => String::from("stalledDL"),
+            TorrentState::CheckingDL => String::from("checkingDL"),
+            TorrentState::ForcedDL => String::from("forcedDL"),
+            TorrentState::CheckingResumeData => String::from("checkingResumeData"),
+            TorrentState::Moving => String::from("moving"),
+            TorrentState::Unknown => String::from("unknown"),
         };
         write!(f, "{}", state_str)
     }
 }
+
+impl TorrentState {
+    /**
+     * Parse a state of the config, unlike client responses an unknown state is rejected since it is most likely a typo
+     */
+    pub fn from_config_str(s: &str) -> Result<Self, anyhow::Error> {
+        let torrent_state: Result<TorrentState, serde::de::value::Error> = TorrentState::deserialize(s.into_deserializer());
+        match torrent_state {
+            Ok(torrent_state) if torrent_state != TorrentState::Unknown => Ok(torrent_state),
+            _ => anyhow::bail!("Unknown torrent state '{}'", s),
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        matches!(self, TorrentState::PausedUP | TorrentState::StoppedUP | TorrentState::PausedDL | TorrentState::StoppedDL)
+    }
+    pub fn is_errored(&self) -> bool {
+        matches!(self, TorrentState::Error | TorrentState::MissingFiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(state_str: &str) -> TorrentState {
+        serde_json::from_value(serde_json::Value::String(state_str.to_string())).unwrap()
+    }
+
+    #[test]
+    fn parses_client_states() {
+        assert!(parse("stalledUP") == TorrentState::StalledUP);
+        assert!(parse("missingFiles") == TorrentState::MissingFiles);
+        assert!(parse("forcedMetaDL") == TorrentState::ForcedMetaDL);
+        assert!(parse("checkingResumeData") == TorrentState::CheckingResumeData);
+    }
+
+    #[test]
+    fn unknown_states_are_unknown() {
+        assert!(parse("someFutureState") == TorrentState::Unknown);
+    }
+
+    #[test]
+    fn unknown_config_states_are_rejected() {
+        assert!(TorrentState::from_config_str("stalledUP").is_ok_and(|torrent_state| torrent_state == TorrentState::StalledUP));
+        assert!(TorrentState::from_config_str("staledUP").is_err());
+        assert!(TorrentState::from_config_str("unknown").is_err());
+    }
+
+    #[test]
+    fn display_matches_the_client_state() {
+        for state_str in ["error", "stoppedUP", "queuedDL", "forcedUP", "moving"] {
+            assert_eq!(parse(state_str).to_string(), state_str);
+        }
+    }
+
+    #[test]
+    fn stopped_and_errored_states() {
+        assert!(parse("pausedUP").is_stopped());
+        assert!(parse("stoppedDL").is_stopped());
+        assert!(!parse("stalledUP").is_stopped());
+        assert!(parse("error").is_errored());
+        assert!(parse("missingFiles").is_errored());
+        assert!(!parse("stoppedUP").is_errored());
+    }
+}
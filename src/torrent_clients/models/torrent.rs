@@ -1,5 +1,8 @@
+use reqwest::Url;
 use serde::Deserialize;
 
+use crate::torrent_clients::enums::torrent_state::TorrentState;
+
 #[derive(Deserialize, Clone)]
 pub struct Torrent {
     hash: String,
@@ -8,13 +11,18 @@ pub struct Torrent {
     content_path: String,
     save_path: String,
     ratio: f32,
-    state: String,
+    state: TorrentState,
     tracker: String,
     category: String,
     tags: String,
     added_on: i64,
     completion_on: i64,
     seeding_time: i64,
+    num_complete: i64,
+    num_incomplete: i64,
+    upspeed: i64,
+    last_activity: i64,
+    uploaded: i64,
 }
 
 impl Torrent {
@@ -36,12 +44,18 @@ impl Torrent {
     pub fn ratio(&self) -> &f32 {
         &self.ratio
     }
-    pub fn state(&self) -> &str {
+    pub fn state(&self) -> &TorrentState {
         &self.state
     }
     pub fn tracker(&self) -> &str {
         &self.tracker
     }
+    /**
+     * Host of the current tracker url, empty if there is no working tracker
+     */
+    pub fn tracker_domain(&self) -> String {
+        Url::parse(&self.tracker).ok().and_then(|url| url.host_str().map(|host| host.to_lowercase())).unwrap_or_default()
+    }
     pub fn category(&self) -> &str {
         &self.category
     }
@@ -57,4 +71,67 @@ impl Torrent {
     pub fn seeding_time(&self) -> &i64 {
         &self.seeding_time
     }
+    /**
+     * Seeders in the swarm
+     */
+    pub fn num_complete(&self) -> &i64 {
+        &self.num_complete
+    }
+    /**
+     * Leechers in the swarm
+     */
+    pub fn num_incomplete(&self) -> &i64 {
+        &self.num_incomplete
+    }
+    /**
+     * Upload speed in bytes/s
+     */
+    pub fn upspeed(&self) -> &i64 {
+        &self.upspeed
+    }
+    /**
+     * Unix timestamp of the last time a chunk was up- or downloaded
+     */
+    pub fn last_activity(&self) -> &i64 {
+        &self.last_activity
+    }
+    /**
+     * Uploaded bytes in total
+     */
+    pub fn uploaded(&self) -> &i64 {
+        &self.uploaded
+    }
+}
+
+#[cfg(test)]
+impl Torrent {
+    /**
+     * Torrent for unit tests, the given fields replace the defaults
+     */
+    pub fn from_test_fields(fields: serde_json::Value) -> Self {
+        let mut torrent_value = serde_json::json!({
+            "hash": "",
+            "name": "",
+            "total_size": 0,
+            "content_path": "",
+            "save_path": "",
+            "ratio": 0.0,
+            "state": "stalledUP",
+            "tracker": "",
+            "category": "",
+            "tags": "",
+            "added_on": 0,
+            "completion_on": 0,
+            "seeding_time": 0,
+            "num_complete": 0,
+            "num_incomplete": 0,
+            "upspeed": 0,
+            "last_activity": 0,
+            "uploaded": 0,
+        });
+        if let (Some(torrent_fields), Some(fields)) = (torrent_value.as_object_mut(), fields.as_object()) {
+            torrent_fields.extend(fields.clone());
+        }
+        serde_json::from_value(torrent_value).unwrap()
+    }
 }
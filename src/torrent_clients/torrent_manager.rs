@@ -37,6 +37,18 @@ impl TorrentManager {
         self.torrent_client.get_torrent_files(torrent_hash).await
     }
 
+    pub async fn reannounce(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error> {
+        self.torrent_client.reannounce(torrent_hashes).await
+    }
+
+    pub async fn recheck(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error> {
+        self.torrent_client.recheck(torrent_hashes).await
+    }
+
+    pub async fn edit_tracker(&self, torrent_hash: &str, orig_url: &str, new_url: &str) -> Result<(), anyhow::Error> {
+        self.torrent_client.edit_tracker(torrent_hash, orig_url, new_url).await
+    }
+
     pub async fn stop_torrent(&self, torrent_hash: &str) -> Result<(), anyhow::Error> {
         self.torrent_client.stop_torrent(torrent_hash).await
     }
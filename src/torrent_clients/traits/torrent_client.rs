@@ -7,6 +7,9 @@ pub trait TorrentClient {
     async fn get_all_torrents(&self) -> Result<Vec<Torrent>, anyhow::Error>;
     async fn get_torrent_trackers(&self, torrent_hash: &str) -> Result<Vec<Tracker>, anyhow::Error>;
     async fn get_torrent_files(&self, torrent_hash: &str) -> Result<Vec<TorrentFile>, anyhow::Error>;
+    async fn reannounce(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error>;
+    async fn recheck(&self, torrent_hashes: &[String]) -> Result<(), anyhow::Error>;
+    async fn edit_tracker(&self, torrent_hash: &str, orig_url: &str, new_url: &str) -> Result<(), anyhow::Error>;
     async fn stop_torrent(&self, torrent_hash: &str) -> Result<(), anyhow::Error>;
     async fn delete_torrent(&self, torrent_hash: &str, delete_files: bool) -> Result<(), anyhow::Error>;
 }
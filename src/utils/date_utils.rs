@@ -1,32 +1,167 @@
-use chrono::{Local, NaiveDate, NaiveDateTime, ParseError};
+#[cfg(test)]
+use std::cell::Cell;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, ParseError, SubsecRound, TimeZone, Utc};
+use chrono_tz::Tz;
+use croner::{Cron, errors::CronError};
 
 static DATE_FORMAT: &str = "%Y-%m-%d";
-static DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+static LOCAL_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+static UTC_DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+static TIME_SETTINGS: OnceLock<TimeSettings> = OnceLock::new();
+
+#[cfg(test)]
+thread_local! {
+    static TEST_TIME_SETTINGS: Cell<Option<TimeSettings>> = const { Cell::new(None) };
+}
+
+#[derive(Clone, Copy)]
+struct TimeSettings {
+    timezone: Option<Tz>,
+    day_start_hour: u32,
+}
 
 pub struct DateUtils;
 
 impl DateUtils {
-    pub fn get_current_local_naive_date() -> NaiveDate {
-        Local::now().date_naive()
+    /**
+     * Set the timezone (None uses the system timezone) and the hour a strike day starts at
+     * Tests set them for their own thread, so they don't depend on the settings of the test that ran first
+     */
+    pub fn set_time_settings(timezone: Option<Tz>, day_start_hour: u32) {
+        #[cfg(test)]
+        TEST_TIME_SETTINGS.set(Some(TimeSettings { timezone, day_start_hour }));
+        #[cfg(not(test))]
+        let _ = TIME_SETTINGS.set(TimeSettings { timezone, day_start_hour });
+    }
+
+    fn get_time_settings() -> Option<TimeSettings> {
+        #[cfg(test)]
+        if let Some(time_settings) = TEST_TIME_SETTINGS.get() {
+            return Some(time_settings);
+        }
+        TIME_SETTINGS.get().copied()
     }
 
-    pub fn get_current_local_naive_datetime() -> NaiveDateTime {
-        Local::now().naive_local()
+    pub fn get_current_utc_datetime() -> DateTime<Utc> {
+        Utc::now().trunc_subsecs(0)
     }
 
-    pub fn convert_naive_date_to_string(naive_date: NaiveDate) -> String {
-        naive_date.format(DATE_FORMAT).to_string()
+    /**
+     * Day a point in time belongs to for strike days, in the configured timezone and starting at the configured hour
+     */
+    pub fn get_strike_day(datetime: &DateTime<Utc>) -> NaiveDate {
+        let (local_datetime, day_start_hour) = match DateUtils::get_time_settings() {
+            Some(TimeSettings { timezone: Some(timezone), day_start_hour }) => (datetime.with_timezone(&timezone).naive_local(), day_start_hour),
+            Some(TimeSettings { timezone: None, day_start_hour }) => (datetime.with_timezone(&Local).naive_local(), day_start_hour),
+            None => (datetime.with_timezone(&Local).naive_local(), 0),
+        };
+        (local_datetime - Duration::hours(day_start_hour as i64)).date()
     }
 
-    pub fn convert_naive_datetime_to_string(naive_datetime: NaiveDateTime) -> String {
-        naive_datetime.format(DATETIME_FORMAT).to_string()
+    /**
+     * Next time after the given one that matches the cron expression in the configured timezone
+     */
+    pub fn get_next_cron_datetime(cron: &Cron, after: &DateTime<Utc>) -> Result<DateTime<Utc>, CronError> {
+        match DateUtils::get_time_settings() {
+            Some(TimeSettings { timezone: Some(timezone), .. }) => cron.find_next_occurrence(&after.with_timezone(&timezone), false).map(|next| next.with_timezone(&Utc)),
+            _ => cron.find_next_occurrence(&after.with_timezone(&Local), false).map(|next| next.with_timezone(&Utc)),
+        }
     }
 
-    pub fn parse_naive_date_from_str(str: &str) -> Result<NaiveDate, ParseError> {
-        NaiveDate::parse_from_str(str, DATE_FORMAT)
+    pub fn convert_utc_datetime_to_string(utc_datetime: &DateTime<Utc>) -> String {
+        utc_datetime.format(UTC_DATETIME_FORMAT).to_string()
+    }
+
+    /**
+     * Format a utc datetime in the configured timezone for logs and notifications
+     */
+    pub fn convert_utc_datetime_to_local_string(utc_datetime: &DateTime<Utc>) -> String {
+        match DateUtils::get_time_settings() {
+            Some(TimeSettings { timezone: Some(timezone), .. }) => utc_datetime.with_timezone(&timezone).format(LOCAL_DATETIME_FORMAT).to_string(),
+            _ => utc_datetime.with_timezone(&Local).format(LOCAL_DATETIME_FORMAT).to_string(),
+        }
+    }
+
+    /**
+     * Format a unix timestamp (e.g. added_on of a torrent) in the configured timezone, None if it is out of range
+     */
+    pub fn convert_timestamp_to_local_string(timestamp: i64) -> Option<String> {
+        DateTime::from_timestamp(timestamp, 0).map(|utc_datetime| DateUtils::convert_utc_datetime_to_local_string(&utc_datetime))
+    }
+
+    /**
+     * Parse a date (e.g. 2024-01-31) as the start of that day in the configured timezone
+     */
+    pub fn parse_local_date_from_str(str: &str) -> Result<DateTime<Utc>, ParseError> {
+        let naive_datetime = NaiveDate::parse_from_str(str, DATE_FORMAT)?.and_time(NaiveTime::MIN);
+        Ok(DateUtils::convert_local_naive_datetime_to_utc(&naive_datetime))
+    }
+
+    pub fn convert_utc_datetime_to_local_naive_datetime(utc_datetime: &DateTime<Utc>) -> NaiveDateTime {
+        match DateUtils::get_time_settings() {
+            Some(TimeSettings { timezone: Some(timezone), .. }) => utc_datetime.with_timezone(&timezone).naive_local(),
+            _ => utc_datetime.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /**
+     * Convert a time in the configured timezone to utc
+     * The earlier time is used if it is ambiguous due to DST, a time skipped by DST is taken as utc
+     */
+    pub fn convert_local_naive_datetime_to_utc(naive_datetime: &NaiveDateTime) -> DateTime<Utc> {
+        let utc_datetime_option = match DateUtils::get_time_settings() {
+            Some(TimeSettings { timezone: Some(timezone), .. }) => timezone.from_local_datetime(naive_datetime).earliest().map(|local_datetime| local_datetime.with_timezone(&Utc)),
+            _ => Local.from_local_datetime(naive_datetime).earliest().map(|local_datetime| local_datetime.with_timezone(&Utc)),
+        };
+        utc_datetime_option.unwrap_or_else(|| naive_datetime.and_utc())
+    }
+
+    pub fn parse_utc_datetime_from_str(str: &str) -> Result<DateTime<Utc>, ParseError> {
+        NaiveDateTime::parse_from_str(str, UTC_DATETIME_FORMAT).map(|naive_datetime| naive_datetime.and_utc())
+    }
+
+    /**
+     * Parse a datetime stored in the system timezone by older versions
+     * The earlier time is used if it is ambiguous due to DST, a time skipped by DST is taken as utc
+     */
+    pub fn parse_legacy_local_datetime_from_str(str: &str) -> Result<DateTime<Utc>, ParseError> {
+        let naive_datetime = NaiveDateTime::parse_from_str(str, LOCAL_DATETIME_FORMAT)?;
+        Ok(Local
+            .from_local_datetime(&naive_datetime)
+            .earliest()
+            .map(|local_datetime| local_datetime.with_timezone(&Utc))
+            .unwrap_or_else(|| naive_datetime.and_utc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::{America::New_York, UTC};
+
+    use super::*;
+
+    #[test]
+    fn time_settings_can_be_changed_by_a_test() {
+        let utc_datetime = Utc.with_ymd_and_hms(2026, 10, 12, 3, 0, 0).unwrap();
+        DateUtils::set_time_settings(Some(UTC), 0);
+        assert_eq!(DateUtils::get_strike_day(&utc_datetime), NaiveDate::from_ymd_opt(2026, 10, 12).unwrap());
+        assert_eq!(DateUtils::convert_utc_datetime_to_local_string(&utc_datetime), "2026-10-12 03:00:00");
+
+        DateUtils::set_time_settings(Some(UTC), 6);
+        assert_eq!(DateUtils::get_strike_day(&utc_datetime), NaiveDate::from_ymd_opt(2026, 10, 11).unwrap());
+
+        DateUtils::set_time_settings(Some(New_York), 0);
+        assert_eq!(DateUtils::convert_utc_datetime_to_local_string(&utc_datetime), "2026-10-11 23:00:00");
     }
 
-    pub fn parse_naive_datetime_from_str(str: &str) -> Result<NaiveDateTime, ParseError> {
-        NaiveDateTime::parse_from_str(str, DATETIME_FORMAT)
+    #[test]
+    fn timestamp_is_formatted_in_the_configured_timezone() {
+        DateUtils::set_time_settings(Some(New_York), 0);
+        let timestamp = Utc.with_ymd_and_hms(2026, 10, 12, 3, 0, 0).unwrap().timestamp();
+        assert_eq!(DateUtils::convert_timestamp_to_local_string(timestamp).as_deref(), Some("2026-10-11 23:00:00"));
+        assert_eq!(DateUtils::convert_timestamp_to_local_string(i64::MAX), None);
     }
 }
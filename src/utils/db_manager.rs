@@ -1,86 +1,177 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
 use anyhow::Context;
 use rusqlite::Connection;
 
-use crate::{logger::enums::category::Category, trace};
+use crate::{
+    config::Database,
+    info,
+    logger::enums::category::Category,
+    trace,
+    utils::{
+        date_utils::DateUtils,
+        db_migrations::{MIGRATIONS, Migration},
+    },
+};
 
-pub struct Session {
-    conn: Option<Connection>,
+/**
+ * Shared db connection, cloned into every job so there is only 1 writer in the process
+ */
+#[derive(Clone)]
+pub struct DbManager {
+    path: String,
+    conn: Arc<Mutex<Connection>>,
 }
 
-impl Session {
-    /// Create a new database session
-    pub fn new() -> Result<Self, anyhow::Error> {
-        let conn = Connection::open("/config/database.db").context("Failed to open connection to database")?;
-        Ok(Self { conn: Some(conn) })
+impl DbManager {
+    /**
+     * Open the db in WAL mode, so external readers (e.g. sqlite3 or a backup tool) don't block writes and the other way around
+     */
+    pub fn open(database: &Database) -> Result<Self, anyhow::Error> {
+        let conn = Connection::open(database.path()).context(format!("Failed to open connection to database: {}", database.path()))?;
+        conn.busy_timeout(Duration::from_millis(database.busy_timeout_ms())).context("Failed to set busy timeout")?;
+        let journal_mode: String = conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0)).context("Failed to enable WAL journal mode")?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            anyhow::bail!("Failed to enable WAL journal mode, db uses {}", journal_mode);
+        }
+        conn.pragma_update(None, "synchronous", "NORMAL").context("Failed to set synchronous mode")?;
+        trace!(Category::DbManager, "Opened db {} (WAL, busy timeout {}ms)", database.path(), database.busy_timeout_ms());
+
+        Ok(Self {
+            path: database.path().to_string(),
+            conn: Arc::new(Mutex::new(conn)),
+        })
     }
 
-    /// Get a reference to the connection
-    pub fn conn(&self) -> Option<&Connection> {
-        self.conn.as_ref()
+    /**
+     * Migrated db that only lives in memory, for tests
+     */
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self, anyhow::Error> {
+        let db_manager = Self {
+            path: String::from(":memory:"),
+            conn: Arc::new(Mutex::new(Connection::open_in_memory().context("Failed to open in-memory database")?)),
+        };
+        db_manager.migrate()?;
+
+        Ok(db_manager)
     }
 
-    /// Get a mutable reference to the connection
-    pub fn conn_mut(&mut self) -> Option<&mut Connection> {
-        self.conn.as_mut()
+    /**
+     * Lock the connection, keep the guard only as long as needed since every job shares it
+     */
+    pub fn conn(&self) -> Result<MutexGuard<'_, Connection>, anyhow::Error> {
+        self.conn.lock().map_err(|_| anyhow::anyhow!("Db connection lock is poisoned"))
     }
 
-    /// Consume the session and return the connection
-    pub fn into_conn(mut self) -> Option<Connection> {
-        self.conn.take()
+    /**
+     * Apply pending schema migrations in order, each one in its own transaction
+     * The db is backed up before migrating and a schema newer than this version supports is refused
+     */
+    pub fn migrate(&self) -> Result<(), anyhow::Error> {
+        let mut conn = self.conn()?;
+
+        let current_version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).context("Failed to get db schema version")?;
+        let latest_version = MIGRATIONS.last().map(Migration::version).unwrap_or(0);
+        if current_version > latest_version {
+            anyhow::bail!(
+                "Db schema version {} is newer than the supported version {}, update torrent-cleaner or restore a backup of the db",
+                current_version,
+                latest_version
+            );
+        }
+        if current_version == latest_version {
+            trace!(Category::DbManager, "Db schema is up to date (version {})", current_version);
+            return Ok(());
+        }
+
+        // Fresh dbs have nothing to back up
+        let table_count: i32 = conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'", [], |row| row.get(0)).context("Failed to count db tables")?;
+        if table_count > 0 {
+            self.backup(&conn, current_version).context("Failed to back up db before migrating")?;
+        }
+
+        for migration in MIGRATIONS.iter().filter(|migration| migration.version() > current_version) {
+            let tx = conn.transaction().context("Failed to get transaction")?;
+            migration.up(&tx).context(format!("Failed to apply migration {} ({})", migration.version(), migration.name()))?;
+            tx.pragma_update(None, "user_version", migration.version()).context("Failed to set db schema version")?;
+            tx.commit().context(format!("Failed to commit migration {} ({})", migration.version(), migration.name()))?;
+            info!(Category::DbManager, "Applied db migration {} ({})", migration.version(), migration.name());
+        }
+
+        Ok(())
     }
 
-    /// Explicitly close the session
-    pub fn close(&mut self) {
-        if let Some(conn) = self.conn.take() {
-            let _ = conn.close();
+    /**
+     * Refuse a db with another schema version than this version supports, for readers that must not migrate it (e.g. the cli while the daemon runs)
+     */
+    pub fn check_version(&self) -> Result<(), anyhow::Error> {
+        let conn = self.conn()?;
+
+        let current_version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).context("Failed to get db schema version")?;
+        let latest_version = MIGRATIONS.last().map(Migration::version).unwrap_or(0);
+        if current_version != latest_version {
+            anyhow::bail!(
+                "Db schema version {} differs from the supported version {}, start torrent-cleaner of this version once to migrate it",
+                current_version,
+                latest_version
+            );
         }
+
+        Ok(())
     }
-}
 
-impl Drop for Session {
-    fn drop(&mut self) {
-        self.close();
+    /**
+     * Copy the db next to it, named after the schema version it had
+     */
+    fn backup(&self, conn: &Connection, version: i32) -> Result<(), anyhow::Error> {
+        let backup_path = format!("{}.v{}.{}.bak", self.path, version, DateUtils::get_current_utc_datetime().format("%Y%m%d%H%M%S"));
+        conn.execute("VACUUM INTO ?1", [&backup_path]).context(format!("Failed to write backup {}", backup_path))?;
+        info!(Category::DbManager, "Backed up db (version {}) to {}", version, backup_path);
+        Ok(())
     }
 }
 
-pub struct DbManager;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl DbManager {
-    /**
-     * Create tables
-     */
-    pub fn check_create_tables() -> Result<(), anyhow::Error> {
-        let mut session = Session::new()?;
-        let conn = session.conn_mut().ok_or_else(|| anyhow::anyhow!("Failed to get connection from session"))?;
-
-        // strikes
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS strikes (
-                    id INTEGER PRIMARY KEY,
-                    strike_type VARCHAR(255) NOT NULL,
-                    hash VARCHAR(255) NOT NULL,
-                    strikes INTEGER NOT NULL,
-                    strike_days INTEGER NOT NULL,
-                    last_strike_date TEXT NOT NULL,
-                    UNIQUE (strike_type, hash)
-                )",
-            (),
-        )
-        .context("Failed to create strikes table")?;
-
-        // jobs
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS jobs (
-                    id INTEGER PRIMARY KEY,
-                    job_name VARCHAR(255) UNIQUE NOT NULL,
-                    last_job_run TEXT NOT NULL
-                )",
-            (),
-        )
-        .context("Failed to create jobs table")?;
-
-        trace!(Category::DbManager, "Check-Created db tables");
+    fn get_user_version(db_manager: &DbManager) -> i32 {
+        db_manager.conn().unwrap().query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
 
-        Ok(())
+    #[test]
+    fn migrate_applies_every_migration() {
+        let db_manager = DbManager::open_in_memory().unwrap();
+        assert_eq!(get_user_version(&db_manager), MIGRATIONS.last().unwrap().version());
+        // Migrating an up to date db does nothing
+        db_manager.migrate().unwrap();
+        assert_eq!(get_user_version(&db_manager), MIGRATIONS.last().unwrap().version());
+    }
+
+    #[test]
+    fn migrate_refuses_a_newer_schema() {
+        let db_manager = DbManager::open_in_memory().unwrap();
+        let newer_version = MIGRATIONS.last().unwrap().version() + 1;
+        db_manager.conn().unwrap().pragma_update(None, "user_version", newer_version).unwrap();
+
+        let error = db_manager.migrate().unwrap_err();
+        assert!(error.to_string().contains(&format!("Db schema version {} is newer", newer_version)));
+        assert_eq!(get_user_version(&db_manager), newer_version);
+    }
+
+    #[test]
+    fn check_version_refuses_another_schema() {
+        let db_manager = DbManager::open_in_memory().unwrap();
+        db_manager.check_version().unwrap();
+        let older_version = MIGRATIONS.last().unwrap().version() - 1;
+        db_manager.conn().unwrap().pragma_update(None, "user_version", older_version).unwrap();
+
+        let error = db_manager.check_version().unwrap_err();
+        assert!(error.to_string().contains(&format!("Db schema version {} differs", older_version)));
+        assert_eq!(get_user_version(&db_manager), older_version);
     }
 }
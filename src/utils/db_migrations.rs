@@ -0,0 +1,416 @@
+use anyhow::Context;
+use chrono::{Days, NaiveDate};
+use rusqlite::{Connection, params};
+
+use crate::utils::date_utils::DateUtils;
+
+pub struct Migration {
+    version: i32,
+    name: &'static str,
+    up: fn(&Connection) -> Result<(), anyhow::Error>,
+}
+
+impl Migration {
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+    pub fn name(&self) -> &str {
+        self.name
+    }
+    pub fn up(&self, conn: &Connection) -> Result<(), anyhow::Error> {
+        (self.up)(conn)
+    }
+}
+
+/**
+ * Schema migrations in the order they are applied, the version is stored in the user_version of the db
+ * Never change a released migration, add a new one with the next version instead
+ */
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_tables",
+        up: DbMigrations::create_tables,
+    },
+    Migration {
+        version: 2,
+        name: "add_strike_policy_columns",
+        up: DbMigrations::add_strike_policy_columns,
+    },
+    Migration {
+        version: 3,
+        name: "convert_timestamps_to_utc",
+        up: DbMigrations::convert_timestamps_to_utc,
+    },
+    Migration {
+        version: 4,
+        name: "drop_last_strike_date",
+        up: DbMigrations::drop_last_strike_date,
+    },
+    Migration {
+        version: 5,
+        name: "create_actions",
+        up: DbMigrations::create_actions,
+    },
+    Migration {
+        version: 6,
+        name: "create_job_runs",
+        up: DbMigrations::create_job_runs,
+    },
+    Migration {
+        version: 7,
+        name: "create_deferred_actions",
+        up: DbMigrations::create_deferred_actions,
+    },
+];
+
+pub struct DbMigrations;
+
+impl DbMigrations {
+    /**
+     * Tables as they were before versioned migrations, IF NOT EXISTS keeps older dbs that already have them
+     */
+    fn create_tables(conn: &Connection) -> Result<(), anyhow::Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS strikes (
+                id INTEGER PRIMARY KEY,
+                strike_type VARCHAR(255) NOT NULL,
+                hash VARCHAR(255) NOT NULL,
+                strikes INTEGER NOT NULL,
+                strike_days INTEGER NOT NULL,
+                last_strike_date TEXT NOT NULL,
+                UNIQUE (strike_type, hash)
+            );
+            CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY,
+                job_name VARCHAR(255) UNIQUE NOT NULL,
+                last_job_run TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS upload_history (
+                id INTEGER PRIMARY KEY,
+                hash VARCHAR(255) NOT NULL,
+                uploaded INTEGER NOT NULL,
+                recorded_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS upload_history_hash ON upload_history (hash, recorded_at);
+            CREATE TABLE IF NOT EXISTS tracker_messages (
+                id INTEGER PRIMARY KEY,
+                domain VARCHAR(255) NOT NULL,
+                msg TEXT NOT NULL,
+                count INTEGER NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                examples TEXT NOT NULL,
+                reported INTEGER NOT NULL,
+                UNIQUE (domain, msg)
+            );
+            CREATE TABLE IF NOT EXISTS tracker_outages (
+                id INTEGER PRIMARY KEY,
+                domain VARCHAR(255) UNIQUE NOT NULL,
+                started_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS tracker_errors (
+                id INTEGER PRIMARY KEY,
+                hash VARCHAR(255) NOT NULL,
+                domain VARCHAR(255) NOT NULL,
+                msg TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                notified_at TEXT NOT NULL DEFAULT '',
+                notified_count INTEGER NOT NULL DEFAULT 0,
+                UNIQUE (hash, domain)
+            );",
+        )
+        .context("Failed to create tables")?;
+
+        Ok(())
+    }
+
+    /**
+     * Add the strike policy columns, existing rows are treated as striked once a day from their first strike day until their last strike date
+     * Strikes beyond 1 per day are put on the last strike date, so sliding windows still count every strike
+     * Skipped if the columns already exist (dbs of versions that added them before versioned migrations)
+     */
+    fn add_strike_policy_columns(conn: &Connection) -> Result<(), anyhow::Error> {
+        let column_names: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('strikes')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to get strikes columns")?;
+        if column_names.iter().any(|column_name| column_name == "first_strike_at") {
+            return Ok(());
+        }
+
+        conn.execute_batch(
+            "ALTER TABLE strikes ADD COLUMN first_strike_at TEXT NOT NULL DEFAULT '';
+            ALTER TABLE strikes ADD COLUMN last_strike_at TEXT NOT NULL DEFAULT '';
+            ALTER TABLE strikes ADD COLUMN strike_times TEXT NOT NULL DEFAULT '';
+            ALTER TABLE strikes ADD COLUMN missed_runs INTEGER NOT NULL DEFAULT 0;
+            UPDATE strikes SET
+                first_strike_at = date(last_strike_date, '-' || (strike_days - 1) || ' days') || ' 00:00:00',
+                last_strike_at = last_strike_date || ' 00:00:00',
+                missed_runs = 0;",
+        )
+        .context("Failed to add strike policy columns")?;
+
+        let legacy_strikes: Vec<(i64, i32, i32, String)> = conn
+            .prepare("SELECT id, strikes, strike_days, last_strike_date FROM strikes")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+            .collect::<Result<Vec<(i64, i32, i32, String)>, _>>()
+            .context("Failed to get legacy strikes")?;
+        let mut stmt = conn.prepare("UPDATE strikes SET strike_times = ?1 WHERE id = ?2")?;
+        for (id, strikes, strike_days, last_strike_date) in legacy_strikes {
+            let last_strike_date = NaiveDate::parse_from_str(&last_strike_date, "%Y-%m-%d").context(format!("Failed to parse last_strike_date {}", last_strike_date))?;
+            let strike_times: Vec<String> = (0..strikes.max(1))
+                .map(|i| {
+                    let strike_date = last_strike_date - Days::new((strike_days - 1 - i).max(0) as u64);
+                    format!("{} 00:00:00", strike_date.format("%Y-%m-%d"))
+                })
+                .collect();
+            stmt.execute(params![strike_times.join("\n"), id]).context("Failed to backfill strike times")?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Convert timestamps written in the system timezone to utc
+     * Values that are already utc end with Z and are skipped
+     */
+    fn convert_timestamps_to_utc(conn: &Connection) -> Result<(), anyhow::Error> {
+        let timestamp_columns = [
+            ("strikes", "first_strike_at"),
+            ("strikes", "last_strike_at"),
+            ("strikes", "strike_times"),
+            ("jobs", "last_job_run"),
+            ("upload_history", "recorded_at"),
+            ("tracker_messages", "first_seen"),
+            ("tracker_messages", "last_seen"),
+            ("tracker_errors", "first_seen"),
+            ("tracker_errors", "last_seen"),
+            ("tracker_errors", "notified_at"),
+            ("tracker_outages", "started_at"),
+        ];
+
+        for (table, column) in timestamp_columns {
+            let rows: Vec<(i64, String)> = conn
+                .prepare(format!("SELECT id, {column} FROM {table} WHERE {column} NOT LIKE '%Z' AND {column} != ''").as_str())?
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<(i64, String)>, _>>()
+                .context(format!("Failed to get {}.{} timestamps", table, column))?;
+            for (id, value) in rows {
+                // strike_times holds one timestamp per line
+                let mut utc_values: Vec<String> = Vec::new();
+                for local_value in value.lines() {
+                    let utc_datetime = DateUtils::parse_legacy_local_datetime_from_str(local_value).context(format!("Failed to parse {}.{} timestamp: {}", table, column, local_value))?;
+                    utc_values.push(DateUtils::convert_utc_datetime_to_string(&utc_datetime));
+                }
+                conn.execute(format!("UPDATE {table} SET {column} = ?1 WHERE id = ?2").as_str(), params![utc_values.join("\n"), id])
+                    .context(format!("Failed to update {}.{} timestamp", table, column))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * last_strike_date was replaced by last_strike_at, the table is rebuilt since older sqlite versions can't drop columns
+     */
+    fn drop_last_strike_date(conn: &Connection) -> Result<(), anyhow::Error> {
+        conn.execute_batch(
+            "CREATE TABLE strikes_new (
+                id INTEGER PRIMARY KEY,
+                strike_type VARCHAR(255) NOT NULL,
+                hash VARCHAR(255) NOT NULL,
+                strikes INTEGER NOT NULL,
+                strike_days INTEGER NOT NULL,
+                first_strike_at TEXT NOT NULL,
+                last_strike_at TEXT NOT NULL,
+                strike_times TEXT NOT NULL,
+                missed_runs INTEGER NOT NULL,
+                UNIQUE (strike_type, hash)
+            );
+            INSERT INTO strikes_new (id, strike_type, hash, strikes, strike_days, first_strike_at, last_strike_at, strike_times, missed_runs)
+                SELECT id, strike_type, hash, strikes, strike_days, first_strike_at, last_strike_at, strike_times, missed_runs FROM strikes;
+            DROP TABLE strikes;
+            ALTER TABLE strikes_new RENAME TO strikes;",
+        )
+        .context("Failed to drop last_strike_date")?;
+
+        Ok(())
+    }
+
+    /**
+     * Audit log of every action taken by a job, paths holds one path per line
+     */
+    fn create_actions(conn: &Connection) -> Result<(), anyhow::Error> {
+        conn.execute_batch(
+            "CREATE TABLE actions (
+                id INTEGER PRIMARY KEY,
+                created_at TEXT NOT NULL,
+                job VARCHAR(255) NOT NULL,
+                action VARCHAR(255) NOT NULL,
+                hash VARCHAR(255) NOT NULL,
+                name TEXT NOT NULL,
+                tracker VARCHAR(255) NOT NULL,
+                paths TEXT NOT NULL,
+                bytes_reclaimed INTEGER NOT NULL,
+                kept_for_cross_seeds INTEGER NOT NULL,
+                rule VARCHAR(255) NOT NULL,
+                success INTEGER NOT NULL,
+                error TEXT NOT NULL
+            );
+            CREATE INDEX actions_created_at ON actions (created_at);
+            CREATE INDEX actions_job ON actions (job, created_at);
+            CREATE INDEX actions_tracker ON actions (tracker, created_at);",
+        )
+        .context("Failed to create actions table")?;
+
+        Ok(())
+    }
+
+    /**
+     * History of job runs replacing the jobs table, which only had the start of the last run
+     * Those starts are kept as successful runs so the jobs don't all run again right after updating
+     */
+    fn create_job_runs(conn: &Connection) -> Result<(), anyhow::Error> {
+        conn.execute_batch(
+            "CREATE TABLE job_runs (
+                id INTEGER PRIMARY KEY,
+                job VARCHAR(255) NOT NULL,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                status VARCHAR(255) NOT NULL,
+                error TEXT NOT NULL,
+                torrents_scanned INTEGER NOT NULL,
+                candidates INTEGER NOT NULL,
+                strikes INTEGER NOT NULL,
+                actions INTEGER NOT NULL,
+                bytes_freed INTEGER NOT NULL
+            );
+            CREATE INDEX job_runs_job ON job_runs (job, started_at);
+            INSERT INTO job_runs (job, started_at, ended_at, status, error, torrents_scanned, candidates, strikes, actions, bytes_freed)
+                SELECT job_name, last_job_run, last_job_run, 'success', '', 0, 0, 0, 0, 0 FROM jobs;
+            DROP TABLE jobs;",
+        )
+        .context("Failed to create job_runs table")?;
+
+        Ok(())
+    }
+
+    /**
+     * Actions queued during quiet hours, same columns as actions without the result
+     */
+    fn create_deferred_actions(conn: &Connection) -> Result<(), anyhow::Error> {
+        conn.execute_batch(
+            "CREATE TABLE deferred_actions (
+                id INTEGER PRIMARY KEY,
+                created_at TEXT NOT NULL,
+                job VARCHAR(255) NOT NULL,
+                action VARCHAR(255) NOT NULL,
+                hash VARCHAR(255) NOT NULL,
+                name TEXT NOT NULL,
+                tracker VARCHAR(255) NOT NULL,
+                paths TEXT NOT NULL,
+                delete_files INTEGER NOT NULL,
+                bytes_reclaimed INTEGER NOT NULL,
+                kept_for_cross_seeds INTEGER NOT NULL,
+                rule VARCHAR(255) NOT NULL
+            );
+            CREATE INDEX deferred_actions_job ON deferred_actions (job);",
+        )
+        .context("Failed to create deferred_actions table")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+    use crate::jobs::enums::strike_type::StrikeType;
+
+    const HASH: &str = "0123456789abcdef";
+
+    fn get_conn() -> Connection {
+        DateUtils::set_time_settings(Some(Berlin), 0);
+        Connection::open_in_memory().unwrap()
+    }
+
+    #[test]
+    fn add_strike_policy_columns_converts_legacy_strikes() {
+        let conn = get_conn();
+        MIGRATIONS[0].up(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO strikes (strike_type, hash, strikes, strike_days, last_strike_date) VALUES (?1, ?2, 5, 3, '2024-05-10')",
+            params![StrikeType::HandleUnlinked.to_string(), HASH],
+        )
+        .unwrap();
+        MIGRATIONS[1].up(&conn).unwrap();
+
+        let (first_strike_at, last_strike_at, strike_times, missed_runs): (String, String, String, i32) = conn
+            .query_row("SELECT first_strike_at, last_strike_at, strike_times, missed_runs FROM strikes WHERE hash = ?1", params![HASH], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap();
+        assert_eq!(first_strike_at, "2024-05-08 00:00:00");
+        assert_eq!(last_strike_at, "2024-05-10 00:00:00");
+        assert_eq!(strike_times, "2024-05-08 00:00:00\n2024-05-09 00:00:00\n2024-05-10 00:00:00\n2024-05-10 00:00:00\n2024-05-10 00:00:00");
+        assert_eq!(missed_runs, 0);
+    }
+
+    #[test]
+    fn add_strike_policy_columns_skips_existing_columns() {
+        let conn = get_conn();
+        MIGRATIONS[0].up(&conn).unwrap();
+        MIGRATIONS[1].up(&conn).unwrap();
+        MIGRATIONS[1].up(&conn).unwrap();
+    }
+
+    #[test]
+    fn drop_last_strike_date_keeps_strikes() {
+        let conn = get_conn();
+        MIGRATIONS[0].up(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO strikes (strike_type, hash, strikes, strike_days, last_strike_date) VALUES (?1, ?2, 5, 3, '2024-05-10')",
+            params![StrikeType::HandleUnlinked.to_string(), HASH],
+        )
+        .unwrap();
+        for migration in MIGRATIONS.iter().skip(1) {
+            migration.up(&conn).unwrap();
+        }
+
+        let column_names: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('strikes')")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap();
+        assert!(!column_names.iter().any(|column_name| column_name == "last_strike_date"));
+        let (strikes, strike_days): (i32, i32) = conn.query_row("SELECT strikes, strike_days FROM strikes WHERE hash = ?1", params![HASH], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!((strikes, strike_days), (5, 3));
+    }
+
+    #[test]
+    fn v6_db_gets_deferred_actions() {
+        let conn = get_conn();
+        let get_table_count = |conn: &Connection| -> i32 { conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'deferred_actions'", [], |row| row.get(0)).unwrap() };
+        for migration in MIGRATIONS.iter().filter(|migration| migration.version() <= 6) {
+            migration.up(&conn).unwrap();
+        }
+        assert_eq!(get_table_count(&conn), 0);
+
+        for migration in MIGRATIONS.iter().filter(|migration| migration.version() > 6) {
+            migration.up(&conn).unwrap();
+        }
+        assert_eq!(get_table_count(&conn), 1);
+    }
+
+    #[test]
+    fn versions_are_unique_and_ascending() {
+        assert!(MIGRATIONS.windows(2).all(|migrations| migrations[0].version() < migrations[1].version()));
+    }
+}
@@ -0,0 +1,15 @@
+pub struct DomainUtils;
+
+impl DomainUtils {
+    /**
+     * True if domain is the configured domain or a subdomain of it (tracker.example.org matches example.org)
+     */
+    pub fn matches(domain: &str, configured_domain: &str) -> bool {
+        let configured_domain = configured_domain.trim().to_lowercase();
+        if configured_domain.is_empty() {
+            return false;
+        }
+        let domain = domain.to_lowercase();
+        domain == configured_domain || domain.ends_with(format!(".{}", configured_domain).as_str())
+    }
+}
@@ -1,3 +1,5 @@
 pub mod date_utils;
 pub mod db_manager;
+pub mod db_migrations;
 pub mod discord_webhook_utils;
+pub mod domain_utils;